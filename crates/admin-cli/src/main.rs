@@ -0,0 +1,224 @@
+use std::collections::BTreeMap;
+
+use async_nats::{
+    jetstream::consumer::{pull::OrderedConfig, DeliverPolicy},
+    Client,
+};
+use chrono::{DateTime, Utc};
+use clap::{Args, Parser, Subcommand};
+use futures::StreamExt;
+use time::OffsetDateTime;
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+
+use common::{
+    message_queue::{replay_reset_subject, replay_subject, MessageQueue},
+    Event,
+};
+
+#[derive(Parser)]
+#[command(name = "admin-cli", about = "Operational tooling for Hermes")]
+struct Cli {
+    #[arg(long, env = "NATS_URL", default_value = "nats://localhost:4222")]
+    nats_url: String,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Create the JetStream event stream if it does not exist
+    Setup,
+    /// Replay a range of the event stream into a named consumer
+    Replay(ReplayArgs),
+    /// Reset a consumer's derived state and replay the whole stream into it
+    Rebuild(RebuildArgs),
+}
+
+#[derive(Args)]
+struct ReplayArgs {
+    /// Consumer receiving events on `replay.<consumer>.<topic>`
+    #[arg(long)]
+    consumer: String,
+
+    /// First stream sequence to replay
+    #[arg(long, conflicts_with = "from_time")]
+    from_seq: Option<u64>,
+
+    /// Replay events published at or after this RFC 3339 timestamp
+    #[arg(long)]
+    from_time: Option<DateTime<Utc>>,
+
+    /// Last stream sequence to replay
+    #[arg(long, conflicts_with = "to_time")]
+    to_seq: Option<u64>,
+
+    /// Stop at events published after this RFC 3339 timestamp
+    #[arg(long)]
+    to_time: Option<DateTime<Utc>>,
+
+    /// Only replay matching subjects (wildcards allowed, repeatable)
+    #[arg(long = "subject")]
+    subjects: Vec<String>,
+
+    /// Count matching events without publishing them
+    #[arg(long)]
+    dry_run: bool,
+}
+
+#[derive(Args)]
+struct RebuildArgs {
+    /// Consumer whose derived state is rebuilt; `blocks` is the only one so far
+    #[arg(long)]
+    consumer: String,
+
+    /// Only replay matching subjects (wildcards allowed, repeatable)
+    #[arg(long = "subject")]
+    subjects: Vec<String>,
+
+    /// Count matching events without resetting or publishing
+    #[arg(long)]
+    dry_run: bool,
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    tracing_subscriber::registry()
+        .with(tracing_subscriber::EnvFilter::new(
+            std::env::var("RUST_LOG").unwrap_or_else(|_| "info".into()),
+        ))
+        .with(tracing_subscriber::fmt::layer())
+        .init();
+
+    dotenvy::dotenv().ok();
+
+    let cli = Cli::parse();
+    let client = async_nats::connect(&cli.nats_url).await?;
+    let mq = MessageQueue::new(client.clone());
+
+    match cli.command {
+        Command::Setup => {
+            let mut stream = mq.ensure_event_stream().await?;
+            let info = stream.info().await?;
+            tracing::info!(
+                "Stream {} ready with {} messages",
+                info.config.name,
+                info.state.messages
+            );
+        }
+        Command::Replay(args) => replay(&client, &mq, args).await?,
+        Command::Rebuild(args) => {
+            if !args.dry_run {
+                client
+                    .publish(replay_reset_subject(&args.consumer), Vec::new().into())
+                    .await?;
+                tracing::info!("Sent reset to consumer {}", args.consumer);
+            }
+
+            replay(
+                &client,
+                &mq,
+                ReplayArgs {
+                    consumer: args.consumer,
+                    from_seq: None,
+                    from_time: None,
+                    to_seq: None,
+                    to_time: None,
+                    subjects: args.subjects,
+                    dry_run: args.dry_run,
+                },
+            )
+            .await?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn replay(client: &Client, mq: &MessageQueue, args: ReplayArgs) -> anyhow::Result<()> {
+    let mut stream = mq.ensure_event_stream().await?;
+
+    // Bound the replay by what exists now so live traffic doesn't keep it running.
+    let last_sequence = stream.info().await?.state.last_sequence;
+    let end_sequence = args.to_seq.map_or(last_sequence, |seq| seq.min(last_sequence));
+    let end_time = args.to_time.map(to_offset_date_time).transpose()?;
+
+    let deliver_policy = match (args.from_seq, args.from_time) {
+        (Some(start_sequence), _) => DeliverPolicy::ByStartSequence { start_sequence },
+        (None, Some(start_time)) => DeliverPolicy::ByStartTime {
+            start_time: to_offset_date_time(start_time)?,
+        },
+        (None, None) => DeliverPolicy::All,
+    };
+
+    let consumer = stream
+        .create_consumer(OrderedConfig {
+            filter_subjects: args.subjects,
+            deliver_policy,
+            ..Default::default()
+        })
+        .await?;
+
+    let mut counts: BTreeMap<String, u64> = BTreeMap::new();
+    let mut skipped = 0u64;
+
+    if consumer.cached_info().num_pending > 0 {
+        let mut messages = consumer.messages().await?;
+
+        while let Some(message) = messages.next().await {
+            let message = message?;
+            let (sequence, published, pending) = {
+                let info = message.info().map_err(|e| anyhow::anyhow!(e.to_string()))?;
+                (info.stream_sequence, info.published, info.pending)
+            };
+
+            if sequence > end_sequence || end_time.is_some_and(|end| published > end) {
+                break;
+            }
+
+            if let Err(e) = serde_json::from_slice::<Event>(&message.payload) {
+                tracing::warn!("Skipping undecodable event at sequence {}: {}", sequence, e);
+                skipped += 1;
+            } else {
+                if !args.dry_run {
+                    // Keep the original trace context so replays link back to it.
+                    client
+                        .publish_with_headers(
+                            replay_subject(&args.consumer, &message.subject),
+                            message.headers.clone().unwrap_or_default(),
+                            message.payload.clone(),
+                        )
+                        .await?;
+                }
+                *counts.entry(message.subject.to_string()).or_default() += 1;
+            }
+
+            if sequence >= end_sequence || pending == 0 {
+                break;
+            }
+        }
+
+        client.flush().await?;
+    }
+
+    let total: u64 = counts.values().sum();
+    for (subject, count) in &counts {
+        tracing::info!("{:>8} {}", count, subject);
+    }
+    tracing::info!(
+        "{} {} events into {} ({} skipped)",
+        if args.dry_run { "Would replay" } else { "Replayed" },
+        total,
+        args.consumer,
+        skipped
+    );
+
+    Ok(())
+}
+
+fn to_offset_date_time(value: DateTime<Utc>) -> anyhow::Result<OffsetDateTime> {
+    let nanos = value
+        .timestamp_nanos_opt()
+        .ok_or_else(|| anyhow::anyhow!("timestamp out of range: {}", value))?;
+    Ok(OffsetDateTime::from_unix_timestamp_nanos(nanos as i128)?)
+}
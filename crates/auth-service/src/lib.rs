@@ -0,0 +1,148 @@
+use axum::{
+    Router,
+    extract::State,
+    http::StatusCode,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use utoipa::ToSchema;
+use utoipa_axum::routes;
+use uuid::Uuid;
+
+use common::{error::ErrorResponse, extract::Json, openapi, AppState, Config, Result, AppError};
+
+// Fields are consumed once the handlers below are implemented.
+#[allow(dead_code)]
+#[derive(Clone)]
+struct AuthState {
+    app_state: AppState,
+    jwt_secret: String,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Deserialize, ToSchema)]
+struct RegisterRequest {
+    username: String,
+    email: String,
+    password: String,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Deserialize, ToSchema)]
+struct LoginRequest {
+    email: String,
+    password: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct AuthResponse {
+    access_token: String,
+    refresh_token: String,
+    user: UserInfo,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct UserInfo {
+    id: Uuid,
+    username: String,
+    email: String,
+}
+
+pub fn router(config: &Config, app_state: AppState) -> Router {
+    let state = Arc::new(AuthState {
+        app_state,
+        jwt_secret: config.jwt_secret.clone(),
+    });
+
+    openapi::into_router(
+        openapi::service_router("auth-service")
+            .routes(routes!(register))
+            .routes(routes!(login))
+            .routes(routes!(refresh_token))
+            .routes(routes!(logout))
+            .with_state(state),
+    )
+}
+
+#[utoipa::path(
+    post,
+    path = "/register",
+    tag = "auth",
+    request_body = RegisterRequest,
+    responses(
+        (status = 200, body = AuthResponse),
+        (status = 400, body = ErrorResponse),
+        (status = 409, description = "Email or username taken", body = ErrorResponse),
+    )
+)]
+async fn register(
+    State(_state): State<Arc<AuthState>>,
+    Json(payload): Json<RegisterRequest>,
+) -> Result<Json<AuthResponse>> {
+    tracing::info!("Registering user: {}", payload.username);
+    
+    // TODO: Implement registration logic
+    // 1. Validate input
+    // 2. Hash password with Argon2
+    // 3. Insert user into database
+    // 4. Generate JWT tokens
+    // 5. Publish UserCreated event
+    
+    Err(AppError::InternalServerError("Not implemented".to_string()))
+}
+
+#[utoipa::path(
+    post,
+    path = "/login",
+    tag = "auth",
+    request_body = LoginRequest,
+    responses(
+        (status = 200, body = AuthResponse),
+        (status = 401, body = ErrorResponse),
+    )
+)]
+async fn login(
+    State(_state): State<Arc<AuthState>>,
+    Json(payload): Json<LoginRequest>,
+) -> Result<Json<AuthResponse>> {
+    tracing::info!("Login attempt for: {}", payload.email);
+    
+    // TODO: Implement login logic
+    // 1. Find user by email
+    // 2. Verify password with Argon2
+    // 3. Generate JWT tokens
+    // 4. Store refresh token in Redis
+    
+    Err(AppError::InternalServerError("Not implemented".to_string()))
+}
+
+#[utoipa::path(
+    post,
+    path = "/refresh",
+    tag = "auth",
+    responses(
+        (status = 200, body = AuthResponse),
+        (status = 401, body = ErrorResponse),
+    )
+)]
+async fn refresh_token(
+    State(_state): State<Arc<AuthState>>,
+) -> Result<Json<AuthResponse>> {
+    // TODO: Implement refresh token logic
+    Err(AppError::InternalServerError("Not implemented".to_string()))
+}
+
+#[utoipa::path(
+    post,
+    path = "/logout",
+    tag = "auth",
+    security(("bearer" = [])),
+    responses((status = 200, description = "Refresh token revoked"))
+)]
+async fn logout(
+    State(_state): State<Arc<AuthState>>,
+) -> Result<StatusCode> {
+    // TODO: Implement logout logic
+    // Invalidate refresh token in Redis
+    Ok(StatusCode::OK)
+}
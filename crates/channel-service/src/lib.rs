@@ -0,0 +1,137 @@
+use axum::{http::StatusCode, Router};
+use std::sync::Arc;
+use utoipa_axum::routes;
+use common::{auth, error::ErrorResponse, models::{Channel, Server, User}, openapi, AppState, Config};
+
+mod rpc;
+mod servers;
+
+pub fn router(config: &Config, app_state: AppState) -> Router {
+    let state = Arc::new(app_state);
+
+    let rpc_router = rpc::router(state.clone());
+    let nats = state.nats().cloned();
+    tokio::spawn(async move {
+        let served = match nats {
+            Ok(nats) => rpc_router.serve(nats, "channel-service").await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = served {
+            tracing::error!("RPC server stopped: {}", e);
+        }
+    });
+
+    openapi::into_router(
+        openapi::service_router("channel-service")
+            .routes(routes!(list_servers))
+            .routes(routes!(get_server))
+            .merge(servers::routes())
+            .routes(routes!(create_channel))
+            .routes(routes!(get_channel, update_channel, delete_channel))
+            .routes(routes!(get_members))
+            .routes(routes!(create_role))
+            .with_state(state),
+    )
+    .layer(auth::layer(&config.jwt_secret))
+}
+
+#[utoipa::path(
+    get,
+    path = "/servers",
+    tag = "servers",
+    security(("bearer" = [])),
+    responses(
+        (status = 200, body = Vec<Server>),
+    )
+)]
+async fn list_servers() -> StatusCode { StatusCode::NOT_IMPLEMENTED }
+
+#[utoipa::path(
+    get,
+    path = "/servers/{id}",
+    tag = "servers",
+    params(("id" = Uuid, Path, description = "Server id")),
+    security(("bearer" = [])),
+    responses(
+        (status = 200, body = Server),
+        (status = 404, body = ErrorResponse),
+    )
+)]
+async fn get_server() -> StatusCode { StatusCode::NOT_IMPLEMENTED }
+
+#[utoipa::path(
+    post,
+    path = "/servers/{id}/channels",
+    tag = "servers",
+    params(("id" = Uuid, Path, description = "Server id")),
+    security(("bearer" = [])),
+    responses(
+        (status = 201, body = Channel),
+        (status = 403, body = ErrorResponse),
+    )
+)]
+async fn create_channel() -> StatusCode { StatusCode::NOT_IMPLEMENTED }
+
+#[utoipa::path(
+    get,
+    path = "/channels/{id}",
+    tag = "servers",
+    params(("id" = Uuid, Path, description = "Channel id")),
+    security(("bearer" = [])),
+    responses(
+        (status = 200, body = Channel),
+        (status = 404, body = ErrorResponse),
+    )
+)]
+async fn get_channel() -> StatusCode { StatusCode::NOT_IMPLEMENTED }
+
+#[utoipa::path(
+    patch,
+    path = "/channels/{id}",
+    tag = "servers",
+    params(("id" = Uuid, Path, description = "Channel id")),
+    security(("bearer" = [])),
+    responses(
+        (status = 200, body = Channel),
+        (status = 403, body = ErrorResponse),
+    )
+)]
+async fn update_channel() -> StatusCode { StatusCode::NOT_IMPLEMENTED }
+
+#[utoipa::path(
+    delete,
+    path = "/channels/{id}",
+    tag = "servers",
+    params(("id" = Uuid, Path, description = "Channel id")),
+    security(("bearer" = [])),
+    responses(
+        (status = 204, description = "Channel deleted"),
+        (status = 403, body = ErrorResponse),
+    )
+)]
+async fn delete_channel() -> StatusCode { StatusCode::NOT_IMPLEMENTED }
+
+#[utoipa::path(
+    get,
+    path = "/servers/{id}/members",
+    tag = "servers",
+    params(("id" = Uuid, Path, description = "Server id")),
+    security(("bearer" = [])),
+    responses(
+        (status = 200, body = Vec<User>),
+    )
+)]
+async fn get_members() -> StatusCode { StatusCode::NOT_IMPLEMENTED }
+
+#[utoipa::path(
+    post,
+    path = "/servers/{id}/roles",
+    tag = "servers",
+    params(("id" = Uuid, Path, description = "Server id")),
+    security(("bearer" = [])),
+    responses(
+        (status = 201, description = "Role created"),
+        (status = 403, body = ErrorResponse),
+    )
+)]
+async fn create_role() -> StatusCode { StatusCode::NOT_IMPLEMENTED }
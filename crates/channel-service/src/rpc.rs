@@ -0,0 +1,72 @@
+use std::sync::Arc;
+
+use common::{
+    models::permissions,
+    rpc::{
+        channel::{
+            CheckMembership, CheckMembershipRequest, CheckMembershipResponse, CheckPermissions,
+            CheckPermissionsRequest, CheckPermissionsResponse,
+        },
+        RpcRouter,
+    },
+    AppError, AppState, Result,
+};
+use uuid::Uuid;
+
+pub fn router(state: Arc<AppState>) -> RpcRouter<Arc<AppState>> {
+    RpcRouter::new(state)
+        .route::<CheckMembership, _, _>(check_membership)
+        .route::<CheckPermissions, _, _>(check_permissions)
+}
+
+async fn check_membership(
+    state: Arc<AppState>,
+    request: CheckMembershipRequest,
+) -> Result<CheckMembershipResponse> {
+    let is_member: bool = sqlx::query_scalar(
+        "SELECT EXISTS(SELECT 1 FROM server_members WHERE server_id = $1 AND user_id = $2)",
+    )
+    .bind(request.server_id)
+    .bind(request.user_id)
+    .fetch_one(state.db()?)
+    .await?;
+
+    Ok(CheckMembershipResponse { is_member })
+}
+
+async fn check_permissions(
+    state: Arc<AppState>,
+    request: CheckPermissionsRequest,
+) -> Result<CheckPermissionsResponse> {
+    // @everyone applies to every member without a member_roles row.
+    let row: Option<(Uuid, bool, i64)> = sqlx::query_as(
+        r#"
+        SELECT s.owner_id,
+               EXISTS(SELECT 1 FROM server_members WHERE server_id = s.id AND user_id = $2),
+               COALESCE((
+                   SELECT BIT_OR(r.permissions)
+                   FROM roles r
+                   LEFT JOIN member_roles mr ON mr.role_id = r.id
+                   LEFT JOIN server_members sm ON sm.id = mr.member_id
+                   WHERE r.server_id = s.id AND (r.name = '@everyone' OR sm.user_id = $2)
+               ), 0)::BIGINT
+        FROM channels c
+        JOIN servers s ON s.id = c.server_id
+        WHERE c.id = $1
+        "#,
+    )
+    .bind(request.channel_id)
+    .bind(request.user_id)
+    .fetch_optional(state.db()?)
+    .await?;
+
+    let (owner_id, is_member, granted) =
+        row.ok_or_else(|| AppError::NotFound("Channel not found".to_string()))?;
+
+    let allowed = owner_id == request.user_id
+        || (is_member
+            && (granted & permissions::ADMINISTRATOR != 0
+                || granted & request.permissions == request.permissions));
+
+    Ok(CheckPermissionsResponse { allowed })
+}
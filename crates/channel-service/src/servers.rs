@@ -0,0 +1,301 @@
+//! Creating, updating and deleting servers.
+//!
+//! A new server starts with an `@everyone` role, a `general` text channel,
+//! a `General` voice channel and its owner as the only member, all created
+//! in one transaction. Deleting a server removes its members, roles,
+//! channels and their messages through `ON DELETE CASCADE`.
+//!
+//! Handing a server to another member needs the owner's password as well as
+//! their session, so a stolen token alone cannot take a server away. Hermes
+//! has no two-factor authentication yet, so re-entering the password stands
+//! in for the 2FA confirmation ownership transfers should eventually ask for.
+//! Failed confirmations are rate limited per user so the endpoint cannot be
+//! used to guess the password.
+
+use std::{sync::Arc, time::Duration};
+
+use axum::{extract::State, http::StatusCode};
+use chrono::Utc;
+use serde::Deserialize;
+use utoipa::ToSchema;
+use utoipa_axum::{router::OpenApiRouter, routes};
+use uuid::Uuid;
+use validator::Validate;
+
+use common::{
+    auth::AuthUser,
+    db::{self, roles},
+    error::ErrorResponse,
+    events::{ServerDeletedEvent, ServerEvent},
+    extract::{Json, Path},
+    models::{permissions, ChannelType, Server},
+    password, AppError, AppState, Event, Result,
+};
+
+/// Failed password confirmations allowed per user within [`CONFIRMATION_WINDOW`].
+const MAX_FAILED_CONFIRMATIONS: u64 = 5;
+const CONFIRMATION_WINDOW: Duration = Duration::from_secs(15 * 60);
+
+pub(crate) fn routes() -> OpenApiRouter<Arc<AppState>> {
+    OpenApiRouter::new()
+        .routes(routes!(create_server))
+        .routes(routes!(update_server, delete_server))
+}
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct CreateServerRequest {
+    #[validate(length(min = 1, max = 100))]
+    pub name: String,
+    #[validate(url, length(max = 2048))]
+    pub icon_url: Option<String>,
+}
+
+/// Omitted fields are left unchanged.
+#[derive(Debug, Default, Deserialize, Validate, ToSchema)]
+pub struct UpdateServerRequest {
+    #[validate(length(min = 1, max = 100))]
+    pub name: Option<String>,
+    #[validate(url, length(max = 2048))]
+    pub icon_url: Option<String>,
+    /// Hands the server to another member. Only the owner may set it.
+    pub owner_id: Option<Uuid>,
+    /// The owner's current password; required with `owner_id`. Stands in
+    /// for a 2FA code until two-factor authentication exists.
+    pub password: Option<String>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/servers",
+    tag = "servers",
+    request_body = CreateServerRequest,
+    security(("bearer" = [])),
+    responses(
+        (status = 201, body = Server),
+        (status = 400, body = ErrorResponse),
+    )
+)]
+async fn create_server(
+    State(state): State<Arc<AppState>>,
+    user: AuthUser,
+    Json(payload): Json<CreateServerRequest>,
+) -> Result<(StatusCode, Json<Server>)> {
+    payload.validate()?;
+
+    let mut tx = state.db()?.begin().await?;
+    let server = db::servers::create(&mut *tx, &payload.name, payload.icon_url.as_deref(), user.id).await?;
+    roles::create(&mut *tx, server.id, roles::EVERYONE, permissions::EVERYONE_DEFAULT).await?;
+    db::channels::create(&mut *tx, server.id, "general", ChannelType::Text, None).await?;
+    db::channels::create(&mut *tx, server.id, "General", ChannelType::Voice, None).await?;
+    db::servers::add_member(&mut *tx, server.id, user.id).await?;
+    tx.commit().await?;
+
+    state.publish_best_effort(&Event::ServerCreated(server_event(&server))).await;
+    Ok((StatusCode::CREATED, Json(server)))
+}
+
+#[utoipa::path(
+    patch,
+    path = "/servers/{id}",
+    tag = "servers",
+    params(("id" = Uuid, Path, description = "Server id")),
+    request_body = UpdateServerRequest,
+    security(("bearer" = [])),
+    responses(
+        (status = 200, body = Server),
+        (status = 400, description = "Invalid, no password given, or the new owner is not a member", body = ErrorResponse),
+        (status = 403, description = "Missing permissions, or an incorrect password", body = ErrorResponse),
+        (status = 404, body = ErrorResponse),
+        (status = 429, description = "Too many incorrect passwords", body = ErrorResponse),
+    )
+)]
+async fn update_server(
+    State(state): State<Arc<AppState>>,
+    user: AuthUser,
+    Path(server_id): Path<Uuid>,
+    Json(payload): Json<UpdateServerRequest>,
+) -> Result<Json<Server>> {
+    payload.validate()?;
+    let db = state.db()?;
+    let mut server = require_permission(&state, server_id, user.id, permissions::MANAGE_SERVER).await?;
+    let transfer = transfer_target(&server, user.id, &payload)?;
+    if let Some((_, password)) = transfer {
+        confirm_password(&state, user.id, password.to_string()).await?;
+    }
+
+    let mut changed = false;
+    let mut tx = db.begin().await?;
+    if payload.name.is_some() || payload.icon_url.is_some() {
+        server = db::servers::update(&mut *tx, server_id, payload.name.as_deref(), payload.icon_url.as_deref())
+            .await?
+            .ok_or_else(not_found)?;
+        changed = true;
+    }
+    if let Some((new_owner_id, _)) = transfer {
+        server = db::servers::transfer_ownership(&mut *tx, server_id, user.id, new_owner_id)
+            .await?
+            .ok_or_else(|| AppError::BadRequest("The new owner must be a member of the server".to_string()))?;
+        changed = true;
+    }
+    tx.commit().await?;
+
+    if changed {
+        state.publish_best_effort(&Event::ServerUpdated(server_event(&server))).await;
+    }
+    Ok(Json(server))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/servers/{id}",
+    tag = "servers",
+    params(("id" = Uuid, Path, description = "Server id")),
+    security(("bearer" = [])),
+    responses(
+        (status = 204, description = "Server deleted"),
+        (status = 403, description = "Only the owner can delete a server", body = ErrorResponse),
+        (status = 404, body = ErrorResponse),
+    )
+)]
+async fn delete_server(
+    State(state): State<Arc<AppState>>,
+    user: AuthUser,
+    Path(server_id): Path<Uuid>,
+) -> Result<StatusCode> {
+    let server = require_permission(&state, server_id, user.id, 0).await?;
+    if server.owner_id != user.id {
+        return Err(AppError::Forbidden("Only the owner can delete a server".to_string()));
+    }
+    if !db::servers::delete(state.db()?, server_id).await? {
+        return Err(not_found());
+    }
+
+    let event = Event::ServerDeleted(ServerDeletedEvent {
+        server_id,
+        timestamp: Utc::now(),
+    });
+    state.publish_best_effort(&event).await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// The server, if `user_id` is its owner or a member holding `permission`
+/// (or `ADMINISTRATOR`). Non-members get 404, so servers they cannot see
+/// stay hidden.
+async fn require_permission(state: &AppState, server_id: Uuid, user_id: Uuid, permission: i64) -> Result<Server> {
+    let db = state.db()?;
+    let server = db::servers::find_by_id(db, server_id).await?.ok_or_else(not_found)?;
+    if server.owner_id == user_id {
+        return Ok(server);
+    }
+    let granted = db::servers::member_permissions(db, server_id, user_id)
+        .await?
+        .ok_or_else(not_found)?;
+    if granted & permissions::ADMINISTRATOR == 0 && granted & permission != permission {
+        return Err(AppError::Forbidden("Missing permissions".to_string()));
+    }
+    Ok(server)
+}
+
+/// The member `request` hands the server to and the password it was given,
+/// if it asks for a transfer. Only the owner may make one, and only with a
+/// password, which the caller still has to check.
+fn transfer_target<'r>(
+    server: &Server,
+    caller_id: Uuid,
+    request: &'r UpdateServerRequest,
+) -> Result<Option<(Uuid, &'r str)>> {
+    let Some(new_owner_id) = request.owner_id.filter(|&id| id != server.owner_id) else {
+        return Ok(None);
+    };
+    if server.owner_id != caller_id {
+        return Err(AppError::Forbidden("Only the owner can transfer the server".to_string()));
+    }
+    let password = request
+        .password
+        .as_deref()
+        .ok_or_else(|| AppError::BadRequest("Transferring the server requires your password".to_string()))?;
+    Ok(Some((new_owner_id, password)))
+}
+
+/// Checks the caller's password, refusing further attempts once
+/// [`MAX_FAILED_CONFIRMATIONS`] have failed within the window.
+async fn confirm_password(state: &AppState, user_id: Uuid, password: String) -> Result<()> {
+    let cache = state.cache().await?;
+    let key = format!("password_confirm_failures:{}", user_id);
+    let failures = cache.get(&key).await?.and_then(|count| count.parse::<u64>().ok()).unwrap_or(0);
+    if failures >= MAX_FAILED_CONFIRMATIONS {
+        return Err(too_many_attempts());
+    }
+
+    let hash = db::users::password_hash(state.db()?, user_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+    // Argon2 is deliberately slow; keep it off the async workers.
+    let verified = tokio::task::spawn_blocking(move || password::verify(&password, &hash))
+        .await
+        .map_err(|e| AppError::InternalServerError(format!("Password check panicked: {}", e)))?;
+    if !verified {
+        let limit = cache.rate_limit(&key, MAX_FAILED_CONFIRMATIONS, CONFIRMATION_WINDOW).await?;
+        if !limit.allowed {
+            return Err(too_many_attempts());
+        }
+        return Err(AppError::Forbidden("Incorrect password".to_string()));
+    }
+    Ok(())
+}
+
+fn too_many_attempts() -> AppError {
+    AppError::TooManyRequests("Too many incorrect passwords; try again later".to_string())
+}
+
+fn server_event(server: &Server) -> ServerEvent {
+    ServerEvent {
+        server_id: server.id,
+        name: server.name.clone(),
+        owner_id: server.owner_id,
+        timestamp: Utc::now(),
+    }
+}
+
+fn not_found() -> AppError {
+    AppError::NotFound("Server not found".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn server(owner_id: Uuid) -> Server {
+        Server {
+            id: Uuid::new_v4(),
+            name: "Guild".to_string(),
+            icon_url: None,
+            owner_id,
+            created_at: Utc::now(),
+        }
+    }
+
+    fn transfer(owner_id: Uuid, password: Option<&str>) -> UpdateServerRequest {
+        UpdateServerRequest {
+            owner_id: Some(owner_id),
+            password: password.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_transfer_target() {
+        let (owner, member) = (Uuid::new_v4(), Uuid::new_v4());
+        let server = server(owner);
+
+        let request = transfer(member, Some("hunter22"));
+        assert_eq!(transfer_target(&server, owner, &request).unwrap(), Some((member, "hunter22")));
+        // Naming the current owner, or no one, is not a transfer.
+        assert_eq!(transfer_target(&server, owner, &transfer(owner, None)).unwrap(), None);
+        assert_eq!(transfer_target(&server, member, &UpdateServerRequest::default()).unwrap(), None);
+
+        let without_password = transfer(member, None);
+        assert!(matches!(transfer_target(&server, owner, &without_password), Err(AppError::BadRequest(_))));
+        assert!(matches!(transfer_target(&server, member, &request), Err(AppError::Forbidden(_))));
+    }
+}
@@ -0,0 +1,39 @@
+use std::time::Duration;
+
+use common::rpc::{
+    channel::{CheckMembership, CheckMembershipRequest},
+    RpcClient,
+};
+use test_support::{fixtures, TestStack};
+
+#[tokio::test]
+#[ignore = "requires Docker"]
+async fn test_check_membership_over_nats() -> anyhow::Result<()> {
+    let stack = TestStack::start().await?;
+    let _service = stack.spawn(channel_service::router).await?;
+
+    let alice = fixtures::create_user(&stack, "alice").await?;
+    let bob = fixtures::create_user(&stack, "bob").await?;
+    let guild = fixtures::create_server(&stack, &alice, "Test Server").await?;
+
+    let client = RpcClient::new(stack.state.nats()?.clone()).with_timeout(Duration::from_secs(5));
+    // The RPC subscription is set up in a spawned task; give it a moment.
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    let owner = client
+        .call::<CheckMembership>(&CheckMembershipRequest { server_id: guild.id(), user_id: alice.id() })
+        .await?;
+    assert!(owner.is_member);
+
+    let outsider = client
+        .call::<CheckMembership>(&CheckMembershipRequest { server_id: guild.id(), user_id: bob.id() })
+        .await?;
+    assert!(!outsider.is_member);
+
+    fixtures::add_member(&stack, &guild, &bob).await?;
+    let joined = client
+        .call::<CheckMembership>(&CheckMembershipRequest { server_id: guild.id(), user_id: bob.id() })
+        .await?;
+    assert!(joined.is_member);
+    Ok(())
+}
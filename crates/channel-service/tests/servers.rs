@@ -0,0 +1,134 @@
+use reqwest::{Client, StatusCode};
+use serde_json::{json, Value};
+use test_support::{fixtures, TestStack};
+
+const PASSWORD: &str = "correct horse battery staple";
+
+#[tokio::test]
+#[ignore = "requires Docker"]
+async fn test_server_lifecycle() -> anyhow::Result<()> {
+    let stack = TestStack::start().await?;
+    let service = stack.spawn(channel_service::router).await?;
+    let http = Client::new();
+
+    let alice = fixtures::create_user_with_password(&stack, "alice", PASSWORD).await?;
+    let bob = fixtures::create_user(&stack, "bob").await?;
+
+    let created = http
+        .post(service.url("/servers"))
+        .header("authorization", alice.bearer())
+        .json(&json!({ "name": "Guild" }))
+        .send()
+        .await?;
+    assert_eq!(created.status(), StatusCode::CREATED);
+    let server: Value = created.json().await?;
+    let server_url = service.url(&format!("/servers/{}", server["id"].as_str().unwrap_or_default()));
+
+    let channels: Vec<(String, String)> =
+        sqlx::query_as("SELECT name, type FROM channels WHERE server_id = $1::uuid ORDER BY type")
+            .bind(server["id"].as_str())
+            .fetch_all(stack.state.db()?)
+            .await?;
+    assert_eq!(channels, [("general".into(), "text".into()), ("General".into(), "voice".into())]);
+
+    let outsider = http.delete(&server_url).header("authorization", bob.bearer()).send().await?;
+    assert_eq!(outsider.status(), StatusCode::NOT_FOUND);
+    let not_member = http
+        .patch(&server_url)
+        .header("authorization", alice.bearer())
+        .json(&json!({ "owner_id": bob.id(), "password": PASSWORD }))
+        .send()
+        .await?;
+    assert_eq!(not_member.status(), StatusCode::BAD_REQUEST);
+
+    let renamed: Value = http
+        .patch(&server_url)
+        .header("authorization", alice.bearer())
+        .json(&json!({ "name": "Renamed" }))
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    assert_eq!(renamed["name"], "Renamed");
+
+    let deleted = http.delete(&server_url).header("authorization", alice.bearer()).send().await?;
+    assert_eq!(deleted.status(), StatusCode::NO_CONTENT);
+    let remaining: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM channels WHERE server_id = $1::uuid")
+        .bind(server["id"].as_str())
+        .fetch_one(stack.state.db()?)
+        .await?;
+    assert_eq!(remaining, 0);
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore = "requires Docker"]
+async fn test_ownership_transfer() -> anyhow::Result<()> {
+    let stack = TestStack::start().await?;
+    let service = stack.spawn(channel_service::router).await?;
+    let http = Client::new();
+
+    let alice = fixtures::create_user_with_password(&stack, "alice", PASSWORD).await?;
+    let bob = fixtures::create_user(&stack, "bob").await?;
+    let guild = fixtures::create_server(&stack, &alice, "Guild").await?;
+    fixtures::add_member(&stack, &guild, &bob).await?;
+    let server_url = service.url(&format!("/servers/{}", guild.id()));
+
+    for (body, status) in [
+        (json!({ "owner_id": bob.id() }), StatusCode::BAD_REQUEST),
+        (json!({ "owner_id": bob.id(), "password": "wrong" }), StatusCode::FORBIDDEN),
+    ] {
+        let refused = http.patch(&server_url).header("authorization", alice.bearer()).json(&body).send().await?;
+        assert_eq!(refused.status(), status);
+    }
+
+    let transferred: Value = http
+        .patch(&server_url)
+        .header("authorization", alice.bearer())
+        .json(&json!({ "owner_id": bob.id(), "password": PASSWORD }))
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    assert_eq!(transferred["owner_id"], json!(bob.id()));
+
+    // alice is now an ordinary member, and bob the one who can delete.
+    let by_alice = http.delete(&server_url).header("authorization", alice.bearer()).send().await?;
+    assert_eq!(by_alice.status(), StatusCode::FORBIDDEN);
+    let by_bob = http.delete(&server_url).header("authorization", bob.bearer()).send().await?;
+    assert_eq!(by_bob.status(), StatusCode::NO_CONTENT);
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore = "requires Docker"]
+async fn test_transfer_password_attempts_are_limited() -> anyhow::Result<()> {
+    let stack = TestStack::start().await?;
+    let service = stack.spawn(channel_service::router).await?;
+    let http = Client::new();
+
+    let alice = fixtures::create_user_with_password(&stack, "alice", PASSWORD).await?;
+    let bob = fixtures::create_user(&stack, "bob").await?;
+    let guild = fixtures::create_server(&stack, &alice, "Guild").await?;
+    fixtures::add_member(&stack, &guild, &bob).await?;
+    let server_url = service.url(&format!("/servers/{}", guild.id()));
+    let transfer = |password: &str| {
+        http.patch(&server_url)
+            .header("authorization", alice.bearer())
+            .json(&json!({ "owner_id": bob.id(), "password": password }))
+            .send()
+    };
+
+    for attempt in 0..5 {
+        let refused = transfer(&format!("guess {}", attempt)).await?;
+        assert_eq!(refused.status(), StatusCode::FORBIDDEN);
+    }
+    // Locked out for the window, even with the right password.
+    let locked = transfer(PASSWORD).await?;
+    assert_eq!(locked.status(), StatusCode::TOO_MANY_REQUESTS);
+    let body: Value = locked.json().await?;
+    assert_eq!(body["error"]["code"], "rate_limited");
+    Ok(())
+}
@@ -0,0 +1,160 @@
+//! One-to-one direct messages.
+//!
+//! A block in either direction, or the recipient's `dm_privacy` setting,
+//! stops a user opening a conversation or sending into an existing one;
+//! history stays readable. Group DMs do not exist yet; adding someone to
+//! one should refuse members returned by
+//! [`BlockList::blocked_among`](common::blocks::BlockList::blocked_among).
+
+use std::sync::Arc;
+
+use axum::{extract::State, http::StatusCode};
+use serde::Deserialize;
+use utoipa::ToSchema;
+use utoipa_axum::{router::OpenApiRouter, routes};
+use uuid::Uuid;
+
+use common::{
+    auth::AuthUser,
+    db,
+    error::ErrorResponse,
+    extract::{Json, Path, Query},
+    models::{DirectMessage, Message},
+    settings::DmPrivacy,
+    AppError, Result,
+};
+
+use crate::{publish_created, validate_content, ChatState, CreateMessageRequest, HistoryQuery};
+
+pub(crate) fn routes() -> OpenApiRouter<Arc<ChatState>> {
+    OpenApiRouter::new()
+        .routes(routes!(open_dm))
+        .routes(routes!(get_dm_messages, send_dm_message))
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct OpenDmRequest {
+    pub recipient_id: Uuid,
+}
+
+#[utoipa::path(
+    post,
+    path = "/users/@me/dms",
+    tag = "dms",
+    request_body = OpenDmRequest,
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "The existing or new conversation", body = DirectMessage),
+        (status = 400, body = ErrorResponse),
+        (status = 403, description = "Blocked, or refused by the recipient's DM privacy", body = ErrorResponse),
+        (status = 404, body = ErrorResponse),
+    )
+)]
+async fn open_dm(
+    State(state): State<Arc<ChatState>>,
+    user: AuthUser,
+    Json(payload): Json<OpenDmRequest>,
+) -> Result<Json<DirectMessage>> {
+    let recipient_id = payload.recipient_id;
+    if recipient_id == user.id {
+        return Err(AppError::BadRequest("You cannot message yourself".to_string()));
+    }
+    let db = state.app_state.db()?;
+    if db::users::find_by_id(db, recipient_id).await?.is_none() {
+        return Err(AppError::NotFound("User not found".to_string()));
+    }
+    ensure_can_message(&state, user.id, recipient_id).await?;
+    Ok(Json(db::direct_messages::find_or_create(db, user.id, recipient_id).await?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/dms/{id}/messages",
+    tag = "dms",
+    params(
+        ("id" = Uuid, Path, description = "Conversation id"),
+        ("before" = Option<Uuid>, Query, description = "Newest first, older than this message id"),
+        ("after" = Option<Uuid>, Query, description = "Oldest first, newer than this message id"),
+        ("limit" = Option<i64>, Query, description = "Page size, at most 100"),
+    ),
+    security(("bearer" = [])),
+    responses(
+        (status = 200, body = Vec<Message>),
+        (status = 400, description = "Unknown cursor", body = ErrorResponse),
+        (status = 404, body = ErrorResponse),
+    )
+)]
+async fn get_dm_messages(
+    State(state): State<Arc<ChatState>>,
+    user: AuthUser,
+    Path(dm_id): Path<Uuid>,
+    Query(query): Query<HistoryQuery>,
+) -> Result<Json<Vec<Message>>> {
+    let db = state.app_state.db()?;
+    let dm = find_for(&state, dm_id, user.id).await?;
+    query.check_cursor(db).await?;
+    let messages = match query.after {
+        Some(after) => db::messages::list_after_in_dm(db, dm.id, after, query.limit()).await?,
+        None => db::messages::list_for_dm(db, dm.id, query.before, query.limit()).await?,
+    };
+    Ok(Json(messages))
+}
+
+#[utoipa::path(
+    post,
+    path = "/dms/{id}/messages",
+    tag = "dms",
+    params(("id" = Uuid, Path, description = "Conversation id")),
+    request_body = CreateMessageRequest,
+    security(("bearer" = [])),
+    responses(
+        (status = 201, body = Message),
+        (status = 400, body = ErrorResponse),
+        (status = 403, description = "Blocked, or refused by the recipient's DM privacy", body = ErrorResponse),
+        (status = 404, body = ErrorResponse),
+    )
+)]
+async fn send_dm_message(
+    State(state): State<Arc<ChatState>>,
+    user: AuthUser,
+    Path(dm_id): Path<Uuid>,
+    Json(payload): Json<CreateMessageRequest>,
+) -> Result<(StatusCode, Json<Message>)> {
+    let content = validate_content(&payload.content)?;
+    let dm = find_for(&state, dm_id, user.id).await?;
+    let recipient_id = dm.other(user.id);
+    ensure_can_message(&state, user.id, recipient_id).await?;
+
+    let message = db::messages::create_in_dm(state.app_state.db()?, dm.id, user.id, content).await?;
+    publish_created(&state.app_state, &message, vec![recipient_id]).await;
+    Ok((StatusCode::CREATED, Json(message)))
+}
+
+async fn ensure_can_message(state: &ChatState, sender_id: Uuid, recipient_id: Uuid) -> Result<()> {
+    let refused = || Err(AppError::Forbidden("You cannot message this user".to_string()));
+    if state.blocks.either_blocked(sender_id, recipient_id).await? {
+        return refused();
+    }
+    let db = state.app_state.db()?;
+    let (settings, _) = db::settings::find(db, recipient_id).await?;
+    let allowed = match settings.dm_privacy {
+        DmPrivacy::Everyone => true,
+        DmPrivacy::ServerMembers => {
+            db::friendships::relationship(db, sender_id, recipient_id).await?.is_friends()
+                || db::servers::share_any(db, sender_id, recipient_id).await?
+        }
+        DmPrivacy::FriendsOnly => db::friendships::relationship(db, sender_id, recipient_id).await?.is_friends(),
+    };
+    if !allowed {
+        return refused();
+    }
+    Ok(())
+}
+
+/// The conversation, if `user_id` takes part in it.
+async fn find_for(state: &ChatState, dm_id: Uuid, user_id: Uuid) -> Result<DirectMessage> {
+    db::direct_messages::find_by_id(state.app_state.db()?, dm_id)
+        .await?
+        .filter(|dm| dm.includes(user_id))
+        .ok_or_else(|| AppError::NotFound("Conversation not found".to_string()))
+}
@@ -0,0 +1,300 @@
+use axum::{extract::State, http::StatusCode, Router};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::sync::Arc;
+use utoipa::ToSchema;
+use utoipa_axum::routes;
+use uuid::Uuid;
+
+use common::{
+    auth::{self, AuthUser},
+    blocks::BlockList,
+    db,
+    error::ErrorResponse,
+    events::MessageEvent,
+    extract::{Json, Path, Query},
+    metrics,
+    models::{permissions, Message},
+    openapi,
+    rpc::{
+        channel::{CheckPermissions, CheckPermissionsRequest},
+        RpcClient,
+    },
+    AppError, AppState, Config, Event, Result,
+};
+
+mod dms;
+
+const MAX_CONTENT_LENGTH: usize = 2000;
+const DEFAULT_PAGE_SIZE: i64 = 50;
+const MAX_PAGE_SIZE: i64 = 100;
+
+struct ChatState {
+    app_state: AppState,
+    blocks: BlockList,
+}
+
+impl ChatState {
+    fn rpc(&self) -> Result<RpcClient> {
+        Ok(RpcClient::new(self.app_state.nats()?.clone()))
+    }
+}
+
+pub fn router(config: &Config, app_state: AppState) -> Router {
+    metrics::describe_counter!("messages_sent_total", "Messages accepted for delivery");
+
+    let state = Arc::new(ChatState {
+        blocks: BlockList::new(app_state.clone()),
+        app_state,
+    });
+
+    openapi::into_router(
+        openapi::service_router("chat-service")
+            .routes(routes!(get_messages, send_message))
+            .routes(routes!(edit_message, delete_message))
+            .routes(routes!(add_reaction, remove_reaction))
+            .merge(dms::routes())
+            .with_state(state),
+    )
+    .layer(auth::layer(&config.jwt_secret))
+}
+
+#[derive(Debug, Deserialize)]
+struct HistoryQuery {
+    before: Option<Uuid>,
+    after: Option<Uuid>,
+    limit: Option<i64>,
+}
+
+impl HistoryQuery {
+    fn limit(&self) -> i64 {
+        self.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE)
+    }
+
+    /// Rejects a cursor that cannot be placed in history, which would
+    /// otherwise silently return an empty page.
+    async fn check_cursor(&self, db: &PgPool) -> Result<()> {
+        for cursor in [self.before, self.after].into_iter().flatten() {
+            if !db::messages::cursor_is_known(db, cursor).await? {
+                return Err(AppError::BadRequest(format!("Unknown message cursor {}", cursor)));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateMessageRequest {
+    pub content: String,
+}
+
+/// A message as seen by the requesting user.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct MessageView {
+    #[serde(flatten)]
+    pub message: Message,
+    /// The viewer has blocked the author; clients collapse the message.
+    pub author_blocked: bool,
+}
+
+#[utoipa::path(
+    get,
+    path = "/channels/{id}/messages",
+    tag = "messages",
+    params(
+        ("id" = Uuid, Path, description = "Channel id"),
+        ("before" = Option<Uuid>, Query, description = "Newest first, older than this message id"),
+        ("after" = Option<Uuid>, Query, description = "Oldest first, newer than this message id"),
+        ("limit" = Option<i64>, Query, description = "Page size, at most 100"),
+    ),
+    security(("bearer" = [])),
+    responses(
+        (status = 200, body = Vec<MessageView>),
+        (status = 400, description = "Unknown cursor", body = ErrorResponse),
+        (status = 403, body = ErrorResponse),
+    )
+)]
+async fn get_messages(
+    State(state): State<Arc<ChatState>>,
+    user: AuthUser,
+    Path(channel_id): Path<Uuid>,
+    Query(query): Query<HistoryQuery>,
+) -> Result<Json<Vec<MessageView>>> {
+    require_permission(&state, channel_id, user.id, 0).await?;
+
+    let db = state.app_state.db()?;
+    query.check_cursor(db).await?;
+    let messages = match query.after {
+        Some(after) => db::messages::list_after(db, channel_id, after, query.limit()).await?,
+        None => db::messages::list_for_channel(db, channel_id, query.before, query.limit()).await?,
+    };
+    let blocked = state.blocks.blocked_by(user.id).await?;
+    let views = messages
+        .into_iter()
+        .map(|message| MessageView {
+            author_blocked: blocked.contains(&message.author_id),
+            message,
+        })
+        .collect();
+    Ok(Json(views))
+}
+
+#[utoipa::path(
+    post,
+    path = "/channels/{id}/messages",
+    tag = "messages",
+    params(("id" = Uuid, Path, description = "Channel id")),
+    request_body = CreateMessageRequest,
+    security(("bearer" = [])),
+    responses(
+        (status = 201, body = Message),
+        (status = 400, body = ErrorResponse),
+        (status = 403, body = ErrorResponse),
+    )
+)]
+async fn send_message(
+    State(state): State<Arc<ChatState>>,
+    user: AuthUser,
+    Path(channel_id): Path<Uuid>,
+    Json(payload): Json<CreateMessageRequest>,
+) -> Result<(StatusCode, Json<Message>)> {
+    let content = validate_content(&payload.content)?;
+    require_permission(&state, channel_id, user.id, permissions::SEND_MESSAGES).await?;
+
+    let message = db::messages::create(state.app_state.db()?, channel_id, user.id, content).await?;
+    let mentions = deliverable_mentions(&state.blocks, user.id, &parse_mentions(content)).await?;
+    publish_created(&state.app_state, &message, mentions).await;
+    Ok((StatusCode::CREATED, Json(message)))
+}
+
+#[utoipa::path(
+    patch,
+    path = "/messages/{id}",
+    tag = "messages",
+    params(("id" = Uuid, Path, description = "Message id")),
+    security(("bearer" = [])),
+    responses(
+        (status = 200, body = Message),
+        (status = 403, body = ErrorResponse),
+    )
+)]
+async fn edit_message() -> StatusCode { StatusCode::NOT_IMPLEMENTED }
+
+#[utoipa::path(
+    delete,
+    path = "/messages/{id}",
+    tag = "messages",
+    params(("id" = Uuid, Path, description = "Message id")),
+    security(("bearer" = [])),
+    responses(
+        (status = 204, description = "Message deleted"),
+        (status = 403, body = ErrorResponse),
+    )
+)]
+async fn delete_message() -> StatusCode { StatusCode::NOT_IMPLEMENTED }
+
+#[utoipa::path(
+    post,
+    path = "/messages/{id}/reactions/{emoji}",
+    tag = "messages",
+    params(("id" = Uuid, Path, description = "Message id"), ("emoji" = String, Path, description = "Unicode emoji or custom emoji id")),
+    security(("bearer" = [])),
+    responses(
+        (status = 204, description = "Reaction added"),
+    )
+)]
+async fn add_reaction() -> StatusCode { StatusCode::NOT_IMPLEMENTED }
+
+#[utoipa::path(
+    delete,
+    path = "/messages/{id}/reactions/{emoji}",
+    tag = "messages",
+    params(("id" = Uuid, Path, description = "Message id"), ("emoji" = String, Path, description = "Unicode emoji or custom emoji id")),
+    security(("bearer" = [])),
+    responses(
+        (status = 204, description = "Reaction removed"),
+    )
+)]
+async fn remove_reaction() -> StatusCode { StatusCode::NOT_IMPLEMENTED }
+
+async fn require_permission(state: &ChatState, channel_id: Uuid, user_id: Uuid, permissions: i64) -> Result<()> {
+    let response = state
+        .rpc()?
+        .call::<CheckPermissions>(&CheckPermissionsRequest {
+            channel_id,
+            user_id,
+            permissions,
+        })
+        .await?;
+    if !response.allowed {
+        return Err(AppError::Forbidden("Missing permissions for this channel".to_string()));
+    }
+    Ok(())
+}
+
+fn validate_content(content: &str) -> Result<&str> {
+    let content = content.trim();
+    if content.is_empty() {
+        return Err(AppError::BadRequest("Message content cannot be empty".to_string()));
+    }
+    if content.chars().count() > MAX_CONTENT_LENGTH {
+        return Err(AppError::BadRequest(format!(
+            "Message content cannot exceed {} characters",
+            MAX_CONTENT_LENGTH
+        )));
+    }
+    Ok(content)
+}
+
+/// User ids mentioned as `<@id>`, in order of first appearance.
+fn parse_mentions(content: &str) -> Vec<Uuid> {
+    let mut mentions = Vec::new();
+    for part in content.split("<@").skip(1) {
+        let Some((id, _)) = part.split_once('>') else { continue };
+        if let Ok(id) = Uuid::parse_str(id) {
+            if !mentions.contains(&id) {
+                mentions.push(id);
+            }
+        }
+    }
+    mentions
+}
+
+/// Drops mentions of users who have blocked the author, so they are not
+/// notified. The text itself is left as written.
+async fn deliverable_mentions(blocks: &BlockList, author_id: Uuid, mentions: &[Uuid]) -> Result<Vec<Uuid>> {
+    let mut deliverable = Vec::with_capacity(mentions.len());
+    for &user_id in mentions {
+        if user_id != author_id && !blocks.has_blocked(user_id, author_id).await? {
+            deliverable.push(user_id);
+        }
+    }
+    Ok(deliverable)
+}
+
+async fn publish_created(state: &AppState, message: &Message, mentions: Vec<Uuid>) {
+    metrics::counter!("messages_sent_total").increment(1);
+    let event = Event::MessageCreated(MessageEvent {
+        message_id: message.id,
+        channel_id: message.channel_id,
+        dm_id: message.dm_id,
+        author_id: message.author_id,
+        content: message.content.clone(),
+        mentions,
+        timestamp: Utc::now(),
+    });
+    state.publish_best_effort(&event).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_mentions() {
+        let (a, b) = (Uuid::new_v4(), Uuid::new_v4());
+        let content = format!("hey <@{a}> and <@{b}>, <@{a}> again; <@nope> <@{b}");
+        assert_eq!(parse_mentions(&content), [a, b]);
+    }
+}
@@ -0,0 +1,68 @@
+use reqwest::{Client, StatusCode};
+use serde_json::{json, Value};
+use test_support::{fixtures, TestStack};
+use uuid::Uuid;
+
+fn contents(messages: &Value) -> Vec<&str> {
+    messages
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|message| message["content"].as_str().unwrap())
+        .collect()
+}
+
+#[tokio::test]
+#[ignore = "requires Docker"]
+async fn test_dm_history_pages_both_ways() -> anyhow::Result<()> {
+    let stack = TestStack::start().await?;
+    let service = stack.spawn(chat_service::router).await?;
+    let http = Client::new();
+
+    let alice = fixtures::create_user(&stack, "alice").await?;
+    let bob = fixtures::create_user(&stack, "bob").await?;
+    let dm: Value = http
+        .post(service.url("/users/@me/dms"))
+        .header("authorization", alice.bearer())
+        .json(&json!({ "recipient_id": bob.id() }))
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    let messages_url = service.url(&format!("/dms/{}/messages", dm["id"].as_str().unwrap()));
+
+    let mut sent = Vec::new();
+    for content in ["one", "two", "three"] {
+        let message: Value = http
+            .post(&messages_url)
+            .header("authorization", alice.bearer())
+            .json(&json!({ "content": content }))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        sent.push(message["id"].as_str().unwrap().to_string());
+    }
+
+    let page = |query: String| {
+        http.get(format!("{}?{}", messages_url, query))
+            .header("authorization", bob.bearer())
+            .send()
+    };
+
+    let older: Value = page(format!("before={}", sent[2])).await?.error_for_status()?.json().await?;
+    assert_eq!(contents(&older), ["two", "one"]);
+    let newer: Value = page(format!("after={}", sent[0])).await?.error_for_status()?.json().await?;
+    assert_eq!(contents(&newer), ["two", "three"]);
+
+    // A UUIDv7 places itself by its timestamp even if no such message exists.
+    let unsent = common::id::generate();
+    let all: Value = page(format!("before={}", unsent)).await?.error_for_status()?.json().await?;
+    assert_eq!(contents(&all), ["three", "two", "one"]);
+
+    let unknown = page(format!("after={}", Uuid::new_v4())).await?;
+    assert_eq!(unknown.status(), StatusCode::BAD_REQUEST);
+    Ok(())
+}
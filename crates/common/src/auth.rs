@@ -0,0 +1,59 @@
+//! Bearer-token authentication for handlers.
+//!
+//! A service adds [`layer`] to its router once; handlers then take an
+//! [`AuthUser`] argument, which rejects the request with `401` unless it
+//! carries a valid access token:
+//!
+//! ```ignore
+//! async fn me(user: AuthUser) -> Result<Json<User>> { ... }
+//!
+//! openapi::into_router(...).layer(auth::layer(&config.jwt_secret))
+//! ```
+
+use std::sync::Arc;
+
+use axum::{
+    async_trait,
+    extract::FromRequestParts,
+    http::{header::AUTHORIZATION, request::Parts},
+    Extension,
+};
+use uuid::Uuid;
+
+use crate::{error::AppError, jwt::JwtService};
+
+/// The user a request was authenticated as.
+#[derive(Debug, Clone)]
+pub struct AuthUser {
+    pub id: Uuid,
+    pub username: String,
+}
+
+/// Makes the token verifier available to [`AuthUser`].
+pub fn layer(jwt_secret: &str) -> Extension<Arc<JwtService>> {
+    Extension(Arc::new(JwtService::new(jwt_secret)))
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for AuthUser {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let jwt = parts.extensions.get::<Arc<JwtService>>().ok_or_else(|| {
+            AppError::InternalServerError("auth::layer is not installed on this router".to_string())
+        })?;
+
+        let token = parts
+            .headers
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .ok_or_else(|| AppError::Unauthorized("Missing bearer token".to_string()))?;
+
+        let claims = jwt.verify_token(token)?;
+        Ok(AuthUser {
+            id: claims.sub,
+            username: claims.username,
+        })
+    }
+}
@@ -0,0 +1,144 @@
+//! Cached block lookups for enforcing blocks on hot paths.
+//!
+//! Blocks live in `friendships` (see [`db::friendships`]); each user's list
+//! of blocked ids is cached under the `blocks` namespace, with an in-process
+//! tier when NATS is available. user-service invalidates a user's entry
+//! whenever they block or unblock someone, which evicts it from every
+//! service's local tier.
+//!
+//! The cache is also a [`Projection`] named `blocks`, so
+//! `admin-cli rebuild --consumer blocks` drops every entry and replays
+//! `UserBlocked` events over it.
+
+use std::{sync::Arc, time::Duration};
+
+use futures::future::BoxFuture;
+use tokio::sync::OnceCell;
+use uuid::Uuid;
+
+use crate::{db, error::Result, message_queue::Projection, typed_cache::Cache, AppState, Event};
+
+pub const NAMESPACE: &str = "blocks";
+
+const TTL: Duration = Duration::from_secs(600);
+const LOCAL_CAPACITY: usize = 10_000;
+const LOCAL_TTL: Duration = Duration::from_secs(60);
+
+/// Cheap to clone; clones share one cache.
+#[derive(Clone)]
+pub struct BlockList {
+    state: AppState,
+    cache: Arc<OnceCell<Cache<Vec<Uuid>>>>,
+}
+
+impl BlockList {
+    /// Uses the state's Postgres pool and key-value cache, connecting on
+    /// first lookup.
+    pub fn new(state: AppState) -> Self {
+        Self {
+            state,
+            cache: Arc::new(OnceCell::new()),
+        }
+    }
+
+    /// Ids of the users `user_id` has blocked.
+    pub async fn blocked_by(&self, user_id: Uuid) -> Result<Vec<Uuid>> {
+        self.cache()
+            .await?
+            .get_or_load(&user_id.to_string(), TTL, || async {
+                db::friendships::blocked_ids(self.state.db()?, user_id).await
+            })
+            .await
+    }
+
+    pub async fn has_blocked(&self, blocker: Uuid, target: Uuid) -> Result<bool> {
+        Ok(self.blocked_by(blocker).await?.contains(&target))
+    }
+
+    /// Either user has blocked the other.
+    pub async fn either_blocked(&self, a: Uuid, b: Uuid) -> Result<bool> {
+        let (a_blocks, b_blocks) = tokio::try_join!(self.has_blocked(a, b), self.has_blocked(b, a))?;
+        Ok(a_blocks || b_blocks)
+    }
+
+    /// Members of `others` with a block in either direction with `user_id`,
+    /// e.g. to refuse adding `user_id` to a group conversation.
+    pub async fn blocked_among(&self, user_id: Uuid, others: &[Uuid]) -> Result<Vec<Uuid>> {
+        let mut blocked = Vec::new();
+        for &other in others {
+            if other != user_id && self.either_blocked(user_id, other).await? {
+                blocked.push(other);
+            }
+        }
+        Ok(blocked)
+    }
+
+    /// Call after `user_id` blocks or unblocks someone.
+    pub async fn invalidate(&self, user_id: Uuid) -> Result<()> {
+        self.cache().await?.invalidate(&user_id.to_string()).await
+    }
+
+    /// Drops every cached block list; each reloads from Postgres on its next
+    /// lookup.
+    pub async fn clear(&self) -> Result<()> {
+        self.cache().await?.clear().await
+    }
+
+    async fn cache(&self) -> Result<&Cache<Vec<Uuid>>> {
+        self.cache
+            .get_or_try_init(|| async {
+                let cache = Cache::new(self.state.cache().await?, NAMESPACE);
+                match self.state.nats() {
+                    Ok(nats) => cache.with_local_tier(nats.clone(), LOCAL_CAPACITY, LOCAL_TTL).await,
+                    Err(_) => Ok(cache),
+                }
+            })
+            .await
+    }
+}
+
+impl Projection for BlockList {
+    fn name(&self) -> &str {
+        NAMESPACE
+    }
+
+    fn subjects(&self) -> &[&str] {
+        &["user.blocked"]
+    }
+
+    fn apply<'a>(&'a self, event: &'a Event) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            match event {
+                Event::UserBlocked(blocked) => self.invalidate(blocked.user_id).await,
+                _ => Ok(()),
+            }
+        })
+    }
+
+    fn reset(&self) -> BoxFuture<'_, Result<()>> {
+        Box::pin(self.clear())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cache::KeyValueCache, memory::InMemoryCache};
+
+    #[tokio::test]
+    async fn test_blocks_apply_in_both_directions() {
+        let (alice, bob, carol) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let cache = Arc::new(InMemoryCache::new());
+        for (user, blocked) in [(alice, vec![bob]), (bob, vec![]), (carol, vec![])] {
+            let entry = serde_json::to_string(&blocked).unwrap();
+            cache.set(&format!("{}:{}", NAMESPACE, user), &entry, None).await.unwrap();
+        }
+        let state = AppState::builder().cache(cache).build().await.unwrap();
+        let blocks = BlockList::new(state);
+
+        assert!(blocks.either_blocked(alice, bob).await.unwrap());
+        assert!(blocks.either_blocked(bob, alice).await.unwrap());
+        assert!(!blocks.has_blocked(bob, alice).await.unwrap());
+        assert_eq!(blocks.blocked_among(bob, &[alice, carol]).await.unwrap(), [alice]);
+    }
+}
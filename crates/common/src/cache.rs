@@ -1,40 +1,378 @@
-use redis::{Client, AsyncCommands};
-use crate::error::{AppError, Result};
+use std::{collections::HashMap, sync::Arc, time::Duration};
 
+use futures::future::BoxFuture;
+use redis::{aio::ConnectionManager, AsyncCommands, Client, FromRedisValue, Pipeline, Script};
+use crate::{
+    error::{AppError, Result},
+    metrics,
+};
+
+/// Fixed-window counter; returns `{count, ttl_ms}`.
+const RATE_LIMIT_SCRIPT: &str = r#"
+local count = redis.call('INCR', KEYS[1])
+if count == 1 then
+    redis.call('PEXPIRE', KEYS[1], ARGV[1])
+end
+return {count, redis.call('PTTL', KEYS[1])}
+"#;
+
+/// HSET followed by PEXPIRE, so a hash never outlives its refresh.
+const HSET_EX_SCRIPT: &str = r#"
+redis.call('HSET', KEYS[1], unpack(ARGV, 2))
+redis.call('PEXPIRE', KEYS[1], ARGV[1])
+return 1
+"#;
+
+/// HINCRBY followed by PEXPIRE; returns the new field value.
+const HINCR_EX_SCRIPT: &str = r#"
+local value = redis.call('HINCRBY', KEYS[1], ARGV[1], ARGV[2])
+redis.call('PEXPIRE', KEYS[1], ARGV[3])
+return value
+"#;
+
+/// Keys examined per `SCAN` step of [`CacheClient::delete_prefix`].
+const SCAN_COUNT: usize = 500;
+
+struct Scripts {
+    rate_limit: Script,
+    hset_ex: Script,
+    hincr_ex: Script,
+}
+
+/// Result of a [`CacheClient::rate_limit`] check.
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimit {
+    pub allowed: bool,
+    pub remaining: u64,
+    pub reset_after_ms: u64,
+}
+
+/// The key, hash, set and rate-limit operations handlers use, implemented
+/// by [`CacheClient`] and by [`InMemoryCache`](crate::memory::InMemoryCache)
+/// for tests.
+pub trait KeyValueCache: Send + Sync {
+    fn get<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<Option<String>>>;
+    fn set<'a>(&'a self, key: &'a str, value: &'a str, ttl: Option<Duration>) -> BoxFuture<'a, Result<()>>;
+    fn delete<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<()>>;
+    fn expire<'a>(&'a self, key: &'a str, ttl: Duration) -> BoxFuture<'a, Result<()>>;
+    fn delete_prefix<'a>(&'a self, prefix: &'a str) -> BoxFuture<'a, Result<()>>;
+
+    fn hget<'a>(&'a self, key: &'a str, field: &'a str) -> BoxFuture<'a, Result<Option<String>>>;
+    fn hgetall<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<HashMap<String, String>>>;
+    fn hset<'a>(&'a self, key: &'a str, fields: &'a [(&'a str, &'a str)]) -> BoxFuture<'a, Result<()>>;
+    fn hdel<'a>(&'a self, key: &'a str, field: &'a str) -> BoxFuture<'a, Result<()>>;
+    fn hincr<'a>(&'a self, key: &'a str, field: &'a str, delta: i64) -> BoxFuture<'a, Result<i64>>;
+
+    fn sadd<'a>(&'a self, key: &'a str, member: &'a str) -> BoxFuture<'a, Result<bool>>;
+    fn srem<'a>(&'a self, key: &'a str, member: &'a str) -> BoxFuture<'a, Result<bool>>;
+    fn smembers<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<Vec<String>>>;
+    fn sismember<'a>(&'a self, key: &'a str, member: &'a str) -> BoxFuture<'a, Result<bool>>;
+
+    fn rate_limit<'a>(&'a self, key: &'a str, limit: u64, window: Duration) -> BoxFuture<'a, Result<RateLimit>>;
+}
+
+/// Redis client backed by a single multiplexed connection that reconnects
+/// on failure. Cheap to clone.
+#[derive(Clone)]
 pub struct CacheClient {
-    client: Client,
+    conn: ConnectionManager,
+    scripts: Arc<Scripts>,
 }
 
 impl CacheClient {
-    pub fn new(client: Client) -> Self {
-        Self { client }
+    pub async fn new(client: Client) -> Result<Self> {
+        let conn = ConnectionManager::new(client)
+            .await
+            .map_err(|e| AppError::Cache(e.to_string()))?;
+
+        Ok(Self {
+            conn,
+            scripts: Arc::new(Scripts {
+                rate_limit: Script::new(RATE_LIMIT_SCRIPT),
+                hset_ex: Script::new(HSET_EX_SCRIPT),
+                hincr_ex: Script::new(HINCR_EX_SCRIPT),
+            }),
+        })
     }
 
     pub async fn get(&self, key: &str) -> Result<Option<String>> {
-        let mut conn = self.client.get_async_connection()
-            .await
-            .map_err(|e| AppError::Cache(e.to_string()))?;
-        
-        conn.get(key).await.map_err(Into::into)
+        self.conn().get(key).await.map_err(Into::into)
     }
 
-    pub async fn set(&self, key: &str, value: &str, ttl: Option<usize>) -> Result<()> {
-        let mut conn = self.client.get_async_connection()
-            .await
-            .map_err(|e| AppError::Cache(e.to_string()))?;
-        
+    pub async fn set(&self, key: &str, value: &str, ttl: Option<Duration>) -> Result<()> {
+        let mut conn = self.conn();
+
         if let Some(ttl) = ttl {
-            conn.set_ex(key, value, ttl).await.map_err(Into::into)
+            conn.pset_ex(key, value, ttl_millis(ttl)).await.map_err(Into::into)
         } else {
             conn.set(key, value).await.map_err(Into::into)
         }
     }
 
     pub async fn delete(&self, key: &str) -> Result<()> {
-        let mut conn = self.client.get_async_connection()
+        self.conn().del(key).await.map_err(Into::into)
+    }
+
+    pub async fn expire(&self, key: &str, ttl: Duration) -> Result<()> {
+        self.conn().pexpire(key, ttl_millis(ttl) as i64).await.map_err(Into::into)
+    }
+
+    /// Deletes every key starting with `prefix`, scanning in batches rather
+    /// than blocking Redis with `KEYS`.
+    pub async fn delete_prefix(&self, prefix: &str) -> Result<()> {
+        let pattern = format!("{}*", escape_glob(prefix));
+        let mut conn = self.conn();
+        let mut cursor = 0u64;
+        loop {
+            let (next, keys): (u64, Vec<String>) = redis::cmd("SCAN")
+                .arg(cursor)
+                .arg("MATCH")
+                .arg(&pattern)
+                .arg("COUNT")
+                .arg(SCAN_COUNT)
+                .query_async(&mut conn)
+                .await?;
+            if !keys.is_empty() {
+                redis::cmd("UNLINK").arg(&keys).query_async::<_, ()>(&mut conn).await?;
+            }
+            if next == 0 {
+                return Ok(());
+            }
+            cursor = next;
+        }
+    }
+
+    /// Runs a pipeline in a single round trip.
+    pub async fn pipeline<T: FromRedisValue>(&self, pipe: &Pipeline) -> Result<T> {
+        pipe.query_async(&mut self.conn()).await.map_err(Into::into)
+    }
+
+    // Batch
+
+    pub async fn mget(&self, keys: &[&str]) -> Result<Vec<Option<String>>> {
+        if keys.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        redis::cmd("MGET")
+            .arg(keys)
+            .query_async(&mut self.conn())
             .await
-            .map_err(|e| AppError::Cache(e.to_string()))?;
-        
-        conn.del(key).await.map_err(Into::into)
+            .map_err(Into::into)
+    }
+
+    pub async fn mset(&self, items: &[(&str, &str)], ttl: Option<Duration>) -> Result<()> {
+        if items.is_empty() {
+            return Ok(());
+        }
+
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        match ttl {
+            Some(ttl) => {
+                for (key, value) in items {
+                    pipe.pset_ex(*key, *value, ttl_millis(ttl)).ignore();
+                }
+            }
+            None => {
+                pipe.mset(items).ignore();
+            }
+        }
+
+        self.pipeline(&pipe).await
+    }
+
+    // Hashes
+
+    pub async fn hget(&self, key: &str, field: &str) -> Result<Option<String>> {
+        self.conn().hget(key, field).await.map_err(Into::into)
+    }
+
+    pub async fn hgetall(&self, key: &str) -> Result<HashMap<String, String>> {
+        self.conn().hgetall(key).await.map_err(Into::into)
+    }
+
+    pub async fn hset(&self, key: &str, fields: &[(&str, &str)]) -> Result<()> {
+        self.conn().hset_multiple(key, fields).await.map_err(Into::into)
+    }
+
+    pub async fn hdel(&self, key: &str, field: &str) -> Result<()> {
+        self.conn().hdel(key, field).await.map_err(Into::into)
+    }
+
+    pub async fn hincr(&self, key: &str, field: &str, delta: i64) -> Result<i64> {
+        self.conn().hincr(key, field, delta).await.map_err(Into::into)
+    }
+
+    // Sets
+
+    pub async fn sadd(&self, key: &str, member: &str) -> Result<bool> {
+        self.conn().sadd(key, member).await.map_err(Into::into)
+    }
+
+    pub async fn srem(&self, key: &str, member: &str) -> Result<bool> {
+        self.conn().srem(key, member).await.map_err(Into::into)
+    }
+
+    pub async fn smembers(&self, key: &str) -> Result<Vec<String>> {
+        self.conn().smembers(key).await.map_err(Into::into)
+    }
+
+    pub async fn sismember(&self, key: &str, member: &str) -> Result<bool> {
+        self.conn().sismember(key, member).await.map_err(Into::into)
     }
+
+    // Sorted sets
+
+    pub async fn zadd(&self, key: &str, member: &str, score: f64) -> Result<()> {
+        self.conn().zadd(key, member, score).await.map_err(Into::into)
+    }
+
+    pub async fn zrem(&self, key: &str, member: &str) -> Result<()> {
+        self.conn().zrem(key, member).await.map_err(Into::into)
+    }
+
+    pub async fn zrange_by_score(&self, key: &str, min: f64, max: f64) -> Result<Vec<String>> {
+        self.conn().zrangebyscore(key, min, max).await.map_err(Into::into)
+    }
+
+    pub async fn zrem_range_by_score(&self, key: &str, min: f64, max: f64) -> Result<u64> {
+        self.conn().zrembyscore(key, min, max).await.map_err(Into::into)
+    }
+
+    // Scripts
+
+    /// Counts a hit against `key` and reports whether it is within `limit`
+    /// for the current `window`.
+    pub async fn rate_limit(&self, key: &str, limit: u64, window: Duration) -> Result<RateLimit> {
+        let (count, ttl): (u64, i64) = self
+            .scripts
+            .rate_limit
+            .key(key)
+            .arg(ttl_millis(window))
+            .invoke_async(&mut self.conn())
+            .await?;
+
+        Ok(RateLimit {
+            allowed: count <= limit,
+            remaining: limit.saturating_sub(count),
+            reset_after_ms: ttl.max(0) as u64,
+        })
+    }
+
+    /// Sets hash fields and (re)applies the key TTL atomically, e.g. for
+    /// `presence:user:{id}` refreshed by heartbeats.
+    pub async fn hset_ex(&self, key: &str, fields: &[(&str, &str)], ttl: Duration) -> Result<()> {
+        if fields.is_empty() {
+            return self.expire(key, ttl).await;
+        }
+
+        let mut invocation = self.scripts.hset_ex.key(key);
+        invocation.arg(ttl_millis(ttl));
+        for (field, value) in fields {
+            invocation.arg(*field).arg(*value);
+        }
+
+        let _: i64 = invocation.invoke_async(&mut self.conn()).await?;
+        Ok(())
+    }
+
+    /// Increments a hash field and (re)applies the key TTL atomically, e.g.
+    /// per-channel unread counters in `unread:{user_id}`.
+    pub async fn hincr_ex(&self, key: &str, field: &str, delta: i64, ttl: Duration) -> Result<i64> {
+        self.scripts
+            .hincr_ex
+            .key(key)
+            .arg(field)
+            .arg(delta)
+            .arg(ttl_millis(ttl))
+            .invoke_async(&mut self.conn())
+            .await
+            .map_err(Into::into)
+    }
+
+    /// Connection handle for one command, counted in `redis_commands_total`.
+    fn conn(&self) -> ConnectionManager {
+        metrics::counter!("redis_commands_total").increment(1);
+        self.conn.clone()
+    }
+}
+
+impl KeyValueCache for CacheClient {
+    fn get<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<Option<String>>> {
+        Box::pin(CacheClient::get(self, key))
+    }
+
+    fn set<'a>(&'a self, key: &'a str, value: &'a str, ttl: Option<Duration>) -> BoxFuture<'a, Result<()>> {
+        Box::pin(CacheClient::set(self, key, value, ttl))
+    }
+
+    fn delete<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<()>> {
+        Box::pin(CacheClient::delete(self, key))
+    }
+
+    fn expire<'a>(&'a self, key: &'a str, ttl: Duration) -> BoxFuture<'a, Result<()>> {
+        Box::pin(CacheClient::expire(self, key, ttl))
+    }
+
+    fn delete_prefix<'a>(&'a self, prefix: &'a str) -> BoxFuture<'a, Result<()>> {
+        Box::pin(CacheClient::delete_prefix(self, prefix))
+    }
+
+    fn hget<'a>(&'a self, key: &'a str, field: &'a str) -> BoxFuture<'a, Result<Option<String>>> {
+        Box::pin(CacheClient::hget(self, key, field))
+    }
+
+    fn hgetall<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<HashMap<String, String>>> {
+        Box::pin(CacheClient::hgetall(self, key))
+    }
+
+    fn hset<'a>(&'a self, key: &'a str, fields: &'a [(&'a str, &'a str)]) -> BoxFuture<'a, Result<()>> {
+        Box::pin(CacheClient::hset(self, key, fields))
+    }
+
+    fn hdel<'a>(&'a self, key: &'a str, field: &'a str) -> BoxFuture<'a, Result<()>> {
+        Box::pin(CacheClient::hdel(self, key, field))
+    }
+
+    fn hincr<'a>(&'a self, key: &'a str, field: &'a str, delta: i64) -> BoxFuture<'a, Result<i64>> {
+        Box::pin(CacheClient::hincr(self, key, field, delta))
+    }
+
+    fn sadd<'a>(&'a self, key: &'a str, member: &'a str) -> BoxFuture<'a, Result<bool>> {
+        Box::pin(CacheClient::sadd(self, key, member))
+    }
+
+    fn srem<'a>(&'a self, key: &'a str, member: &'a str) -> BoxFuture<'a, Result<bool>> {
+        Box::pin(CacheClient::srem(self, key, member))
+    }
+
+    fn smembers<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<Vec<String>>> {
+        Box::pin(CacheClient::smembers(self, key))
+    }
+
+    fn sismember<'a>(&'a self, key: &'a str, member: &'a str) -> BoxFuture<'a, Result<bool>> {
+        Box::pin(CacheClient::sismember(self, key, member))
+    }
+
+    fn rate_limit<'a>(&'a self, key: &'a str, limit: u64, window: Duration) -> BoxFuture<'a, Result<RateLimit>> {
+        Box::pin(CacheClient::rate_limit(self, key, limit, window))
+    }
+}
+
+/// Escapes the characters `SCAN MATCH` treats as glob syntax.
+fn escape_glob(literal: &str) -> String {
+    let mut escaped = String::with_capacity(literal.len());
+    for c in literal.chars() {
+        if matches!(c, '*' | '?' | '[' | ']' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Redis rejects a zero expiry, so round sub-millisecond TTLs up.
+fn ttl_millis(ttl: Duration) -> u64 {
+    (ttl.as_millis() as u64).max(1)
 }
@@ -1,8 +0,0 @@
-use sqlx::PgPool;
-
-pub async fn health_check(pool: &PgPool) -> Result<(), sqlx::Error> {
-    sqlx::query("SELECT 1")
-        .execute(pool)
-        .await?;
-    Ok(())
-}
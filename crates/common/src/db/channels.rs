@@ -0,0 +1,63 @@
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+use crate::{error::Result, models::{Channel, ChannelType}};
+
+const COLUMNS: &str = "id, server_id, name, type, created_at";
+
+pub async fn find_by_id<'e>(db: impl PgExecutor<'e>, id: Uuid) -> Result<Option<Channel>> {
+    let channel = sqlx::query_as(&format!("SELECT {COLUMNS} FROM channels WHERE id = $1"))
+        .bind(id)
+        .fetch_optional(db)
+        .await?;
+    Ok(channel)
+}
+
+pub async fn list_for_server<'e>(db: impl PgExecutor<'e>, server_id: Uuid) -> Result<Vec<Channel>> {
+    let channels = sqlx::query_as(&format!(
+        "SELECT {COLUMNS} FROM channels WHERE server_id = $1 ORDER BY position, created_at"
+    ))
+    .bind(server_id)
+    .fetch_all(db)
+    .await?;
+    Ok(channels)
+}
+
+pub async fn create<'e>(
+    db: impl PgExecutor<'e>,
+    server_id: Uuid,
+    name: &str,
+    channel_type: ChannelType,
+    parent_id: Option<Uuid>,
+) -> Result<Channel> {
+    let channel = sqlx::query_as(&format!(
+        "INSERT INTO channels (server_id, name, type, parent_id) VALUES ($1, $2, $3, $4) \
+         RETURNING {COLUMNS}"
+    ))
+    .bind(server_id)
+    .bind(name)
+    .bind(channel_type)
+    .bind(parent_id)
+    .fetch_one(db)
+    .await?;
+    Ok(channel)
+}
+
+pub async fn rename<'e>(db: impl PgExecutor<'e>, id: Uuid, name: &str) -> Result<Option<Channel>> {
+    let channel = sqlx::query_as(&format!(
+        "UPDATE channels SET name = $2 WHERE id = $1 RETURNING {COLUMNS}"
+    ))
+    .bind(id)
+    .bind(name)
+    .fetch_optional(db)
+    .await?;
+    Ok(channel)
+}
+
+pub async fn delete<'e>(db: impl PgExecutor<'e>, id: Uuid) -> Result<bool> {
+    let result = sqlx::query("DELETE FROM channels WHERE id = $1")
+        .bind(id)
+        .execute(db)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
@@ -0,0 +1,55 @@
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+use crate::{
+    error::Result,
+    models::{Connection, ConnectionProvider},
+};
+
+const COLUMNS: &str = "provider, account_name, visible, created_at";
+
+/// Oldest first. With `visible_only`, hidden connections are left out, for
+/// showing to other users.
+pub async fn list_for_user<'e>(db: impl PgExecutor<'e>, user_id: Uuid, visible_only: bool) -> Result<Vec<Connection>> {
+    let connections = sqlx::query_as(&format!(
+        "SELECT {COLUMNS} FROM user_connections \
+         WHERE user_id = $1 AND (visible OR NOT $2) ORDER BY created_at"
+    ))
+    .bind(user_id)
+    .bind(visible_only)
+    .fetch_all(db)
+    .await?;
+    Ok(connections)
+}
+
+/// Adds the connection, or replaces the user's existing one for `provider`.
+pub async fn upsert<'e>(
+    db: impl PgExecutor<'e>,
+    user_id: Uuid,
+    provider: ConnectionProvider,
+    account_name: &str,
+    visible: bool,
+) -> Result<Connection> {
+    let connection = sqlx::query_as(&format!(
+        "INSERT INTO user_connections (user_id, provider, account_name, visible) VALUES ($1, $2, $3, $4) \
+         ON CONFLICT (user_id, provider) DO UPDATE \
+         SET account_name = EXCLUDED.account_name, visible = EXCLUDED.visible \
+         RETURNING {COLUMNS}"
+    ))
+    .bind(user_id)
+    .bind(provider)
+    .bind(account_name)
+    .bind(visible)
+    .fetch_one(db)
+    .await?;
+    Ok(connection)
+}
+
+pub async fn delete<'e>(db: impl PgExecutor<'e>, user_id: Uuid, provider: ConnectionProvider) -> Result<bool> {
+    let result = sqlx::query("DELETE FROM user_connections WHERE user_id = $1 AND provider = $2")
+        .bind(user_id)
+        .bind(provider)
+        .execute(db)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
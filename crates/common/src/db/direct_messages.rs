@@ -0,0 +1,30 @@
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+use crate::{error::Result, models::DirectMessage};
+
+const COLUMNS: &str = "id, user_one_id, user_two_id, created_at";
+
+pub async fn find_by_id<'e>(db: impl PgExecutor<'e>, id: Uuid) -> Result<Option<DirectMessage>> {
+    let dm = sqlx::query_as(&format!("SELECT {COLUMNS} FROM direct_messages WHERE id = $1"))
+        .bind(id)
+        .fetch_optional(db)
+        .await?;
+    Ok(dm)
+}
+
+/// The conversation between `a` and `b`, created on first use.
+pub async fn find_or_create<'e>(db: impl PgExecutor<'e>, a: Uuid, b: Uuid) -> Result<DirectMessage> {
+    let (one, two) = if a < b { (a, b) } else { (b, a) };
+    // The no-op update makes RETURNING yield the existing row on conflict.
+    let dm = sqlx::query_as(&format!(
+        "INSERT INTO direct_messages (user_one_id, user_two_id) VALUES ($1, $2) \
+         ON CONFLICT (user_one_id, user_two_id) DO UPDATE SET user_one_id = EXCLUDED.user_one_id \
+         RETURNING {COLUMNS}"
+    ))
+    .bind(one)
+    .bind(two)
+    .fetch_one(db)
+    .await?;
+    Ok(dm)
+}
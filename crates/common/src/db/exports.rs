@@ -0,0 +1,239 @@
+//! Data export jobs and the rows that go into them.
+//!
+//! `data_exports` doubles as the job queue: [`claim_next`] takes the oldest
+//! pending job that is due, or one whose worker stopped reporting, with
+//! `FOR UPDATE SKIP LOCKED`, so any number of workers can poll it. A failed
+//! job is not due again until its `next_attempt_at`.
+//!
+//! A worker reports with [`heartbeat`] while it runs, and identifies its run
+//! by the `attempts` value it claimed: once a job has been reclaimed, the old
+//! run's [`heartbeat`], [`complete`] and [`fail`] no longer match and change
+//! nothing.
+
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use sqlx::{types::Json, PgExecutor};
+use uuid::Uuid;
+
+use crate::{error::Result, models::DataExport};
+
+const COLUMNS: &str = "id, user_id, status, attempts, object_key, requested_at, completed_at, expires_at";
+
+/// Queues an export, unless the user already has one pending or running.
+pub async fn create<'e>(db: impl PgExecutor<'e>, user_id: Uuid) -> Result<Option<DataExport>> {
+    let export = sqlx::query_as(&format!(
+        "INSERT INTO data_exports (user_id) VALUES ($1) ON CONFLICT DO NOTHING RETURNING {COLUMNS}"
+    ))
+    .bind(user_id)
+    .fetch_optional(db)
+    .await?;
+    Ok(export)
+}
+
+pub async fn find_for_user<'e>(db: impl PgExecutor<'e>, user_id: Uuid, id: Uuid) -> Result<Option<DataExport>> {
+    let export = sqlx::query_as(&format!("SELECT {COLUMNS} FROM data_exports WHERE id = $1 AND user_id = $2"))
+        .bind(id)
+        .bind(user_id)
+        .fetch_optional(db)
+        .await?;
+    Ok(export)
+}
+
+/// The user's most recent exports, newest first.
+pub async fn list_for_user<'e>(db: impl PgExecutor<'e>, user_id: Uuid, limit: i64) -> Result<Vec<DataExport>> {
+    let exports = sqlx::query_as(&format!(
+        "SELECT {COLUMNS} FROM data_exports WHERE user_id = $1 ORDER BY requested_at DESC LIMIT $2"
+    ))
+    .bind(user_id)
+    .bind(limit)
+    .fetch_all(db)
+    .await?;
+    Ok(exports)
+}
+
+/// Marks the oldest pending job that is due, or a running one that has not
+/// reported for `stale_after`, as running and counts the attempt.
+pub async fn claim_next<'e>(db: impl PgExecutor<'e>, stale_after: Duration) -> Result<Option<DataExport>> {
+    let export = sqlx::query_as(&format!(
+        "UPDATE data_exports SET status = 'running', started_at = NOW(), attempts = attempts + 1 \
+         WHERE id = (\
+             SELECT id FROM data_exports \
+             WHERE (status = 'pending' AND next_attempt_at <= NOW()) \
+                OR (status = 'running' AND started_at < NOW() - make_interval(secs => $1)) \
+             ORDER BY requested_at \
+             LIMIT 1 \
+             FOR UPDATE SKIP LOCKED) \
+         RETURNING {COLUMNS}"
+    ))
+    .bind(stale_after.as_secs_f64())
+    .fetch_optional(db)
+    .await?;
+    Ok(export)
+}
+
+/// Tells other workers that run `attempt` of the job is still going. `false`
+/// if the job has since been reclaimed.
+pub async fn heartbeat<'e>(db: impl PgExecutor<'e>, id: Uuid, attempt: i32) -> Result<bool> {
+    let result = sqlx::query(
+        "UPDATE data_exports SET started_at = NOW() WHERE id = $1 AND status = 'running' AND attempts = $2",
+    )
+    .bind(id)
+    .bind(attempt)
+    .execute(db)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Records run `attempt` as finished. `false` if the job has since been
+/// reclaimed.
+pub async fn complete<'e>(
+    db: impl PgExecutor<'e>,
+    id: Uuid,
+    attempt: i32,
+    object_key: &str,
+    expires_at: DateTime<Utc>,
+) -> Result<bool> {
+    let result = sqlx::query(
+        "UPDATE data_exports SET status = 'completed', object_key = $3, error = NULL, \
+             completed_at = NOW(), expires_at = $4 \
+         WHERE id = $1 AND status = 'running' AND attempts = $2",
+    )
+    .bind(id)
+    .bind(attempt)
+    .bind(object_key)
+    .bind(expires_at)
+    .execute(db)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Records run `attempt` as failed. With `retry_after` the job goes back in
+/// the queue and is due again once that has passed, otherwise it is given up
+/// on. `false` if the job has since been reclaimed.
+pub async fn fail<'e>(
+    db: impl PgExecutor<'e>,
+    id: Uuid,
+    attempt: i32,
+    error: &str,
+    retry_after: Option<Duration>,
+) -> Result<bool> {
+    let result = sqlx::query(
+        "UPDATE data_exports SET status = CASE WHEN $4::float8 IS NULL THEN 'failed' ELSE 'pending' END, \
+             error = $3, next_attempt_at = NOW() + make_interval(secs => COALESCE($4, 0)) \
+         WHERE id = $1 AND status = 'running' AND attempts = $2",
+    )
+    .bind(id)
+    .bind(attempt)
+    .bind(error)
+    .bind(retry_after.map(|delay| delay.as_secs_f64()))
+    .execute(db)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// A file of the archive holding one row per line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Section {
+    Relationships,
+    ServerMemberships,
+    Messages,
+    Attachments,
+    Reactions,
+    VoiceSessions,
+    AuditLog,
+}
+
+impl Section {
+    pub const ALL: [Section; 7] = [
+        Section::Relationships,
+        Section::ServerMemberships,
+        Section::Messages,
+        Section::Attachments,
+        Section::Reactions,
+        Section::VoiceSessions,
+        Section::AuditLog,
+    ];
+
+    pub fn file_name(self) -> &'static str {
+        match self {
+            Section::Relationships => "relationships.ndjson",
+            Section::ServerMemberships => "server_memberships.ndjson",
+            Section::Messages => "messages.ndjson",
+            Section::Attachments => "attachments.ndjson",
+            Section::Reactions => "reactions.ndjson",
+            Section::VoiceSessions => "voice_sessions.ndjson",
+            Section::AuditLog => "audit_log.ndjson",
+        }
+    }
+
+    /// Rows about `$1` with ids after `$2`, in id order, at most `$3`.
+    fn query(self) -> &'static str {
+        match self {
+            // The user's own rows, plus requests others have sent them; only
+            // the requester's row exists while a request is pending.
+            Section::Relationships => {
+                "SELECT f.id, f.friend_id AS user_id, u.username, FALSE AS incoming, f.status, f.created_at, \
+                        f.updated_at \
+                 FROM friendships f JOIN users u ON u.id = f.friend_id \
+                 WHERE f.user_id = $1 AND f.id > $2 \
+                 UNION ALL \
+                 SELECT f.id, f.user_id, u.username, TRUE, f.status, f.created_at, f.updated_at \
+                 FROM friendships f JOIN users u ON u.id = f.user_id \
+                 WHERE f.friend_id = $1 AND f.status = 'pending' AND f.id > $2 \
+                 ORDER BY id LIMIT $3"
+            }
+            Section::ServerMemberships => {
+                "SELECT sm.id, sm.server_id, s.name AS server_name, sm.nickname, sm.avatar_url, sm.joined_at \
+                 FROM server_members sm JOIN servers s ON s.id = sm.server_id \
+                 WHERE sm.user_id = $1 AND sm.id > $2 ORDER BY sm.id LIMIT $3"
+            }
+            Section::Messages => {
+                "SELECT m.id, m.channel_id, m.dm_id, m.content, m.edited_at, m.pinned, m.created_at \
+                 FROM messages m \
+                 WHERE m.author_id = $1 AND m.id > $2 ORDER BY m.id LIMIT $3"
+            }
+            Section::Attachments => {
+                "SELECT a.id, a.message_id, a.filename, a.size, a.content_type, a.width, a.height, a.created_at \
+                 FROM message_attachments a JOIN messages m ON m.id = a.message_id \
+                 WHERE m.author_id = $1 AND a.id > $2 ORDER BY a.id LIMIT $3"
+            }
+            Section::Reactions => {
+                "SELECT r.id, r.message_id, r.emoji, r.created_at \
+                 FROM reactions r \
+                 WHERE r.user_id = $1 AND r.id > $2 ORDER BY r.id LIMIT $3"
+            }
+            Section::VoiceSessions => {
+                "SELECT v.id, v.channel_id, v.joined_at, v.left_at, v.muted, v.deafened, v.video_enabled, \
+                        v.screen_sharing \
+                 FROM voice_sessions v \
+                 WHERE v.user_id = $1 AND v.id > $2 ORDER BY v.id LIMIT $3"
+            }
+            Section::AuditLog => {
+                "SELECT l.id, l.server_id, l.user_id, l.action_type, l.target_type, l.target_id, l.changes, \
+                        l.reason, l.created_at \
+                 FROM audit_logs l \
+                 WHERE (l.user_id = $1 OR l.target_id = $1) AND l.id > $2 ORDER BY l.id LIMIT $3"
+            }
+        }
+    }
+}
+
+/// One page of `section` for `user_id` as JSON objects, starting after the
+/// row id `after` (`Uuid::nil()` for the first page).
+pub async fn section_page<'e>(
+    db: impl PgExecutor<'e>,
+    section: Section,
+    user_id: Uuid,
+    after: Uuid,
+    limit: i64,
+) -> Result<Vec<(Uuid, Json<Value>)>> {
+    let rows = sqlx::query_as(&format!("SELECT t.id, to_jsonb(t) FROM ({}) t ORDER BY t.id", section.query()))
+        .bind(user_id)
+        .bind(after)
+        .bind(limit)
+        .fetch_all(db)
+        .await?;
+    Ok(rows)
+}
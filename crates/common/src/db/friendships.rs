@@ -0,0 +1,202 @@
+//! Relationships between users, one `friendships` row per direction:
+//!
+//! - `pending`: `user_id` sent `friend_id` a request. Only the requester's
+//!   row exists until the request is accepted, declined or cancelled.
+//! - `accepted`: both rows exist, so either side lists its friends with a
+//!   single index lookup.
+//! - `blocked`: `user_id` blocked `friend_id`. Blocking removes any
+//!   friendship or request in either direction; the other side may hold its
+//!   own `blocked` row.
+//!
+//! Changes to a pair must run in a transaction holding [`lock_pair`], so
+//! concurrent requests between the same two users cannot interleave.
+
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+use crate::{
+    error::Result,
+    models::{Friend, FriendRequest, FriendshipStatus, PublicUser},
+};
+
+/// The [`PublicUser`] fields of `u`; other users' emails are never listed.
+const USER_COLUMNS: &str = "u.id, u.username, u.display_name, u.avatar_url";
+
+/// Both directions of the relationship between two users.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Relationship {
+    /// The row from `user_id` to `other_id`.
+    pub outgoing: Option<FriendshipStatus>,
+    /// The row from `other_id` to `user_id`.
+    pub incoming: Option<FriendshipStatus>,
+}
+
+impl Relationship {
+    /// Either side has blocked the other.
+    pub fn is_blocked(&self) -> bool {
+        self.outgoing == Some(FriendshipStatus::Blocked) || self.incoming == Some(FriendshipStatus::Blocked)
+    }
+
+    pub fn is_friends(&self) -> bool {
+        self.outgoing == Some(FriendshipStatus::Accepted)
+    }
+}
+
+/// Serializes changes to the pair for the rest of the transaction.
+pub async fn lock_pair<'e>(db: impl PgExecutor<'e>, a: Uuid, b: Uuid) -> Result<()> {
+    let (low, high) = if a < b { (a, b) } else { (b, a) };
+    sqlx::query("SELECT pg_advisory_xact_lock(hashtextextended($1::text || ':' || $2::text, 0))")
+        .bind(low)
+        .bind(high)
+        .execute(db)
+        .await?;
+    Ok(())
+}
+
+pub async fn relationship<'e>(db: impl PgExecutor<'e>, user_id: Uuid, other_id: Uuid) -> Result<Relationship> {
+    let rows: Vec<(Uuid, FriendshipStatus)> = sqlx::query_as(
+        "SELECT user_id, status FROM friendships \
+         WHERE (user_id = $1 AND friend_id = $2) OR (user_id = $2 AND friend_id = $1)",
+    )
+    .bind(user_id)
+    .bind(other_id)
+    .fetch_all(db)
+    .await?;
+
+    let mut relationship = Relationship::default();
+    for (from, status) in rows {
+        if from == user_id {
+            relationship.outgoing = Some(status);
+        } else {
+            relationship.incoming = Some(status);
+        }
+    }
+    Ok(relationship)
+}
+
+/// Creates or overwrites the row from `user_id` to `friend_id`.
+pub async fn upsert<'e>(
+    db: impl PgExecutor<'e>,
+    user_id: Uuid,
+    friend_id: Uuid,
+    status: FriendshipStatus,
+) -> Result<()> {
+    sqlx::query(
+        "INSERT INTO friendships (user_id, friend_id, status) VALUES ($1, $2, $3) \
+         ON CONFLICT (user_id, friend_id) DO UPDATE SET status = EXCLUDED.status, updated_at = NOW()",
+    )
+    .bind(user_id)
+    .bind(friend_id)
+    .bind(status)
+    .execute(db)
+    .await?;
+    Ok(())
+}
+
+/// Deletes the row from `user_id` to `friend_id` if it has `status`.
+pub async fn delete<'e>(
+    db: impl PgExecutor<'e>,
+    user_id: Uuid,
+    friend_id: Uuid,
+    status: FriendshipStatus,
+) -> Result<bool> {
+    let result = sqlx::query("DELETE FROM friendships WHERE user_id = $1 AND friend_id = $2 AND status = $3")
+        .bind(user_id)
+        .bind(friend_id)
+        .bind(status)
+        .execute(db)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Accepted friends, alphabetically, with `user_id`'s notes on them.
+pub async fn list_friends<'e>(db: impl PgExecutor<'e>, user_id: Uuid) -> Result<Vec<Friend>> {
+    let friends = sqlx::query_as(&format!(
+        "SELECT {USER_COLUMNS}, n.nickname, n.note FROM friendships f JOIN users u ON u.id = f.friend_id \
+         LEFT JOIN user_notes n ON n.owner_id = f.user_id AND n.target_id = f.friend_id \
+         WHERE f.user_id = $1 AND f.status = 'accepted' ORDER BY u.username"
+    ))
+    .bind(user_id)
+    .fetch_all(db)
+    .await?;
+    Ok(friends)
+}
+
+/// Users who are friends with both, alphabetically.
+pub async fn list_mutual<'e>(db: impl PgExecutor<'e>, user_id: Uuid, other_id: Uuid) -> Result<Vec<PublicUser>> {
+    let friends = sqlx::query_as(&format!(
+        "SELECT {USER_COLUMNS} FROM users u \
+         WHERE u.id IN (\
+             SELECT friend_id FROM friendships WHERE user_id = $1 AND status = 'accepted' \
+             INTERSECT \
+             SELECT friend_id FROM friendships WHERE user_id = $2 AND status = 'accepted') \
+         ORDER BY u.username"
+    ))
+    .bind(user_id)
+    .bind(other_id)
+    .fetch_all(db)
+    .await?;
+    Ok(friends)
+}
+
+/// Requests sent to `user_id`, newest first.
+pub async fn incoming_requests<'e>(db: impl PgExecutor<'e>, user_id: Uuid) -> Result<Vec<FriendRequest>> {
+    let requests = sqlx::query_as(&format!(
+        "SELECT {USER_COLUMNS}, f.created_at AS requested_at \
+         FROM friendships f JOIN users u ON u.id = f.user_id \
+         WHERE f.friend_id = $1 AND f.status = 'pending' ORDER BY f.created_at DESC"
+    ))
+    .bind(user_id)
+    .fetch_all(db)
+    .await?;
+    Ok(requests)
+}
+
+/// Requests sent by `user_id`, newest first.
+pub async fn outgoing_requests<'e>(db: impl PgExecutor<'e>, user_id: Uuid) -> Result<Vec<FriendRequest>> {
+    let requests = sqlx::query_as(&format!(
+        "SELECT {USER_COLUMNS}, f.created_at AS requested_at \
+         FROM friendships f JOIN users u ON u.id = f.friend_id \
+         WHERE f.user_id = $1 AND f.status = 'pending' ORDER BY f.created_at DESC"
+    ))
+    .bind(user_id)
+    .fetch_all(db)
+    .await?;
+    Ok(requests)
+}
+
+/// Users `user_id` has blocked, alphabetically.
+pub async fn list_blocked<'e>(db: impl PgExecutor<'e>, user_id: Uuid) -> Result<Vec<PublicUser>> {
+    let blocked = sqlx::query_as(&format!(
+        "SELECT {USER_COLUMNS} FROM friendships f JOIN users u ON u.id = f.friend_id \
+         WHERE f.user_id = $1 AND f.status = 'blocked' ORDER BY u.username"
+    ))
+    .bind(user_id)
+    .fetch_all(db)
+    .await?;
+    Ok(blocked)
+}
+
+/// Ids of the users `user_id` has blocked.
+pub async fn blocked_ids<'e>(db: impl PgExecutor<'e>, user_id: Uuid) -> Result<Vec<Uuid>> {
+    let ids = sqlx::query_scalar("SELECT friend_id FROM friendships WHERE user_id = $1 AND status = 'blocked'")
+        .bind(user_id)
+        .fetch_all(db)
+        .await?;
+    Ok(ids)
+}
+
+/// Deletes any friendship or pending request between the pair, keeping
+/// blocks. Returns whether they were friends.
+pub async fn unlink<'e>(db: impl PgExecutor<'e>, a: Uuid, b: Uuid) -> Result<bool> {
+    let removed: Vec<FriendshipStatus> = sqlx::query_scalar(
+        "DELETE FROM friendships WHERE status <> 'blocked' \
+         AND ((user_id = $1 AND friend_id = $2) OR (user_id = $2 AND friend_id = $1)) \
+         RETURNING status",
+    )
+    .bind(a)
+    .bind(b)
+    .fetch_all(db)
+    .await?;
+    Ok(removed.contains(&FriendshipStatus::Accepted))
+}
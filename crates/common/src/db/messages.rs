@@ -0,0 +1,174 @@
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+use crate::{error::Result, id, models::Message};
+
+const COLUMNS: &str = "id, channel_id, dm_id, author_id, content, created_at, edited_at";
+
+/// Where the message `$2` sits in `(created_at, id)` order. Rows from
+/// before UUIDv7 have random ids, so history is ordered by time first and
+/// the id only breaks ties. A cursor that has since been deleted falls back
+/// to the time in its id, passed as `$4`; callers reject cursors that have
+/// neither, see [`cursor_is_known`].
+const CURSOR: &str = "(COALESCE((SELECT created_at FROM messages WHERE id = $2), $4), $2)";
+
+pub async fn find_by_id<'e>(db: impl PgExecutor<'e>, id: Uuid) -> Result<Option<Message>> {
+    let message = sqlx::query_as(&format!("SELECT {COLUMNS} FROM messages WHERE id = $1"))
+        .bind(id)
+        .fetch_optional(db)
+        .await?;
+    Ok(message)
+}
+
+/// Whether `cursor` can position a page: it is a stored message, or a
+/// UUIDv7 whose embedded time stands in for a deleted one.
+pub async fn cursor_is_known<'e>(db: impl PgExecutor<'e>, cursor: Uuid) -> Result<bool> {
+    if id::created_at(cursor).is_some() {
+        return Ok(true);
+    }
+    let exists = sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM messages WHERE id = $1)")
+        .bind(cursor)
+        .fetch_one(db)
+        .await?;
+    Ok(exists)
+}
+
+/// Newest first, optionally strictly older than the message `before`.
+pub async fn list_for_channel<'e>(
+    db: impl PgExecutor<'e>,
+    channel_id: Uuid,
+    before: Option<Uuid>,
+    limit: i64,
+) -> Result<Vec<Message>> {
+    let messages = sqlx::query_as(&format!(
+        "SELECT {COLUMNS} FROM messages \
+         WHERE channel_id = $1 AND ($2::uuid IS NULL OR (created_at, id) < {CURSOR}) \
+         ORDER BY created_at DESC, id DESC LIMIT $3"
+    ))
+    .bind(channel_id)
+    .bind(before)
+    .bind(limit)
+    .bind(before.and_then(id::created_at))
+    .fetch_all(db)
+    .await?;
+    Ok(messages)
+}
+
+/// Oldest first, strictly newer than the message `after`, for catching up
+/// from the last message a client has seen.
+pub async fn list_after<'e>(
+    db: impl PgExecutor<'e>,
+    channel_id: Uuid,
+    after: Uuid,
+    limit: i64,
+) -> Result<Vec<Message>> {
+    let messages = sqlx::query_as(&format!(
+        "SELECT {COLUMNS} FROM messages \
+         WHERE channel_id = $1 AND (created_at, id) > {CURSOR} \
+         ORDER BY created_at ASC, id ASC LIMIT $3"
+    ))
+    .bind(channel_id)
+    .bind(after)
+    .bind(limit)
+    .bind(id::created_at(after))
+    .fetch_all(db)
+    .await?;
+    Ok(messages)
+}
+
+/// Newest first, like [`list_for_channel`].
+pub async fn list_for_dm<'e>(
+    db: impl PgExecutor<'e>,
+    dm_id: Uuid,
+    before: Option<Uuid>,
+    limit: i64,
+) -> Result<Vec<Message>> {
+    let messages = sqlx::query_as(&format!(
+        "SELECT {COLUMNS} FROM messages \
+         WHERE dm_id = $1 AND ($2::uuid IS NULL OR (created_at, id) < {CURSOR}) \
+         ORDER BY created_at DESC, id DESC LIMIT $3"
+    ))
+    .bind(dm_id)
+    .bind(before)
+    .bind(limit)
+    .bind(before.and_then(id::created_at))
+    .fetch_all(db)
+    .await?;
+    Ok(messages)
+}
+
+/// Oldest first, like [`list_after`].
+pub async fn list_after_in_dm<'e>(
+    db: impl PgExecutor<'e>,
+    dm_id: Uuid,
+    after: Uuid,
+    limit: i64,
+) -> Result<Vec<Message>> {
+    let messages = sqlx::query_as(&format!(
+        "SELECT {COLUMNS} FROM messages \
+         WHERE dm_id = $1 AND (created_at, id) > {CURSOR} \
+         ORDER BY created_at ASC, id ASC LIMIT $3"
+    ))
+    .bind(dm_id)
+    .bind(after)
+    .bind(limit)
+    .bind(id::created_at(after))
+    .fetch_all(db)
+    .await?;
+    Ok(messages)
+}
+
+pub async fn create<'e>(
+    db: impl PgExecutor<'e>,
+    channel_id: Uuid,
+    author_id: Uuid,
+    content: &str,
+) -> Result<Message> {
+    let message = sqlx::query_as(&format!(
+        "INSERT INTO messages (channel_id, author_id, content) VALUES ($1, $2, $3) \
+         RETURNING {COLUMNS}"
+    ))
+    .bind(channel_id)
+    .bind(author_id)
+    .bind(content)
+    .fetch_one(db)
+    .await?;
+    Ok(message)
+}
+
+pub async fn create_in_dm<'e>(
+    db: impl PgExecutor<'e>,
+    dm_id: Uuid,
+    author_id: Uuid,
+    content: &str,
+) -> Result<Message> {
+    let message = sqlx::query_as(&format!(
+        "INSERT INTO messages (dm_id, author_id, content) VALUES ($1, $2, $3) \
+         RETURNING {COLUMNS}"
+    ))
+    .bind(dm_id)
+    .bind(author_id)
+    .bind(content)
+    .fetch_one(db)
+    .await?;
+    Ok(message)
+}
+
+pub async fn update_content<'e>(db: impl PgExecutor<'e>, id: Uuid, content: &str) -> Result<Option<Message>> {
+    let message = sqlx::query_as(&format!(
+        "UPDATE messages SET content = $2, edited_at = NOW() WHERE id = $1 RETURNING {COLUMNS}"
+    ))
+    .bind(id)
+    .bind(content)
+    .fetch_optional(db)
+    .await?;
+    Ok(message)
+}
+
+pub async fn delete<'e>(db: impl PgExecutor<'e>, id: Uuid) -> Result<bool> {
+    let result = sqlx::query("DELETE FROM messages WHERE id = $1")
+        .bind(id)
+        .execute(db)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
@@ -0,0 +1,103 @@
+//! Database access: health checks, embedded migrations and typed
+//! repositories.
+//!
+//! Repository queries use runtime-checked `query_as` so the workspace builds
+//! without a live database. Every function takes a `PgExecutor`, so callers
+//! can pass either the pool or an open transaction.
+
+pub mod channels;
+pub mod connections;
+pub mod direct_messages;
+pub mod exports;
+pub mod friendships;
+pub mod messages;
+pub mod notes;
+pub mod roles;
+pub mod servers;
+pub mod settings;
+pub mod users;
+
+use std::time::Duration;
+
+use sqlx::{
+    migrate::{Migrate, MigrateError, Migrator},
+    postgres::PgPoolOptions,
+    PgPool,
+};
+
+/// Migrations under `/migrations`, embedded at compile time.
+pub static MIGRATOR: Migrator = sqlx::migrate!("../../migrations");
+
+#[derive(Debug, Clone, Copy)]
+pub struct PoolSettings {
+    pub max_connections: u32,
+    pub min_connections: u32,
+    pub acquire_timeout: Duration,
+}
+
+impl Default for PoolSettings {
+    fn default() -> Self {
+        Self {
+            max_connections: 20,
+            min_connections: 5,
+            acquire_timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+/// Builds a pool that opens its first connection on first use, so a service
+/// can start while Postgres is still coming up.
+pub fn connect_lazy(url: &str, settings: PoolSettings) -> Result<PgPool, sqlx::Error> {
+    PgPoolOptions::new()
+        .max_connections(settings.max_connections)
+        .min_connections(settings.min_connections)
+        .acquire_timeout(settings.acquire_timeout)
+        .connect_lazy(url)
+}
+
+pub async fn health_check(pool: &PgPool) -> Result<(), sqlx::Error> {
+    sqlx::query("SELECT 1")
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Applies pending migrations, after [`baseline`] for databases created
+/// before they existed.
+pub async fn run_migrations(pool: &PgPool) -> Result<(), MigrateError> {
+    baseline(pool).await?;
+    MIGRATOR.run(pool).await
+}
+
+/// Databases created from the old `infra/postgres/init.sql` have the
+/// initial schema but no `_sqlx_migrations` table, so the first migration
+/// would run again and fail on existing tables. Records it as applied for
+/// them; does nothing for new or already migrated databases.
+pub async fn baseline(pool: &PgPool) -> Result<(), MigrateError> {
+    let legacy: bool = sqlx::query_scalar(
+        "SELECT to_regclass('public.users') IS NOT NULL AND to_regclass('public._sqlx_migrations') IS NULL",
+    )
+    .fetch_one(pool)
+    .await?;
+    if !legacy {
+        return Ok(());
+    }
+    let Some(initial) = MIGRATOR.iter().next() else {
+        return Ok(());
+    };
+
+    tracing::info!("Recording migration {} as applied to an existing schema", initial.version);
+    let mut conn = pool.acquire().await?;
+    conn.ensure_migrations_table().await?;
+    // Services starting together may race to do this.
+    sqlx::query(
+        "INSERT INTO _sqlx_migrations (version, description, success, checksum, execution_time) \
+         VALUES ($1, $2, TRUE, $3, 0) ON CONFLICT (version) DO NOTHING",
+    )
+    .bind(initial.version)
+    .bind(&*initial.description)
+    .bind(&*initial.checksum)
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,70 @@
+//! Private notes and nicknames one user keeps about another.
+
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+use crate::{error::Result, models::UserNote};
+
+const COLUMNS: &str = "target_id AS user_id, note, nickname, updated_at";
+
+pub async fn find<'e>(db: impl PgExecutor<'e>, owner_id: Uuid, target_id: Uuid) -> Result<Option<UserNote>> {
+    let note = sqlx::query_as(&format!(
+        "SELECT {COLUMNS} FROM user_notes WHERE owner_id = $1 AND target_id = $2"
+    ))
+    .bind(owner_id)
+    .bind(target_id)
+    .fetch_optional(db)
+    .await?;
+    Ok(note)
+}
+
+/// Everything `owner_id` keeps, most recently changed first.
+pub async fn list_for_owner<'e>(db: impl PgExecutor<'e>, owner_id: Uuid) -> Result<Vec<UserNote>> {
+    let notes = sqlx::query_as(&format!(
+        "SELECT {COLUMNS} FROM user_notes WHERE owner_id = $1 ORDER BY updated_at DESC"
+    ))
+    .bind(owner_id)
+    .fetch_all(db)
+    .await?;
+    Ok(notes)
+}
+
+/// Sets the note and nickname; `None` leaves a field as is and `Some(None)`
+/// clears it. Follow with [`delete_if_empty`] when clearing.
+pub async fn upsert<'e>(
+    db: impl PgExecutor<'e>,
+    owner_id: Uuid,
+    target_id: Uuid,
+    note: Option<Option<&str>>,
+    nickname: Option<Option<&str>>,
+) -> Result<UserNote> {
+    let note = sqlx::query_as(&format!(
+        "INSERT INTO user_notes (owner_id, target_id, note, nickname) VALUES ($1, $2, $4, $6) \
+         ON CONFLICT (owner_id, target_id) DO UPDATE SET \
+             note = CASE WHEN $3 THEN $4 ELSE user_notes.note END, \
+             nickname = CASE WHEN $5 THEN $6 ELSE user_notes.nickname END, \
+             updated_at = NOW() \
+         RETURNING {COLUMNS}"
+    ))
+    .bind(owner_id)
+    .bind(target_id)
+    .bind(note.is_some())
+    .bind(note.flatten())
+    .bind(nickname.is_some())
+    .bind(nickname.flatten())
+    .fetch_one(db)
+    .await?;
+    Ok(note)
+}
+
+/// Removes the row once it holds neither a note nor a nickname.
+pub async fn delete_if_empty<'e>(db: impl PgExecutor<'e>, owner_id: Uuid, target_id: Uuid) -> Result<bool> {
+    let result = sqlx::query(
+        "DELETE FROM user_notes WHERE owner_id = $1 AND target_id = $2 AND note IS NULL AND nickname IS NULL",
+    )
+    .bind(owner_id)
+    .bind(target_id)
+    .execute(db)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
@@ -0,0 +1,18 @@
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+use crate::error::Result;
+
+/// The role every member holds implicitly, without a `member_roles` row.
+pub const EVERYONE: &str = "@everyone";
+
+/// Returns the new role's id.
+pub async fn create<'e>(db: impl PgExecutor<'e>, server_id: Uuid, name: &str, permissions: i64) -> Result<Uuid> {
+    let id = sqlx::query_scalar("INSERT INTO roles (server_id, name, permissions) VALUES ($1, $2, $3) RETURNING id")
+        .bind(server_id)
+        .bind(name)
+        .bind(permissions)
+        .fetch_one(db)
+        .await?;
+    Ok(id)
+}
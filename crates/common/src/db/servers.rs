@@ -0,0 +1,184 @@
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+use crate::{error::Result, models::{MutualServer, Server, ServerProfile}};
+
+const COLUMNS: &str = "id, name, icon_url, owner_id, created_at";
+
+pub async fn find_by_id<'e>(db: impl PgExecutor<'e>, id: Uuid) -> Result<Option<Server>> {
+    let server = sqlx::query_as(&format!("SELECT {COLUMNS} FROM servers WHERE id = $1"))
+        .bind(id)
+        .fetch_optional(db)
+        .await?;
+    Ok(server)
+}
+
+/// Servers the user is a member of, oldest membership first.
+pub async fn list_for_user<'e>(db: impl PgExecutor<'e>, user_id: Uuid) -> Result<Vec<Server>> {
+    let servers = sqlx::query_as(
+        "SELECT s.id, s.name, s.icon_url, s.owner_id, s.created_at \
+         FROM servers s \
+         JOIN server_members sm ON sm.server_id = s.id \
+         WHERE sm.user_id = $1 \
+         ORDER BY sm.joined_at",
+    )
+    .bind(user_id)
+    .fetch_all(db)
+    .await?;
+    Ok(servers)
+}
+
+/// Whether the two users are members of at least one common server.
+pub async fn share_any<'e>(db: impl PgExecutor<'e>, a: Uuid, b: Uuid) -> Result<bool> {
+    let shared = sqlx::query_scalar(
+        "SELECT EXISTS(\
+             SELECT 1 FROM server_members mine \
+             JOIN server_members theirs ON theirs.server_id = mine.server_id \
+             WHERE mine.user_id = $1 AND theirs.user_id = $2)",
+    )
+    .bind(a)
+    .bind(b)
+    .fetch_one(db)
+    .await?;
+    Ok(shared)
+}
+
+/// Servers both users are members of, alphabetically, with `other_id`'s
+/// nickname in each.
+pub async fn list_mutual<'e>(db: impl PgExecutor<'e>, user_id: Uuid, other_id: Uuid) -> Result<Vec<MutualServer>> {
+    let servers = sqlx::query_as(
+        "SELECT s.id, s.name, s.icon_url, theirs.nickname \
+         FROM servers s \
+         JOIN server_members theirs ON theirs.server_id = s.id AND theirs.user_id = $2 \
+         WHERE s.id IN (\
+             SELECT server_id FROM server_members WHERE user_id = $1 \
+             INTERSECT \
+             SELECT server_id FROM server_members WHERE user_id = $2) \
+         ORDER BY s.name",
+    )
+    .bind(user_id)
+    .bind(other_id)
+    .fetch_all(db)
+    .await?;
+    Ok(servers)
+}
+
+pub async fn create<'e>(
+    db: impl PgExecutor<'e>,
+    name: &str,
+    icon_url: Option<&str>,
+    owner_id: Uuid,
+) -> Result<Server> {
+    let server = sqlx::query_as(&format!(
+        "INSERT INTO servers (name, icon_url, owner_id) VALUES ($1, $2, $3) RETURNING {COLUMNS}"
+    ))
+    .bind(name)
+    .bind(icon_url)
+    .bind(owner_id)
+    .fetch_one(db)
+    .await?;
+    Ok(server)
+}
+
+pub async fn add_member<'e>(db: impl PgExecutor<'e>, server_id: Uuid, user_id: Uuid) -> Result<()> {
+    sqlx::query("INSERT INTO server_members (server_id, user_id) VALUES ($1, $2)")
+        .bind(server_id)
+        .bind(user_id)
+        .execute(db)
+        .await?;
+    Ok(())
+}
+
+/// Permission bits `user_id` holds through `@everyone` and their roles, or
+/// `None` if they are not a member. Ownership is not reflected.
+pub async fn member_permissions<'e>(db: impl PgExecutor<'e>, server_id: Uuid, user_id: Uuid) -> Result<Option<i64>> {
+    let granted = sqlx::query_scalar(
+        "SELECT COALESCE((\
+             SELECT BIT_OR(r.permissions) FROM roles r \
+             LEFT JOIN member_roles mr ON mr.role_id = r.id AND mr.member_id = sm.id \
+             WHERE r.server_id = sm.server_id AND (r.name = '@everyone' OR mr.id IS NOT NULL)\
+         ), 0)::BIGINT \
+         FROM server_members sm WHERE sm.server_id = $1 AND sm.user_id = $2",
+    )
+    .bind(server_id)
+    .bind(user_id)
+    .fetch_optional(db)
+    .await?;
+    Ok(granted)
+}
+
+/// Hands the server to `new_owner_id` if `owner_id` still owns it and the
+/// new owner is a member. Returns the updated row.
+pub async fn transfer_ownership<'e>(
+    db: impl PgExecutor<'e>,
+    id: Uuid,
+    owner_id: Uuid,
+    new_owner_id: Uuid,
+) -> Result<Option<Server>> {
+    let server = sqlx::query_as(&format!(
+        "UPDATE servers SET owner_id = $3 \
+         WHERE id = $1 AND owner_id = $2 \
+           AND EXISTS(SELECT 1 FROM server_members WHERE server_id = $1 AND user_id = $3) \
+         RETURNING {COLUMNS}"
+    ))
+    .bind(id)
+    .bind(owner_id)
+    .bind(new_owner_id)
+    .fetch_optional(db)
+    .await?;
+    Ok(server)
+}
+
+/// Applies the provided fields and returns the updated row.
+pub async fn update<'e>(
+    db: impl PgExecutor<'e>,
+    id: Uuid,
+    name: Option<&str>,
+    icon_url: Option<&str>,
+) -> Result<Option<Server>> {
+    let server = sqlx::query_as(&format!(
+        "UPDATE servers SET name = COALESCE($2, name), icon_url = COALESCE($3, icon_url) \
+         WHERE id = $1 RETURNING {COLUMNS}"
+    ))
+    .bind(id)
+    .bind(name)
+    .bind(icon_url)
+    .fetch_optional(db)
+    .await?;
+    Ok(server)
+}
+
+/// Sets a member's per-server overrides; `None` leaves a field as is and
+/// `Some(None)` clears it. Returns `None` if the user is not a member.
+pub async fn update_member_profile<'e>(
+    db: impl PgExecutor<'e>,
+    server_id: Uuid,
+    user_id: Uuid,
+    nickname: Option<Option<&str>>,
+    avatar_url: Option<Option<&str>>,
+) -> Result<Option<ServerProfile>> {
+    let profile = sqlx::query_as(
+        "UPDATE server_members SET \
+             nickname = CASE WHEN $3 THEN $4 ELSE nickname END, \
+             avatar_url = CASE WHEN $5 THEN $6 ELSE avatar_url END \
+         WHERE server_id = $1 AND user_id = $2 \
+         RETURNING server_id, user_id, nickname, avatar_url",
+    )
+    .bind(server_id)
+    .bind(user_id)
+    .bind(nickname.is_some())
+    .bind(nickname.flatten())
+    .bind(avatar_url.is_some())
+    .bind(avatar_url.flatten())
+    .fetch_optional(db)
+    .await?;
+    Ok(profile)
+}
+
+pub async fn delete<'e>(db: impl PgExecutor<'e>, id: Uuid) -> Result<bool> {
+    let result = sqlx::query("DELETE FROM servers WHERE id = $1")
+        .bind(id)
+        .execute(db)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
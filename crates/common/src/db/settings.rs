@@ -0,0 +1,43 @@
+use sqlx::{types::Json, PgExecutor};
+use uuid::Uuid;
+
+use crate::{error::Result, settings::UserSettings};
+
+/// The user's settings and their version; defaults at version 0 if never
+/// saved.
+pub async fn find<'e>(db: impl PgExecutor<'e>, user_id: Uuid) -> Result<(UserSettings, i64)> {
+    let row: Option<(Json<UserSettings>, i64)> =
+        sqlx::query_as("SELECT settings, version FROM user_settings WHERE user_id = $1")
+            .bind(user_id)
+            .fetch_optional(db)
+            .await?;
+    Ok(row.map(|(Json(settings), version)| (settings, version)).unwrap_or_default())
+}
+
+/// Like [`find`], but locks the row for the rest of the transaction,
+/// creating it first if needed.
+pub async fn find_for_update(tx: &mut sqlx::PgConnection, user_id: Uuid) -> Result<(UserSettings, i64)> {
+    sqlx::query("INSERT INTO user_settings (user_id) VALUES ($1) ON CONFLICT (user_id) DO NOTHING")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    let (Json(settings), version): (Json<UserSettings>, i64) =
+        sqlx::query_as("SELECT settings, version FROM user_settings WHERE user_id = $1 FOR UPDATE")
+            .bind(user_id)
+            .fetch_one(&mut *tx)
+            .await?;
+    Ok((settings, version))
+}
+
+/// Stores `settings` and returns the new version.
+pub async fn save<'e>(db: impl PgExecutor<'e>, user_id: Uuid, settings: &UserSettings) -> Result<i64> {
+    let version = sqlx::query_scalar(
+        "UPDATE user_settings SET settings = $2, version = version + 1, updated_at = NOW() \
+         WHERE user_id = $1 RETURNING version",
+    )
+    .bind(user_id)
+    .bind(Json(settings))
+    .fetch_one(db)
+    .await?;
+    Ok(version)
+}
@@ -0,0 +1,205 @@
+use sqlx::{PgExecutor, Postgres, QueryBuilder};
+use uuid::Uuid;
+
+use crate::{error::Result, models::{User, UserSearchResult, UserStatus}};
+
+const COLUMNS: &str = "id, username, email, display_name, avatar_url, bio, banner_url, pronouns, accent_color, status, created_at";
+
+pub struct NewUser<'a> {
+    pub username: &'a str,
+    pub email: &'a str,
+    pub password_hash: &'a str,
+    pub display_name: &'a str,
+}
+
+/// Profile fields to change. `None` leaves a field as is; `Some(None)`
+/// clears a nullable one.
+#[derive(Debug, Default)]
+pub struct ProfileUpdate<'a> {
+    pub display_name: Option<&'a str>,
+    pub avatar_url: Option<Option<&'a str>>,
+    pub bio: Option<Option<&'a str>>,
+    pub banner_url: Option<Option<&'a str>>,
+    pub pronouns: Option<Option<&'a str>>,
+    pub accent_color: Option<Option<i32>>,
+}
+
+pub async fn find_by_id<'e>(db: impl PgExecutor<'e>, id: Uuid) -> Result<Option<User>> {
+    let user = sqlx::query_as(&format!("SELECT {COLUMNS} FROM users WHERE id = $1"))
+        .bind(id)
+        .fetch_optional(db)
+        .await?;
+    Ok(user)
+}
+
+pub async fn find_by_email<'e>(db: impl PgExecutor<'e>, email: &str) -> Result<Option<User>> {
+    let user = sqlx::query_as(&format!("SELECT {COLUMNS} FROM users WHERE email = $1"))
+        .bind(email)
+        .fetch_optional(db)
+        .await?;
+    Ok(user)
+}
+
+pub async fn find_by_username<'e>(db: impl PgExecutor<'e>, username: &str) -> Result<Option<User>> {
+    let user = sqlx::query_as(&format!("SELECT {COLUMNS} FROM users WHERE username = $1"))
+        .bind(username)
+        .fetch_optional(db)
+        .await?;
+    Ok(user)
+}
+
+pub async fn password_hash<'e>(db: impl PgExecutor<'e>, id: Uuid) -> Result<Option<String>> {
+    let hash = sqlx::query_scalar("SELECT password_hash FROM users WHERE id = $1")
+        .bind(id)
+        .fetch_optional(db)
+        .await?;
+    Ok(hash)
+}
+
+pub async fn create<'e>(db: impl PgExecutor<'e>, user: NewUser<'_>) -> Result<User> {
+    let user = sqlx::query_as(&format!(
+        "INSERT INTO users (username, email, password_hash, display_name) \
+         VALUES ($1, $2, $3, $4) RETURNING {COLUMNS}"
+    ))
+    .bind(user.username)
+    .bind(user.email)
+    .bind(user.password_hash)
+    .bind(user.display_name)
+    .fetch_one(db)
+    .await?;
+    Ok(user)
+}
+
+/// Returns the updated user, or `None` if there is no such user.
+pub async fn update_profile<'e>(
+    db: impl PgExecutor<'e>,
+    id: Uuid,
+    update: &ProfileUpdate<'_>,
+) -> Result<Option<User>> {
+    let mut query = QueryBuilder::<Postgres>::new("UPDATE users SET updated_at = NOW()");
+    if let Some(display_name) = update.display_name {
+        query.push(", display_name = ").push_bind(display_name);
+    }
+    if let Some(avatar_url) = update.avatar_url {
+        query.push(", avatar_url = ").push_bind(avatar_url);
+    }
+    if let Some(bio) = update.bio {
+        query.push(", bio = ").push_bind(bio);
+    }
+    if let Some(banner_url) = update.banner_url {
+        query.push(", banner_url = ").push_bind(banner_url);
+    }
+    if let Some(pronouns) = update.pronouns {
+        query.push(", pronouns = ").push_bind(pronouns);
+    }
+    if let Some(accent_color) = update.accent_color {
+        query.push(", accent_color = ").push_bind(accent_color);
+    }
+    query.push(" WHERE id = ").push_bind(id);
+    query.push(format!(" RETURNING {COLUMNS}"));
+
+    let user = query.build_query_as().fetch_optional(db).await?;
+    Ok(user)
+}
+
+pub async fn update_status<'e>(db: impl PgExecutor<'e>, id: Uuid, status: UserStatus) -> Result<()> {
+    sqlx::query("UPDATE users SET status = $2 WHERE id = $1")
+        .bind(id)
+        .bind(status)
+        .execute(db)
+        .await?;
+    Ok(())
+}
+
+/// Users whose username or display name starts with or resembles `query`,
+/// excluding `searcher_id`, anyone blocked in either direction, and users
+/// who opted out of discovery unless they are the searcher's friends.
+///
+/// Friends rank first, then users sharing a server with the searcher, then
+/// prefix matches, then by trigram similarity.
+pub async fn search<'e>(
+    db: impl PgExecutor<'e>,
+    searcher_id: Uuid,
+    query: &str,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<UserSearchResult>> {
+    let query = query.to_lowercase();
+    let prefix = format!("{}%", escape_like(&query));
+    let results = sqlx::query_as(
+        r#"
+        SELECT id, username, display_name, avatar_url, is_friend, mutual_servers
+        FROM (
+            SELECT u.id, u.username, u.display_name, u.avatar_url, u.discoverable,
+                   EXISTS(
+                       SELECT 1 FROM friendships f
+                       WHERE f.user_id = $1 AND f.friend_id = u.id AND f.status = 'accepted'
+                   ) AS is_friend,
+                   (
+                       SELECT COUNT(*) FROM server_members mine
+                       JOIN server_members theirs ON theirs.server_id = mine.server_id
+                       WHERE mine.user_id = $1 AND theirs.user_id = u.id
+                   ) AS mutual_servers,
+                   (lower(u.username) LIKE $3 OR lower(u.display_name) LIKE $3) AS is_prefix,
+                   GREATEST(similarity(lower(u.username), $2), similarity(lower(u.display_name), $2)) AS score
+            FROM users u
+            WHERE u.id <> $1
+              AND (lower(u.username) LIKE $3 OR lower(u.display_name) LIKE $3
+                   OR lower(u.username) % $2 OR lower(u.display_name) % $2)
+              AND NOT EXISTS(
+                  SELECT 1 FROM friendships b
+                  WHERE b.status = 'blocked'
+                    AND ((b.user_id = $1 AND b.friend_id = u.id) OR (b.user_id = u.id AND b.friend_id = $1))
+              )
+        ) hits
+        WHERE discoverable OR is_friend
+        ORDER BY is_friend DESC, mutual_servers > 0 DESC, is_prefix DESC, score DESC, username
+        LIMIT $4 OFFSET $5
+        "#,
+    )
+    .bind(searcher_id)
+    .bind(&query)
+    .bind(prefix)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(db)
+    .await?;
+    Ok(results)
+}
+
+pub async fn set_discoverable<'e>(db: impl PgExecutor<'e>, id: Uuid, discoverable: bool) -> Result<()> {
+    sqlx::query("UPDATE users SET discoverable = $2, updated_at = NOW() WHERE id = $1")
+        .bind(id)
+        .bind(discoverable)
+        .execute(db)
+        .await?;
+    Ok(())
+}
+
+pub async fn is_discoverable<'e>(db: impl PgExecutor<'e>, id: Uuid) -> Result<Option<bool>> {
+    let discoverable = sqlx::query_scalar("SELECT discoverable FROM users WHERE id = $1")
+        .bind(id)
+        .fetch_optional(db)
+        .await?;
+    Ok(discoverable)
+}
+
+pub async fn delete<'e>(db: impl PgExecutor<'e>, id: Uuid) -> Result<bool> {
+    let result = sqlx::query("DELETE FROM users WHERE id = $1")
+        .bind(id)
+        .execute(db)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Escapes `LIKE` wildcards so user input matches literally.
+fn escape_like(input: &str) -> String {
+    let mut escaped = String::with_capacity(input.len());
+    for c in input.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
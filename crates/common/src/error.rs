@@ -1,13 +1,170 @@
+//! Error model shared by every service.
+//!
+//! Handlers return [`AppError`]; its response body carries a stable
+//! [`ErrorCode`], a client-safe message, the request id and, for validation
+//! failures, per-field details:
+//!
+//! ```json
+//! {"error": {"code": "validation_failed", "message": "...", "request_id": "...",
+//!            "details": [{"field": "email", "code": "email", "message": null}]}}
+//! ```
+//!
+//! Handlers take bodies, path and query parameters through
+//! [`crate::extract`], whose rejections are `AppError`s too, so malformed
+//! requests get this shape rather than axum's plain-text rejections.
+//!
+//! Infrastructure failures are logged in full and reported to clients only
+//! as `internal_error`. The catalog of codes lives in `docs/error-codes.json`
+//! and is kept in sync with [`catalog`] by a test.
+
 use thiserror::Error;
 use axum::{
+    extract::Request,
     http::StatusCode,
+    middleware::Next,
     response::{IntoResponse, Response},
     Json,
 };
-use serde_json::json;
+use serde::Serialize;
+use serde_json::{json, Value};
+use utoipa::ToSchema;
+use validator::{ValidationErrors, ValidationErrorsKind};
+
+use crate::telemetry::REQUEST_ID_HEADER;
 
 pub type Result<T> = std::result::Result<T, AppError>;
 
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+/// Makes the request id available to [`AppError`] responses produced while
+/// handling this request.
+pub async fn scope_request_id(request: Request, next: Next) -> Response {
+    let request_id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_string();
+    REQUEST_ID.scope(request_id, next.run(request)).await
+}
+
+/// Stable, machine-readable error codes. Never rename a variant; add new
+/// ones instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    NotFound,
+    Unauthorized,
+    InvalidToken,
+    Forbidden,
+    BadRequest,
+    ValidationFailed,
+    Conflict,
+    PreconditionFailed,
+    RateLimited,
+    InternalError,
+}
+
+impl ErrorCode {
+    pub const ALL: &'static [ErrorCode] = &[
+        ErrorCode::NotFound,
+        ErrorCode::Unauthorized,
+        ErrorCode::InvalidToken,
+        ErrorCode::Forbidden,
+        ErrorCode::BadRequest,
+        ErrorCode::ValidationFailed,
+        ErrorCode::Conflict,
+        ErrorCode::PreconditionFailed,
+        ErrorCode::RateLimited,
+        ErrorCode::InternalError,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ErrorCode::NotFound => "not_found",
+            ErrorCode::Unauthorized => "unauthorized",
+            ErrorCode::InvalidToken => "invalid_token",
+            ErrorCode::Forbidden => "forbidden",
+            ErrorCode::BadRequest => "bad_request",
+            ErrorCode::ValidationFailed => "validation_failed",
+            ErrorCode::Conflict => "conflict",
+            ErrorCode::PreconditionFailed => "precondition_failed",
+            ErrorCode::RateLimited => "rate_limited",
+            ErrorCode::InternalError => "internal_error",
+        }
+    }
+
+    pub fn status(self) -> StatusCode {
+        match self {
+            ErrorCode::NotFound => StatusCode::NOT_FOUND,
+            ErrorCode::Unauthorized | ErrorCode::InvalidToken => StatusCode::UNAUTHORIZED,
+            ErrorCode::Forbidden => StatusCode::FORBIDDEN,
+            ErrorCode::BadRequest | ErrorCode::ValidationFailed => StatusCode::BAD_REQUEST,
+            ErrorCode::Conflict => StatusCode::CONFLICT,
+            ErrorCode::PreconditionFailed => StatusCode::PRECONDITION_FAILED,
+            ErrorCode::RateLimited => StatusCode::TOO_MANY_REQUESTS,
+            ErrorCode::InternalError => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            ErrorCode::NotFound => "The requested resource does not exist or is not visible to the caller.",
+            ErrorCode::Unauthorized => "Authentication is required or the credentials are wrong.",
+            ErrorCode::InvalidToken => "The access or refresh token is malformed, expired or revoked.",
+            ErrorCode::Forbidden => "The caller is authenticated but lacks permission for this action.",
+            ErrorCode::BadRequest => "The request is malformed or not allowed in the current state.",
+            ErrorCode::ValidationFailed => "One or more fields are invalid; see `details`.",
+            ErrorCode::Conflict => "The request conflicts with existing state, e.g. a duplicate.",
+            ErrorCode::PreconditionFailed => "The resource changed since the version given in `If-Match`; refetch and retry.",
+            ErrorCode::RateLimited => "Too many attempts; wait before retrying.",
+            ErrorCode::InternalError => "An unexpected server-side failure; retry or report the request id.",
+        }
+    }
+}
+
+/// Machine-readable catalog of every [`ErrorCode`].
+pub fn catalog() -> Value {
+    Value::Array(
+        ErrorCode::ALL
+            .iter()
+            .map(|code| {
+                json!({
+                    "code": code.as_str(),
+                    "status": code.status().as_u16(),
+                    "description": code.description(),
+                })
+            })
+            .collect(),
+    )
+}
+
+/// One invalid field, addressed by a dotted path such as `settings.theme`
+/// or `attachments[0].url`.
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct FieldError {
+    pub field: String,
+    pub code: String,
+    pub message: Option<String>,
+}
+
+/// Body of every error response.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ErrorResponse {
+    pub error: ErrorBody,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ErrorBody {
+    pub code: ErrorCode,
+    pub message: String,
+    pub request_id: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub details: Vec<FieldError>,
+}
+
 #[derive(Error, Debug)]
 pub enum AppError {
     #[error("Not found: {0}")]
@@ -25,6 +182,12 @@ pub enum AppError {
     #[error("Conflict: {0}")]
     Conflict(String),
 
+    #[error("Precondition failed: {0}")]
+    PreconditionFailed(String),
+
+    #[error("Too many requests: {0}")]
+    TooManyRequests(String),
+
     #[error("Internal server error: {0}")]
     InternalServerError(String),
 
@@ -37,40 +200,110 @@ pub enum AppError {
     #[error("Message queue error: {0}")]
     MessageQueue(String),
 
+    #[error("Storage error: {0}")]
+    Storage(String),
+
     #[error("JWT error: {0}")]
     Jwt(String),
 
     #[error("Validation error: {0}")]
     Validation(String),
+
+    #[error("Validation error: {0}")]
+    InvalidFields(ValidationErrors),
+}
+
+impl AppError {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            AppError::NotFound(_) => ErrorCode::NotFound,
+            AppError::Unauthorized(_) => ErrorCode::Unauthorized,
+            AppError::Forbidden(_) => ErrorCode::Forbidden,
+            AppError::BadRequest(_) => ErrorCode::BadRequest,
+            AppError::Conflict(_) => ErrorCode::Conflict,
+            AppError::PreconditionFailed(_) => ErrorCode::PreconditionFailed,
+            AppError::TooManyRequests(_) => ErrorCode::RateLimited,
+            AppError::Jwt(_) => ErrorCode::InvalidToken,
+            AppError::Validation(_) | AppError::InvalidFields(_) => ErrorCode::ValidationFailed,
+            AppError::InternalServerError(_)
+            | AppError::Database(_)
+            | AppError::Cache(_)
+            | AppError::MessageQueue(_)
+            | AppError::Storage(_) => ErrorCode::InternalError,
+        }
+    }
 }
 
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
-        let (status, error_message) = match self {
-            AppError::NotFound(msg) => (StatusCode::NOT_FOUND, msg),
-            AppError::Unauthorized(msg) => (StatusCode::UNAUTHORIZED, msg),
-            AppError::Forbidden(msg) => (StatusCode::FORBIDDEN, msg),
-            AppError::BadRequest(msg) => (StatusCode::BAD_REQUEST, msg),
-            AppError::Conflict(msg) => (StatusCode::CONFLICT, msg),
-            AppError::Database(msg) | 
-            AppError::Cache(msg) | 
-            AppError::MessageQueue(msg) => {
-                tracing::error!("Internal error: {}", msg);
-                (StatusCode::INTERNAL_SERVER_ERROR, "Internal server error".to_string())
+        let code = self.code();
+        let request_id = REQUEST_ID.try_with(|id| id.clone()).ok().filter(|id| !id.is_empty());
+
+        let mut details = Vec::new();
+        let message = match self {
+            AppError::NotFound(msg)
+            | AppError::Unauthorized(msg)
+            | AppError::Forbidden(msg)
+            | AppError::BadRequest(msg)
+            | AppError::Conflict(msg)
+            | AppError::PreconditionFailed(msg)
+            | AppError::TooManyRequests(msg)
+            | AppError::Validation(msg) => msg,
+            AppError::InvalidFields(errors) => {
+                flatten_field_errors(&errors, "", &mut details);
+                "Request validation failed".to_string()
             }
-            AppError::Jwt(msg) => (StatusCode::UNAUTHORIZED, msg),
-            AppError::Validation(msg) => (StatusCode::BAD_REQUEST, msg),
-            AppError::InternalServerError(msg) => {
-                tracing::error!("Internal error: {}", msg);
-                (StatusCode::INTERNAL_SERVER_ERROR, msg)
+            AppError::Jwt(msg) => {
+                tracing::debug!("Rejected token: {}", msg);
+                "Invalid or expired token".to_string()
             }
+            err @ (AppError::InternalServerError(_)
+            | AppError::Database(_)
+            | AppError::Cache(_)
+            | AppError::MessageQueue(_)
+            | AppError::Storage(_)) => {
+                tracing::error!(request_id = request_id.as_deref(), "Internal error: {}", err);
+                "Internal server error".to_string()
+            }
+        };
+
+        let body = ErrorResponse {
+            error: ErrorBody {
+                code,
+                message,
+                request_id,
+                details,
+            },
         };
+        (code.status(), Json(body)).into_response()
+    }
+}
 
-        let body = Json(json!({
-            "error": error_message
-        }));
+fn flatten_field_errors(errors: &ValidationErrors, prefix: &str, out: &mut Vec<FieldError>) {
+    let mut fields: Vec<_> = errors.errors().iter().collect();
+    fields.sort_by_key(|(field, _)| *field);
 
-        (status, body).into_response()
+    for (field, kind) in fields {
+        let path = if prefix.is_empty() {
+            field.to_string()
+        } else {
+            format!("{}.{}", prefix, field)
+        };
+        match kind {
+            ValidationErrorsKind::Field(errors) => {
+                out.extend(errors.iter().map(|error| FieldError {
+                    field: path.clone(),
+                    code: error.code.to_string(),
+                    message: error.message.as_ref().map(|message| message.to_string()),
+                }));
+            }
+            ValidationErrorsKind::Struct(nested) => flatten_field_errors(nested, &path, out),
+            ValidationErrorsKind::List(items) => {
+                for (index, nested) in items {
+                    flatten_field_errors(nested, &format!("{}[{}]", path, index), out);
+                }
+            }
+        }
     }
 }
 
@@ -91,3 +324,36 @@ impl From<jsonwebtoken::errors::Error> for AppError {
         AppError::Jwt(err.to_string())
     }
 }
+
+impl From<ValidationErrors> for AppError {
+    fn from(err: ValidationErrors) -> Self {
+        AppError::InvalidFields(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use validator::ValidationError;
+
+    #[test]
+    fn test_catalog_matches_docs() {
+        let documented: Value =
+            serde_json::from_str(include_str!("../../../docs/error-codes.json")).unwrap();
+        assert_eq!(documented, catalog(), "regenerate docs/error-codes.json from error::catalog()");
+    }
+
+    #[test]
+    fn test_field_errors_are_flattened_with_paths() {
+        let mut nested = ValidationErrors::new();
+        nested.add("theme", ValidationError::new("length"));
+        let mut errors = ValidationErrors::new();
+        errors.add("email", ValidationError::new("email"));
+        let errors = ValidationErrors::merge(Err(errors), "settings", Err(nested)).unwrap_err();
+
+        let mut details = Vec::new();
+        flatten_field_errors(&errors, "", &mut details);
+        let fields: Vec<_> = details.iter().map(|d| (d.field.as_str(), d.code.as_str())).collect();
+        assert_eq!(fields, [("email", "email"), ("settings.theme", "length")]);
+    }
+}
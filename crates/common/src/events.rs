@@ -2,6 +2,8 @@ use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
 
+use crate::settings::UserSettings;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum Event {
@@ -14,6 +16,9 @@ pub enum Event {
     FriendAdded(FriendEvent),
     FriendRemoved(FriendEvent),
     UserBlocked(UserBlockedEvent),
+    UserSettingsUpdated(UserSettingsUpdatedEvent),
+    UserNoteUpdated(UserNoteUpdatedEvent),
+    DataExportReady(DataExportReadyEvent),
     
     // Server Events
     ServerCreated(ServerEvent),
@@ -67,8 +72,56 @@ pub struct UserDeletedEvent {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UserProfileUpdatedEvent {
     pub user_id: Uuid,
+    /// Set when only the profile within this server changed.
+    #[serde(default)]
+    pub server_id: Option<Uuid>,
+    /// Names of the fields that changed. A listed field whose value below is
+    /// `None` was cleared.
+    #[serde(default)]
+    pub changed: Vec<String>,
     pub display_name: Option<String>,
     pub avatar_url: Option<String>,
+    #[serde(default)]
+    pub bio: Option<String>,
+    #[serde(default)]
+    pub banner_url: Option<String>,
+    #[serde(default)]
+    pub pronouns: Option<String>,
+    #[serde(default)]
+    pub accent_color: Option<i32>,
+    #[serde(default)]
+    pub nickname: Option<String>,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Carries the whole document so sessions replace theirs rather than
+/// merging.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserSettingsUpdatedEvent {
+    pub user_id: Uuid,
+    pub version: i64,
+    pub settings: UserSettings,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// A user's private note or nickname for someone changed. Only sent to the
+/// owner's own sessions; `None` fields are cleared.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserNoteUpdatedEvent {
+    pub user_id: Uuid,
+    pub target_id: Uuid,
+    pub note: Option<String>,
+    pub nickname: Option<String>,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// A requested data export finished. Events are kept in the stream and can
+/// be replayed, so no download link is sent; the owner fetches one from
+/// `GET /users/@me/exports/{export_id}`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DataExportReadyEvent {
+    pub user_id: Uuid,
+    pub export_id: Uuid,
     pub timestamp: DateTime<Utc>,
 }
 
@@ -123,8 +176,13 @@ pub struct ChannelDeletedEvent {
 pub struct MessageEvent {
     pub message_id: Uuid,
     pub channel_id: Option<Uuid>,
+    #[serde(default)]
+    pub dm_id: Option<Uuid>,
     pub author_id: Uuid,
     pub content: String,
+    /// Users to notify; excludes anyone who has blocked the author.
+    #[serde(default)]
+    pub mentions: Vec<Uuid>,
     pub timestamp: DateTime<Utc>,
 }
 
@@ -207,6 +265,9 @@ impl Event {
             Event::FriendAdded(_) => "user.friend.added",
             Event::FriendRemoved(_) => "user.friend.removed",
             Event::UserBlocked(_) => "user.blocked",
+            Event::UserSettingsUpdated(_) => "user.settings.updated",
+            Event::UserNoteUpdated(_) => "user.note.updated",
+            Event::DataExportReady(_) => "user.export.ready",
             Event::ServerCreated(_) => "server.created",
             Event::ServerUpdated(_) => "server.updated",
             Event::ServerDeleted(_) => "server.deleted",
@@ -0,0 +1,173 @@
+//! Drop-in replacements for axum's `Json`, `Path`, `Query` and `Multipart`
+//! extractors that reject with [`AppError`], so a malformed body or a
+//! non-UUID path id gets the same error body as every other failure
+//! instead of axum's plain-text one.
+//!
+//! Input that cannot be read at all, such as invalid JSON, a missing
+//! `Content-Type` or an unparsable path segment, is `bad_request`. Input
+//! that parses but has the wrong shape, such as a missing field or a string
+//! where a number belongs, is `validation_failed`.
+
+use axum::{
+    async_trait,
+    extract::{
+        rejection::{JsonRejection, PathRejection, QueryRejection},
+        FromRequest, FromRequestParts, Request,
+    },
+    http::request::Parts,
+    response::{IntoResponse, Response},
+};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::error::AppError;
+
+/// JSON request body, or a JSON response like [`axum::Json`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Json<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequest<S> for Json<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        match axum::Json::<T>::from_request(req, state).await {
+            Ok(axum::Json(value)) => Ok(Self(value)),
+            Err(JsonRejection::JsonDataError(e)) => Err(AppError::Validation(e.body_text())),
+            Err(rejection) => Err(AppError::BadRequest(rejection.body_text())),
+        }
+    }
+}
+
+impl<T: Serialize> IntoResponse for Json<T> {
+    fn into_response(self) -> Response {
+        axum::Json(self.0).into_response()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Path<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequestParts<S> for Path<T>
+where
+    T: DeserializeOwned + Send,
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        match axum::extract::Path::<T>::from_request_parts(parts, state).await {
+            Ok(axum::extract::Path(value)) => Ok(Self(value)),
+            Err(PathRejection::FailedToDeserializePathParams(e)) => Err(AppError::BadRequest(e.body_text())),
+            // The route and the handler disagree about the parameters.
+            Err(rejection) => Err(AppError::InternalServerError(rejection.body_text())),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Query<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequestParts<S> for Query<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        match axum::extract::Query::<T>::from_request_parts(parts, state).await {
+            Ok(axum::extract::Query(value)) => Ok(Self(value)),
+            Err(QueryRejection::FailedToDeserializeQueryString(e)) => Err(AppError::Validation(e.body_text())),
+            Err(rejection) => Err(AppError::BadRequest(rejection.body_text())),
+        }
+    }
+}
+
+/// A `multipart/form-data` body; see [`axum::extract::Multipart`].
+pub struct Multipart(pub axum::extract::Multipart);
+
+#[async_trait]
+impl<S> FromRequest<S> for Multipart
+where
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        axum::extract::Multipart::from_request(req, state)
+            .await
+            .map(Self)
+            .map_err(|rejection| AppError::BadRequest(rejection.body_text()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        body::Body,
+        http::{header::CONTENT_TYPE, StatusCode},
+        routing::get,
+        Router,
+    };
+    use serde::Deserialize;
+    use tower::ServiceExt;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::error::ErrorCode;
+
+    #[derive(Debug, Deserialize)]
+    struct Named {
+        name: String,
+    }
+
+    async fn extract_json(content_type: &str, body: &'static str) -> Result<Json<Named>, AppError> {
+        let request = Request::builder()
+            .header(CONTENT_TYPE, content_type)
+            .body(Body::from(body))
+            .unwrap();
+        Json::<Named>::from_request(request, &()).await
+    }
+
+    #[tokio::test]
+    async fn test_json_rejections() {
+        let Json(named) = extract_json("application/json", r#"{"name": "hermes"}"#).await.unwrap();
+        assert_eq!(named.name, "hermes");
+
+        let cases = [
+            ("application/json", r#"{"name": 1}"#, ErrorCode::ValidationFailed),
+            ("application/json", r#"{"#, ErrorCode::BadRequest),
+            ("text/plain", r#"{"name": "hermes"}"#, ErrorCode::BadRequest),
+        ];
+        for (content_type, body, code) in cases {
+            let rejection = extract_json(content_type, body).await.unwrap_err();
+            assert_eq!(rejection.code(), code, "{} {}", content_type, body);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_path_rejection_is_a_json_error() {
+        let app = Router::new().route("/:id", get(|Path(id): Path<Uuid>| async move { id.to_string() }));
+        let response = app.oneshot(Request::builder().uri("/nope").body(Body::empty()).unwrap()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error"]["code"], "bad_request");
+    }
+
+    #[tokio::test]
+    async fn test_query_rejections() {
+        let (mut parts, _) = Request::builder().uri("/?before=nope").body(()).unwrap().into_parts();
+        let rejection = Query::<std::collections::HashMap<String, Uuid>>::from_request_parts(&mut parts, &())
+            .await
+            .unwrap_err();
+        assert_eq!(rejection.code(), ErrorCode::ValidationFailed);
+    }
+}
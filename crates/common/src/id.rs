@@ -0,0 +1,62 @@
+//! Time-ordered ids.
+//!
+//! Entity ids are UUIDv7: the first 48 bits are the Unix time in
+//! milliseconds and the rest is random, so ids sort by creation time, land
+//! at the right edge of B-tree indexes and double as pagination cursors.
+//! Postgres assigns them by default through `uuid_generate_v7()`; call
+//! [`generate`] when the id is needed before the row is written. Clients
+//! can recover the creation time from the first 12 hex digits.
+
+use chrono::{DateTime, Utc};
+use uuid::{Builder, Uuid};
+
+/// A new id, strictly greater than every id generated before it in this
+/// process.
+pub fn generate() -> Uuid {
+    Uuid::now_v7()
+}
+
+/// When `id` was generated, to the millisecond. `None` for ids that are not
+/// time-ordered, such as rows created before the switch to UUIDv7.
+pub fn created_at(id: Uuid) -> Option<DateTime<Utc>> {
+    if id.get_version_num() != 7 {
+        return None;
+    }
+    let (secs, nanos) = id.get_timestamp()?.to_unix();
+    DateTime::from_timestamp(secs as i64, nanos)
+}
+
+/// The smallest id generated at `at`, for turning a time into a cursor:
+/// `id >= lower_bound(t)` selects rows created at or after `t`.
+pub fn lower_bound(at: DateTime<Utc>) -> Uuid {
+    let millis = at.timestamp_millis().max(0) as u64;
+    Builder::from_unix_timestamp_millis(millis, &[0; 10]).into_uuid()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ids_sort_by_generation_and_carry_their_time() {
+        let before = Utc::now();
+        let ids: Vec<_> = (0..1000).map(|_| generate()).collect();
+        let after = Utc::now();
+
+        assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+        let created = created_at(ids[0]).unwrap();
+        assert!(created.timestamp_millis() >= before.timestamp_millis());
+        assert!(created <= after);
+        assert_eq!(created_at(Uuid::new_v4()), None);
+    }
+
+    #[test]
+    fn test_lower_bound_precedes_ids_from_the_same_millisecond() {
+        let id = generate();
+        let at = created_at(id).unwrap();
+
+        assert!(lower_bound(at) <= id);
+        assert!(lower_bound(at + chrono::Duration::milliseconds(1)) > id);
+        assert_eq!(created_at(lower_bound(at)), Some(at));
+    }
+}
@@ -1,48 +1,209 @@
 // Common library for Discord Clone
 // Shared types, utilities, and infrastructure code
 
+pub mod auth;
+pub mod blocks;
+pub mod config;
 pub mod error;
 pub mod events;
+pub mod extract;
+pub mod id;
 pub mod models;
 pub mod db;
 pub mod cache;
+pub mod typed_cache;
 pub mod message_queue;
+pub mod memory;
+pub mod metrics;
+pub mod openapi;
+pub mod password;
 pub mod jwt;
+pub mod rpc;
+pub mod server;
+pub mod settings;
+pub mod storage;
+pub mod telemetry;
 
 // Re-export commonly used types
+pub use config::Config;
 pub use error::{AppError, Result};
 pub use events::Event;
 
+use std::sync::Arc;
+
 use sqlx::PgPool;
 use redis::Client as RedisClient;
 use async_nats::Client as NatsClient;
+use tokio::sync::OnceCell;
+
+use cache::{CacheClient, KeyValueCache};
+use db::PoolSettings;
+use message_queue::{EventBus, MessageQueue};
+use storage::{ObjectStorage, StorageSettings};
 
-/// Application state shared across services
-#[derive(Clone)]
+/// Application state shared across services.
+///
+/// Each dependency is optional; services declare what they need through
+/// [`AppState::builder`] and reach it through the accessors, which fail with
+/// an internal error if the dependency was not configured.
+#[derive(Clone, Default)]
 pub struct AppState {
-    pub db: PgPool,
-    pub redis: RedisClient,
-    pub nats: NatsClient,
+    db: Option<PgPool>,
+    redis: Option<RedisClient>,
+    nats: Option<NatsClient>,
+    storage: Option<ObjectStorage>,
+    events: Option<Arc<dyn EventBus>>,
+    cache: Arc<OnceCell<Arc<dyn KeyValueCache>>>,
 }
 
 impl AppState {
-    pub async fn new(
-        database_url: &str,
-        redis_url: &str,
-        nats_url: &str,
-    ) -> Result<Self> {
-        let db = PgPool::connect(database_url)
-            .await
-            .map_err(|e| AppError::Database(e.to_string()))?;
+    pub fn builder() -> AppStateBuilder {
+        AppStateBuilder::default()
+    }
+
+    pub fn db(&self) -> Result<&PgPool> {
+        self.db.as_ref().ok_or_else(|| missing("Postgres"))
+    }
+
+    pub fn redis(&self) -> Result<&RedisClient> {
+        self.redis.as_ref().ok_or_else(|| missing("Redis"))
+    }
+
+    pub fn nats(&self) -> Result<&NatsClient> {
+        self.nats.as_ref().ok_or_else(|| missing("NATS"))
+    }
+
+    /// Postgres pool if this service declared one.
+    pub fn try_db(&self) -> Option<&PgPool> {
+        self.db.as_ref()
+    }
+
+    pub fn storage(&self) -> Result<&ObjectStorage> {
+        self.storage.as_ref().ok_or_else(|| missing("object storage"))
+    }
 
-        let redis = RedisClient::open(redis_url)
-            .map_err(|e| AppError::Cache(e.to_string()))?;
+    /// Event publisher: NATS unless replaced with
+    /// [`AppStateBuilder::event_bus`].
+    pub fn events(&self) -> Result<&Arc<dyn EventBus>> {
+        self.events.as_ref().ok_or_else(|| missing("NATS"))
+    }
+
+    /// Publishes `event` for a change that is already committed, so a
+    /// failure is logged rather than returned.
+    pub async fn publish_best_effort(&self, event: &Event) {
+        match self.events() {
+            Ok(events) => {
+                if let Err(e) = events.publish(event).await {
+                    tracing::error!("Failed to publish {}: {}", event.topic(), e);
+                }
+            }
+            Err(_) => tracing::warn!("No event bus configured; {} not published", event.topic()),
+        }
+    }
 
-        let nats = async_nats::connect(nats_url)
+    /// Key-value cache: Redis, connected on first call, unless replaced with
+    /// [`AppStateBuilder::cache`].
+    pub async fn cache(&self) -> Result<Arc<dyn KeyValueCache>> {
+        self.cache
+            .get_or_try_init(|| async {
+                let client = CacheClient::new(self.redis()?.clone()).await?;
+                Ok(Arc::new(client) as Arc<dyn KeyValueCache>)
+            })
             .await
-            .map_err(|e| AppError::MessageQueue(e.to_string()))?;
+            .cloned()
+    }
+
+    pub async fn run_migrations(&self) -> Result<()> {
+        tracing::info!("Applying database migrations");
+        db::run_migrations(self.db()?)
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))
+    }
+}
+
+fn missing(dependency: &str) -> AppError {
+    AppError::InternalServerError(format!("{} is not configured for this service", dependency))
+}
+
+/// Declares which dependencies an [`AppState`] holds. Nothing connects
+/// eagerly: the Postgres pool and NATS client connect in the background and
+/// Redis and object storage connect on first use.
+#[derive(Default)]
+pub struct AppStateBuilder {
+    postgres: Option<(String, PoolSettings)>,
+    redis: Option<String>,
+    nats: Option<String>,
+    storage: Option<StorageSettings>,
+    events: Option<Arc<dyn EventBus>>,
+    cache: Option<Arc<dyn KeyValueCache>>,
+}
+
+impl AppStateBuilder {
+    pub fn postgres(mut self, url: impl Into<String>, pool: PoolSettings) -> Self {
+        self.postgres = Some((url.into(), pool));
+        self
+    }
+
+    pub fn redis(mut self, url: impl Into<String>) -> Self {
+        self.redis = Some(url.into());
+        self
+    }
+
+    pub fn nats(mut self, url: impl Into<String>) -> Self {
+        self.nats = Some(url.into());
+        self
+    }
+
+    pub fn storage(mut self, settings: StorageSettings) -> Self {
+        self.storage = Some(settings);
+        self
+    }
+
+    /// Publishes events through `events` instead of NATS, e.g. an
+    /// [`InMemoryEventBus`](memory::InMemoryEventBus) in tests.
+    pub fn event_bus(mut self, events: Arc<dyn EventBus>) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// Uses `cache` instead of Redis, e.g. an
+    /// [`InMemoryCache`](memory::InMemoryCache) in tests.
+    pub fn cache(mut self, cache: Arc<dyn KeyValueCache>) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    pub async fn build(self) -> Result<AppState> {
+        let db = self
+            .postgres
+            .map(|(url, pool)| db::connect_lazy(&url, pool))
+            .transpose()?;
+
+        let redis = self
+            .redis
+            .map(|url| RedisClient::open(url.as_str()))
+            .transpose()?;
+
+        let nats = match self.nats {
+            Some(url) => Some(
+                async_nats::ConnectOptions::new()
+                    .retry_on_initial_connect()
+                    .connect(url)
+                    .await
+                    .map_err(|e| AppError::MessageQueue(e.to_string()))?,
+            ),
+            None => None,
+        };
+
+        let storage = self.storage.as_ref().map(ObjectStorage::new).transpose()?;
+
+        let events = self.events.or_else(|| {
+            nats.clone()
+                .map(|client| Arc::new(MessageQueue::new(client)) as Arc<dyn EventBus>)
+        });
+        let cache = Arc::new(OnceCell::new_with(self.cache));
 
-        Ok(Self { db, redis, nats })
+        Ok(AppState { db, redis, nats, storage, events, cache })
     }
 }
 
@@ -0,0 +1,449 @@
+//! In-memory [`EventBus`] and [`KeyValueCache`] for unit tests.
+//!
+//! Both are deterministic: events are kept in publish order, set members
+//! come back sorted and TTLs follow `tokio::time`, so a test can
+//! `tokio::time::pause()` and `advance()` past an expiry. Either can be
+//! told to fail, to exercise a handler's error paths:
+//!
+//! ```ignore
+//! let events = Arc::new(InMemoryEventBus::new());
+//! let state = AppState::builder().event_bus(events.clone()).build().await?;
+//! events.fail_next(1);
+//! assert!(handler(&state).await.is_err());
+//! assert!(events.published().is_empty());
+//! ```
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+
+use futures::future::{self, BoxFuture};
+use tokio::time::Instant;
+
+use crate::{
+    cache::{KeyValueCache, RateLimit},
+    error::{AppError, Result},
+    message_queue::EventBus,
+    Event,
+};
+
+/// Failure injection shared by the in-memory implementations.
+#[derive(Default)]
+struct Failures {
+    pending: AtomicUsize,
+    unavailable: AtomicBool,
+}
+
+impl Failures {
+    fn check(&self) -> std::result::Result<(), String> {
+        if self.unavailable.load(Ordering::SeqCst) {
+            return Err("simulated outage".to_string());
+        }
+        let failed = self
+            .pending
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+            .is_ok();
+        if failed {
+            Err("simulated failure".to_string())
+        } else {
+            Ok(())
+        }
+    }
+}
+
+fn ready<'a, T: Send + 'a>(result: Result<T>) -> BoxFuture<'a, Result<T>> {
+    Box::pin(future::ready(result))
+}
+
+/// Records published events instead of sending them.
+#[derive(Default)]
+pub struct InMemoryEventBus {
+    published: Mutex<Vec<Event>>,
+    failures: Failures,
+}
+
+impl InMemoryEventBus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Events published so far, oldest first.
+    pub fn published(&self) -> Vec<Event> {
+        self.published.lock().unwrap().clone()
+    }
+
+    /// Subjects of the events published so far, oldest first.
+    pub fn published_topics(&self) -> Vec<String> {
+        self.published.lock().unwrap().iter().map(Event::topic).collect()
+    }
+
+    /// Returns and forgets the events published so far.
+    pub fn take(&self) -> Vec<Event> {
+        std::mem::take(&mut *self.published.lock().unwrap())
+    }
+
+    /// Fails the next `count` publishes; failed events are not recorded.
+    pub fn fail_next(&self, count: usize) {
+        self.failures.pending.store(count, Ordering::SeqCst);
+    }
+
+    /// Fails every publish until called again with `false`.
+    pub fn set_unavailable(&self, unavailable: bool) {
+        self.failures.unavailable.store(unavailable, Ordering::SeqCst);
+    }
+}
+
+impl EventBus for InMemoryEventBus {
+    fn publish<'a>(&'a self, event: &'a Event) -> BoxFuture<'a, Result<()>> {
+        let result = self.failures.check().map_err(AppError::MessageQueue).map(|()| {
+            self.published.lock().unwrap().push(event.clone());
+        });
+        ready(result)
+    }
+}
+
+enum Value {
+    String(String),
+    Hash(HashMap<String, String>),
+    Set(BTreeSet<String>),
+}
+
+struct Entry {
+    value: Value,
+    expires_at: Option<Instant>,
+}
+
+/// A single-process stand-in for Redis with the same type rules: a hash
+/// command on a string key fails with `WRONGTYPE`, and emptied hashes and
+/// sets are removed.
+#[derive(Default)]
+pub struct InMemoryCache {
+    entries: Mutex<HashMap<String, Entry>>,
+    failures: Failures,
+}
+
+impl InMemoryCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Live keys, sorted.
+    pub fn keys(&self) -> Vec<String> {
+        let now = Instant::now();
+        let mut keys: Vec<_> = self
+            .entries
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, entry)| !entry.expired(now))
+            .map(|(key, _)| key.clone())
+            .collect();
+        keys.sort();
+        keys
+    }
+
+    /// Remaining time to live of `key`, if it exists and has one.
+    pub fn ttl(&self, key: &str) -> Option<Duration> {
+        let now = Instant::now();
+        self.entries
+            .lock()
+            .unwrap()
+            .get(key)
+            .filter(|entry| !entry.expired(now))
+            .and_then(|entry| entry.expires_at)
+            .map(|at| at - now)
+    }
+
+    /// Fails the next `count` commands.
+    pub fn fail_next(&self, count: usize) {
+        self.failures.pending.store(count, Ordering::SeqCst);
+    }
+
+    /// Fails every command until called again with `false`.
+    pub fn set_unavailable(&self, unavailable: bool) {
+        self.failures.unavailable.store(unavailable, Ordering::SeqCst);
+    }
+
+    /// Runs `f` against the live entries after checking for injected failures.
+    fn with<'a, T: Send + 'a>(
+        &self,
+        f: impl FnOnce(&mut HashMap<String, Entry>) -> Result<T>,
+    ) -> BoxFuture<'a, Result<T>> {
+        let result = self.failures.check().map_err(AppError::Cache).and_then(|()| {
+            let mut entries = self.entries.lock().unwrap();
+            let now = Instant::now();
+            entries.retain(|_, entry| !entry.expired(now));
+            f(&mut entries)
+        });
+        ready(result)
+    }
+}
+
+impl Entry {
+    fn expired(&self, now: Instant) -> bool {
+        self.expires_at.is_some_and(|at| at <= now)
+    }
+}
+
+fn wrong_type() -> AppError {
+    AppError::Cache("WRONGTYPE Operation against a key holding the wrong kind of value".to_string())
+}
+
+fn string<'e>(entries: &'e HashMap<String, Entry>, key: &str) -> Result<Option<&'e String>> {
+    match entries.get(key).map(|entry| &entry.value) {
+        None => Ok(None),
+        Some(Value::String(value)) => Ok(Some(value)),
+        Some(_) => Err(wrong_type()),
+    }
+}
+
+fn hash<'e>(entries: &'e HashMap<String, Entry>, key: &str) -> Result<Option<&'e HashMap<String, String>>> {
+    match entries.get(key).map(|entry| &entry.value) {
+        None => Ok(None),
+        Some(Value::Hash(hash)) => Ok(Some(hash)),
+        Some(_) => Err(wrong_type()),
+    }
+}
+
+fn set<'e>(entries: &'e HashMap<String, Entry>, key: &str) -> Result<Option<&'e BTreeSet<String>>> {
+    match entries.get(key).map(|entry| &entry.value) {
+        None => Ok(None),
+        Some(Value::Set(set)) => Ok(Some(set)),
+        Some(_) => Err(wrong_type()),
+    }
+}
+
+fn hash_mut<'e>(entries: &'e mut HashMap<String, Entry>, key: &str) -> Result<&'e mut HashMap<String, String>> {
+    let entry = entries.entry(key.to_string()).or_insert_with(|| Entry {
+        value: Value::Hash(HashMap::new()),
+        expires_at: None,
+    });
+    match &mut entry.value {
+        Value::Hash(hash) => Ok(hash),
+        _ => Err(wrong_type()),
+    }
+}
+
+fn set_mut<'e>(entries: &'e mut HashMap<String, Entry>, key: &str) -> Result<&'e mut BTreeSet<String>> {
+    let entry = entries.entry(key.to_string()).or_insert_with(|| Entry {
+        value: Value::Set(BTreeSet::new()),
+        expires_at: None,
+    });
+    match &mut entry.value {
+        Value::Set(set) => Ok(set),
+        _ => Err(wrong_type()),
+    }
+}
+
+/// Drops `key` if it holds an empty hash or set, as Redis does.
+fn remove_if_empty(entries: &mut HashMap<String, Entry>, key: &str) {
+    let empty = match entries.get(key).map(|entry| &entry.value) {
+        Some(Value::Hash(hash)) => hash.is_empty(),
+        Some(Value::Set(set)) => set.is_empty(),
+        _ => false,
+    };
+    if empty {
+        entries.remove(key);
+    }
+}
+
+impl KeyValueCache for InMemoryCache {
+    fn get<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<Option<String>>> {
+        self.with(|entries| Ok(string(entries, key)?.cloned()))
+    }
+
+    fn set<'a>(&'a self, key: &'a str, value: &'a str, ttl: Option<Duration>) -> BoxFuture<'a, Result<()>> {
+        self.with(|entries| {
+            entries.insert(
+                key.to_string(),
+                Entry {
+                    value: Value::String(value.to_string()),
+                    expires_at: ttl.map(|ttl| Instant::now() + ttl),
+                },
+            );
+            Ok(())
+        })
+    }
+
+    fn delete<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<()>> {
+        self.with(|entries| {
+            entries.remove(key);
+            Ok(())
+        })
+    }
+
+    fn expire<'a>(&'a self, key: &'a str, ttl: Duration) -> BoxFuture<'a, Result<()>> {
+        self.with(|entries| {
+            if let Some(entry) = entries.get_mut(key) {
+                entry.expires_at = Some(Instant::now() + ttl);
+            }
+            Ok(())
+        })
+    }
+
+    fn delete_prefix<'a>(&'a self, prefix: &'a str) -> BoxFuture<'a, Result<()>> {
+        self.with(|entries| {
+            entries.retain(|key, _| !key.starts_with(prefix));
+            Ok(())
+        })
+    }
+
+    fn hget<'a>(&'a self, key: &'a str, field: &'a str) -> BoxFuture<'a, Result<Option<String>>> {
+        self.with(|entries| Ok(hash(entries, key)?.and_then(|hash| hash.get(field).cloned())))
+    }
+
+    fn hgetall<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<HashMap<String, String>>> {
+        self.with(|entries| Ok(hash(entries, key)?.cloned().unwrap_or_default()))
+    }
+
+    fn hset<'a>(&'a self, key: &'a str, fields: &'a [(&'a str, &'a str)]) -> BoxFuture<'a, Result<()>> {
+        self.with(|entries| {
+            let hash = hash_mut(entries, key)?;
+            for (field, value) in fields {
+                hash.insert(field.to_string(), value.to_string());
+            }
+            remove_if_empty(entries, key);
+            Ok(())
+        })
+    }
+
+    fn hdel<'a>(&'a self, key: &'a str, field: &'a str) -> BoxFuture<'a, Result<()>> {
+        self.with(|entries| {
+            if hash(entries, key)?.is_some() {
+                hash_mut(entries, key)?.remove(field);
+                remove_if_empty(entries, key);
+            }
+            Ok(())
+        })
+    }
+
+    fn hincr<'a>(&'a self, key: &'a str, field: &'a str, delta: i64) -> BoxFuture<'a, Result<i64>> {
+        self.with(|entries| {
+            let value = hash_mut(entries, key)?.entry(field.to_string()).or_insert_with(|| "0".to_string());
+            let current: i64 = value
+                .parse()
+                .map_err(|_| AppError::Cache("ERR hash value is not an integer".to_string()))?;
+            let next = current + delta;
+            *value = next.to_string();
+            Ok(next)
+        })
+    }
+
+    fn sadd<'a>(&'a self, key: &'a str, member: &'a str) -> BoxFuture<'a, Result<bool>> {
+        self.with(|entries| Ok(set_mut(entries, key)?.insert(member.to_string())))
+    }
+
+    fn srem<'a>(&'a self, key: &'a str, member: &'a str) -> BoxFuture<'a, Result<bool>> {
+        self.with(|entries| {
+            if set(entries, key)?.is_none() {
+                return Ok(false);
+            }
+            let removed = set_mut(entries, key)?.remove(member);
+            remove_if_empty(entries, key);
+            Ok(removed)
+        })
+    }
+
+    fn smembers<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<Vec<String>>> {
+        self.with(|entries| {
+            Ok(set(entries, key)?
+                .map(|set| set.iter().cloned().collect())
+                .unwrap_or_default())
+        })
+    }
+
+    fn sismember<'a>(&'a self, key: &'a str, member: &'a str) -> BoxFuture<'a, Result<bool>> {
+        self.with(|entries| Ok(set(entries, key)?.is_some_and(|set| set.contains(member))))
+    }
+
+    fn rate_limit<'a>(&'a self, key: &'a str, limit: u64, window: Duration) -> BoxFuture<'a, Result<RateLimit>> {
+        self.with(|entries| {
+            let now = Instant::now();
+            let count = match string(entries, key)? {
+                Some(value) => value
+                    .parse::<u64>()
+                    .map_err(|_| AppError::Cache("ERR value is not an integer".to_string()))?
+                    + 1,
+                None => 1,
+            };
+            let entry = entries.entry(key.to_string()).or_insert_with(|| Entry {
+                value: Value::String(String::new()),
+                expires_at: Some(now + window),
+            });
+            entry.value = Value::String(count.to_string());
+            let reset_after = entry.expires_at.map_or(Duration::ZERO, |at| at - now);
+
+            Ok(RateLimit {
+                allowed: count <= limit,
+                remaining: limit.saturating_sub(count),
+                reset_after_ms: reset_after.as_millis() as u64,
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::PresenceEvent;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_cache_expires_keys_and_windows_with_tokio_time() {
+        let cache = InMemoryCache::new();
+        cache.set("session", "abc", Some(Duration::from_secs(10))).await.unwrap();
+
+        let first = cache.rate_limit("rl", 2, Duration::from_secs(60)).await.unwrap();
+        let second = cache.rate_limit("rl", 2, Duration::from_secs(60)).await.unwrap();
+        let third = cache.rate_limit("rl", 2, Duration::from_secs(60)).await.unwrap();
+        assert!(first.allowed && second.allowed && !third.allowed);
+        assert_eq!(third.reset_after_ms, 60_000);
+
+        tokio::time::advance(Duration::from_secs(10)).await;
+        assert_eq!(cache.get("session").await.unwrap(), None);
+
+        tokio::time::advance(Duration::from_secs(50)).await;
+        assert!(cache.rate_limit("rl", 2, Duration::from_secs(60)).await.unwrap().allowed);
+    }
+
+    #[tokio::test]
+    async fn test_cache_enforces_types_and_injected_failures() {
+        let cache = InMemoryCache::new();
+        cache.set("name", "hermes", None).await.unwrap();
+        assert!(matches!(cache.sadd("name", "x").await, Err(AppError::Cache(_))));
+
+        cache.sadd("members", "b").await.unwrap();
+        cache.sadd("members", "a").await.unwrap();
+        assert_eq!(cache.smembers("members").await.unwrap(), ["a", "b"]);
+        cache.srem("members", "a").await.unwrap();
+        cache.srem("members", "b").await.unwrap();
+        assert_eq!(cache.keys(), ["name"]);
+
+        cache.fail_next(1);
+        assert!(cache.get("name").await.is_err());
+        assert_eq!(cache.get("name").await.unwrap().as_deref(), Some("hermes"));
+    }
+
+    #[tokio::test]
+    async fn test_event_bus_records_successful_publishes() {
+        let bus = InMemoryEventBus::new();
+        let event = Event::PresenceStatusChanged(PresenceEvent {
+            user_id: uuid::Uuid::new_v4(),
+            status: "online".to_string(),
+            custom_status: None,
+            timestamp: chrono::Utc::now(),
+        });
+
+        bus.fail_next(1);
+        assert!(bus.publish(&event).await.is_err());
+        bus.publish(&event).await.unwrap();
+
+        assert_eq!(bus.published_topics(), [event.topic()]);
+        assert_eq!(bus.take().len(), 1);
+        assert!(bus.published().is_empty());
+    }
+}
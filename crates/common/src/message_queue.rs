@@ -1,5 +1,60 @@
-use async_nats::Client;
-use crate::{Event, error::{AppError, Result}};
+use std::sync::Arc;
+
+use async_nats::{jetstream, Client};
+use futures::{future::BoxFuture, stream, StreamExt};
+use tracing::Instrument;
+use crate::{Event, error::{AppError, Result}, metrics, telemetry};
+
+/// JetStream stream that retains every published `Event` for replay.
+pub const EVENT_STREAM: &str = "EVENTS";
+
+/// Subjects captured by [`EVENT_STREAM`], one per `Event::topic` prefix.
+pub const EVENT_SUBJECTS: &[&str] = &[
+    "auth.>",
+    "user.>",
+    "server.>",
+    "channel.>",
+    "message.>",
+    "voice.>",
+    "stream.>",
+    "presence.>",
+];
+
+const REPLAY_PREFIX: &str = "replay";
+
+/// Subject a replayed event is delivered on for the named consumer.
+pub fn replay_subject(consumer: &str, topic: &str) -> String {
+    format!("{}.{}.{}", REPLAY_PREFIX, consumer, topic)
+}
+
+/// Control subject telling the named consumer to drop its derived state
+/// before a full rebuild.
+pub fn replay_reset_subject(consumer: &str) -> String {
+    format!("{}.{}.reset", REPLAY_PREFIX, consumer)
+}
+
+/// Publishes domain events. [`MessageQueue`] sends them over NATS;
+/// [`InMemoryEventBus`](crate::memory::InMemoryEventBus) records them for tests.
+pub trait EventBus: Send + Sync {
+    fn publish<'a>(&'a self, event: &'a Event) -> BoxFuture<'a, Result<()>>;
+}
+
+/// State a service derives from events, such as cached lookups or a search
+/// index. [`MessageQueue::spawn_projection`] applies live events on
+/// [`subjects`](Projection::subjects) and the events `admin-cli replay`
+/// sends to [`name`](Projection::name); `admin-cli rebuild` resets it first.
+///
+/// Every instance of a service receives every event, so `apply` must be
+/// idempotent.
+pub trait Projection: Send + Sync + 'static {
+    /// Consumer name to pass to `admin-cli replay --consumer`.
+    fn name(&self) -> &str;
+    /// Live subjects to apply, e.g. `user.blocked`.
+    fn subjects(&self) -> &[&str];
+    fn apply<'a>(&'a self, event: &'a Event) -> BoxFuture<'a, Result<()>>;
+    /// Drops all derived state before a rebuild replays the stream.
+    fn reset(&self) -> BoxFuture<'_, Result<()>>;
+}
 
 pub struct MessageQueue {
     client: Client,
@@ -15,9 +70,10 @@ impl MessageQueue {
         let payload = serde_json::to_vec(event)
             .map_err(|e| AppError::MessageQueue(e.to_string()))?;
         
-        self.client.publish(topic, payload.into())
+        self.client.publish_with_headers(topic.clone(), telemetry::nats_headers(), payload.into())
             .await
             .map_err(|e| AppError::MessageQueue(e.to_string()))?;
+        metrics::record_published(&topic);
         
         Ok(())
     }
@@ -27,4 +83,112 @@ impl MessageQueue {
             .await
             .map_err(|e| AppError::MessageQueue(e.to_string()))
     }
+
+    /// Subscribes to events replayed for `consumer`, including the reset marker.
+    pub async fn subscribe_replay(&self, consumer: &str) -> Result<async_nats::Subscriber> {
+        self.subscribe(&format!("{}.{}.>", REPLAY_PREFIX, consumer)).await
+    }
+
+    /// Feeds `projection` its live and replayed events, one at a time and
+    /// in the order received, until the connection closes. Failures are
+    /// logged and the message skipped.
+    pub async fn spawn_projection(&self, projection: Arc<dyn Projection>) -> Result<()> {
+        let mut subscribers = vec![self.subscribe_replay(projection.name()).await?];
+        for subject in projection.subjects() {
+            subscribers.push(self.subscribe(subject).await?);
+        }
+
+        let reset = replay_reset_subject(projection.name());
+        let mut messages = stream::select_all(subscribers);
+        tokio::spawn(async move {
+            while let Some(message) = messages.next().await {
+                metrics::record_consumed(&message.subject);
+                let span = telemetry::consumer_span(&message);
+                let delivered = deliver(&*projection, &reset, &message.subject, &message.payload);
+                if let Err(e) = delivered.instrument(span).await {
+                    tracing::error!("Projection {} failed on {}: {}", projection.name(), message.subject, e);
+                }
+            }
+        });
+        Ok(())
+    }
+
+    /// Creates the event stream if it does not exist yet.
+    pub async fn ensure_event_stream(&self) -> Result<jetstream::stream::Stream> {
+        jetstream::new(self.client.clone())
+            .get_or_create_stream(jetstream::stream::Config {
+                name: EVENT_STREAM.to_string(),
+                subjects: EVENT_SUBJECTS.iter().map(|s| s.to_string()).collect(),
+                ..Default::default()
+            })
+            .await
+            .map_err(|e| AppError::MessageQueue(e.to_string()))
+    }
+}
+
+impl EventBus for MessageQueue {
+    fn publish<'a>(&'a self, event: &'a Event) -> BoxFuture<'a, Result<()>> {
+        Box::pin(MessageQueue::publish(self, event))
+    }
+}
+
+async fn deliver(projection: &dyn Projection, reset: &str, subject: &str, payload: &[u8]) -> Result<()> {
+    if subject == reset {
+        return projection.reset().await;
+    }
+    let event = serde_json::from_slice::<Event>(payload).map_err(|e| AppError::MessageQueue(e.to_string()))?;
+    projection.apply(&event).await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use chrono::Utc;
+    use futures::future::ready;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::events::UserBlockedEvent;
+
+    #[derive(Default)]
+    struct Recorder(Mutex<Vec<String>>);
+
+    impl Projection for Recorder {
+        fn name(&self) -> &str {
+            "recorder"
+        }
+
+        fn subjects(&self) -> &[&str] {
+            &["user.blocked"]
+        }
+
+        fn apply<'a>(&'a self, event: &'a Event) -> BoxFuture<'a, Result<()>> {
+            self.0.lock().unwrap().push(event.topic());
+            Box::pin(ready(Ok(())))
+        }
+
+        fn reset(&self) -> BoxFuture<'_, Result<()>> {
+            self.0.lock().unwrap().push("reset".to_string());
+            Box::pin(ready(Ok(())))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_deliver_resets_and_applies() {
+        let projection = Recorder::default();
+        let reset = replay_reset_subject("recorder");
+        let event = serde_json::to_vec(&Event::UserBlocked(UserBlockedEvent {
+            user_id: Uuid::new_v4(),
+            blocked_user_id: Uuid::new_v4(),
+            timestamp: Utc::now(),
+        }))
+        .unwrap();
+
+        deliver(&projection, &reset, &reset, b"").await.unwrap();
+        deliver(&projection, &reset, &replay_subject("recorder", "user.blocked"), &event).await.unwrap();
+        deliver(&projection, &reset, "user.blocked", &event).await.unwrap();
+        assert!(deliver(&projection, &reset, "user.blocked", b"not json").await.is_err());
+        assert_eq!(*projection.0.lock().unwrap(), ["reset", "user.blocked", "user.blocked"]);
+    }
 }
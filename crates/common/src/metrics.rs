@@ -0,0 +1,124 @@
+//! Prometheus metrics.
+//!
+//! [`ServiceBuilder`](crate::server::ServiceBuilder) installs the recorder,
+//! wraps every route in [`track_http`] and serves `GET /metrics`. Shared
+//! infrastructure records into the same registry:
+//!
+//! - `http_requests_total` / `http_request_duration_seconds` by method, route and status
+//! - `db_pool_connections` / `db_pool_idle_connections`, sampled on scrape
+//! - `redis_commands_total` issued through [`CacheClient`](crate::cache::CacheClient)
+//! - `cache_requests_total` by namespace and hit/miss from [`Cache`](crate::typed_cache::Cache)
+//! - `nats_messages_published_total` / `nats_messages_consumed_total` by subject
+//!
+//! Services add domain metrics with the re-exported macros, describing them
+//! once at startup:
+//!
+//! ```ignore
+//! metrics::describe_gauge!("gateway_connections", "Open WebSocket connections");
+//! metrics::gauge!("gateway_connections").increment(1.0);
+//! ```
+
+use std::{
+    sync::{Arc, OnceLock},
+    time::Instant,
+};
+
+use axum::{
+    extract::{MatchedPath, Request, State},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use metrics_exporter_prometheus::{Matcher, PrometheusBuilder, PrometheusHandle};
+use sqlx::PgPool;
+
+pub use ::metrics::{
+    counter, describe_counter, describe_gauge, describe_histogram, gauge, histogram, Unit,
+};
+
+/// Latency buckets in seconds, from 5ms to 10s.
+const HTTP_BUCKETS: &[f64] = &[0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+static HANDLE: OnceLock<PrometheusHandle> = OnceLock::new();
+
+/// Installs the global Prometheus recorder. Safe to call more than once.
+pub fn install() -> PrometheusHandle {
+    HANDLE
+        .get_or_init(|| {
+            let handle = PrometheusBuilder::new()
+                .set_buckets_for_metric(
+                    Matcher::Full("http_request_duration_seconds".to_string()),
+                    HTTP_BUCKETS,
+                )
+                .expect("buckets are non-empty")
+                .install_recorder()
+                .expect("no other metrics recorder is installed");
+            describe();
+            handle
+        })
+        .clone()
+}
+
+fn describe() {
+    describe_counter!("http_requests_total", "HTTP requests handled");
+    describe_histogram!("http_request_duration_seconds", Unit::Seconds, "HTTP request latency");
+    describe_gauge!("db_pool_connections", "Open Postgres connections in the pool");
+    describe_gauge!("db_pool_idle_connections", "Idle Postgres connections in the pool");
+    describe_counter!("redis_commands_total", "Redis commands and scripts issued");
+    describe_counter!("cache_requests_total", "Typed cache lookups by namespace and result");
+    describe_counter!("nats_messages_published_total", "Messages published to NATS");
+    describe_counter!("nats_messages_consumed_total", "Messages consumed from NATS");
+}
+
+struct MetricsState {
+    handle: PrometheusHandle,
+    db: Option<PgPool>,
+}
+
+/// `GET /metrics` in the Prometheus text format.
+pub fn router(handle: PrometheusHandle, db: Option<PgPool>) -> Router {
+    Router::new()
+        .route("/metrics", get(render))
+        .with_state(Arc::new(MetricsState { handle, db }))
+}
+
+async fn render(State(state): State<Arc<MetricsState>>) -> impl IntoResponse {
+    if let Some(pool) = &state.db {
+        gauge!("db_pool_connections").set(pool.size() as f64);
+        gauge!("db_pool_idle_connections").set(pool.num_idle() as f64);
+    }
+    state.handle.render()
+}
+
+/// Records count and latency of every request, labelled by the matched
+/// route template rather than the raw path to keep cardinality bounded.
+pub async fn track_http(request: Request, next: Next) -> Response {
+    let started = Instant::now();
+    let method = request.method().to_string();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| "unmatched".to_string());
+
+    let response = next.run(request).await;
+
+    let labels = [
+        ("method", method),
+        ("route", route),
+        ("status", response.status().as_u16().to_string()),
+    ];
+    counter!("http_requests_total", &labels).increment(1);
+    histogram!("http_request_duration_seconds", &labels).record(started.elapsed().as_secs_f64());
+
+    response
+}
+
+pub(crate) fn record_published(subject: &str) {
+    counter!("nats_messages_published_total", "subject" => subject.to_string()).increment(1);
+}
+
+pub(crate) fn record_consumed(subject: &str) {
+    counter!("nats_messages_consumed_total", "subject" => subject.to_string()).increment(1);
+}
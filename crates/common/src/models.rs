@@ -1,20 +1,124 @@
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
+use sqlx::FromRow;
+use utoipa::ToSchema;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
 pub struct User {
     pub id: Uuid,
     pub username: String,
     pub email: String,
     pub display_name: String,
     pub avatar_url: Option<String>,
+    pub bio: Option<String>,
+    pub banner_url: Option<String>,
+    pub pronouns: Option<String>,
+    /// `0xRRGGBB`.
+    pub accent_color: Option<i32>,
     pub status: UserStatus,
     pub created_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// The fields of a user anyone may see; never includes the email.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct PublicUser {
+    pub id: Uuid,
+    pub username: String,
+    pub display_name: String,
+    pub avatar_url: Option<String>,
+}
+
+impl From<User> for PublicUser {
+    fn from(user: User) -> Self {
+        PublicUser {
+            id: user.id,
+            username: user.username,
+            display_name: user.display_name,
+            avatar_url: user.avatar_url,
+        }
+    }
+}
+
+/// Another user's profile as anyone may see it.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct UserProfile {
+    #[serde(flatten)]
+    pub user: PublicUser,
+    pub bio: Option<String>,
+    pub banner_url: Option<String>,
+    pub pronouns: Option<String>,
+    pub accent_color: Option<i32>,
+    /// Only connections the user has made visible.
+    pub connections: Vec<Connection>,
+    /// The viewer's private note about this user.
+    pub note: Option<String>,
+    /// The viewer's private nickname for this user.
+    pub nickname: Option<String>,
+}
+
+/// A server two users are both members of.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct MutualServer {
+    pub id: Uuid,
+    pub name: String,
+    pub icon_url: Option<String>,
+    /// The profile owner's nickname there.
+    pub nickname: Option<String>,
+}
+
+/// What the viewer and a profile's owner have in common.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct Mutuals {
+    pub mutual_servers: Vec<MutualServer>,
+    pub mutual_friends: Vec<PublicUser>,
+}
+
+/// A user's overrides within one server.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct ServerProfile {
+    pub server_id: Uuid,
+    pub user_id: Uuid,
+    pub nickname: Option<String>,
+    pub avatar_url: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type, ToSchema)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "varchar", rename_all = "lowercase")]
+pub enum ConnectionProvider {
+    Github,
+    Twitch,
+    Youtube,
+    Steam,
+    Spotify,
+    Xbox,
+}
+
+/// An account on another platform listed on a profile. Not verified with
+/// the provider.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct Connection {
+    pub provider: ConnectionProvider,
+    pub account_name: String,
+    pub visible: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A user search hit, with how the searcher knows them.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct UserSearchResult {
+    #[sqlx(flatten)]
+    #[serde(flatten)]
+    pub user: PublicUser,
+    pub is_friend: bool,
+    /// Servers both the searcher and this user are members of.
+    pub mutual_servers: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::Type, ToSchema)]
 #[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "varchar", rename_all = "lowercase")]
 pub enum UserStatus {
     Online,
     Offline,
@@ -22,7 +126,7 @@ pub enum UserStatus {
     Dnd,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
 pub struct Server {
     pub id: Uuid,
     pub name: String,
@@ -31,17 +135,19 @@ pub struct Server {
     pub created_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
 pub struct Channel {
     pub id: Uuid,
     pub server_id: Option<Uuid>,
     pub name: String,
+    #[sqlx(rename = "type")]
     pub channel_type: ChannelType,
     pub created_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::Type, ToSchema)]
 #[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "varchar", rename_all = "lowercase")]
 pub enum ChannelType {
     Text,
     Voice,
@@ -49,16 +155,107 @@ pub enum ChannelType {
     Announcement,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Status of a `friendships` row, read from `user_id`'s side.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type, ToSchema)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "varchar", rename_all = "lowercase")]
+pub enum FriendshipStatus {
+    Pending,
+    Accepted,
+    Blocked,
+}
+
+/// A friend, with the private nickname and note the listing user keeps for
+/// them.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct Friend {
+    #[sqlx(flatten)]
+    #[serde(flatten)]
+    pub user: PublicUser,
+    pub nickname: Option<String>,
+    pub note: Option<String>,
+}
+
+/// What one user privately keeps about another; only its owner sees it.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct UserNote {
+    /// The user the note is about.
+    pub user_id: Uuid,
+    pub note: Option<String>,
+    pub nickname: Option<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A pending friend request, seen from either end.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct FriendRequest {
+    /// The other party: the requester for incoming requests, the addressee
+    /// for outgoing ones.
+    #[sqlx(flatten)]
+    #[serde(flatten)]
+    pub user: PublicUser,
+    pub requested_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
 pub struct Message {
     pub id: Uuid,
     pub channel_id: Option<Uuid>,
+    pub dm_id: Option<Uuid>,
     pub author_id: Uuid,
     pub content: String,
     pub created_at: DateTime<Utc>,
     pub edited_at: Option<DateTime<Utc>>,
 }
 
+/// A one-to-one conversation. The pair is stored with
+/// `user_one_id < user_two_id`.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct DirectMessage {
+    pub id: Uuid,
+    pub user_one_id: Uuid,
+    pub user_two_id: Uuid,
+    pub created_at: DateTime<Utc>,
+}
+
+impl DirectMessage {
+    pub fn includes(&self, user_id: Uuid) -> bool {
+        self.user_one_id == user_id || self.user_two_id == user_id
+    }
+
+    /// The participant other than `user_id`.
+    pub fn other(&self, user_id: Uuid) -> Uuid {
+        if self.user_one_id == user_id { self.user_two_id } else { self.user_one_id }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type, ToSchema)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "varchar", rename_all = "lowercase")]
+pub enum ExportStatus {
+    Pending,
+    Running,
+    Completed,
+    Failed,
+}
+
+/// A requested copy of everything stored about a user.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct DataExport {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub status: ExportStatus,
+    /// Runs started so far; the job gives up after a few.
+    pub attempts: i32,
+    /// Storage key of the archive once completed.
+    #[serde(skip)]
+    pub object_key: Option<String>,
+    pub requested_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+    /// When the archive stops being downloadable.
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct JwtClaims {
     pub sub: Uuid,      // user_id
@@ -66,3 +263,14 @@ pub struct JwtClaims {
     pub exp: i64,       // expiration
     pub iat: i64,       // issued at
 }
+
+/// Permission bitflags stored in `roles.permissions`
+pub mod permissions {
+    pub const ADMINISTRATOR: i64 = 1 << 3;
+    pub const MANAGE_CHANNELS: i64 = 1 << 4;
+    pub const MANAGE_SERVER: i64 = 1 << 5;
+    pub const SEND_MESSAGES: i64 = 1 << 11;
+
+    /// Granted to `@everyone` in a new server.
+    pub const EVERYONE_DEFAULT: i64 = SEND_MESSAGES;
+}
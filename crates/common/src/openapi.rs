@@ -0,0 +1,97 @@
+//! OpenAPI 3.1 documents generated from handler annotations.
+//!
+//! Services build their routers with [`service_router`] and
+//! `utoipa_axum::routes!`, so a route cannot exist without its
+//! documentation, then call [`into_router`] to serve the result on
+//! `GET /openapi.json`. The gateway merges every service's document into
+//! one spec.
+
+use axum::{routing::get, Json, Router};
+use utoipa::{
+    openapi::{
+        security::{HttpAuthScheme, HttpBuilder, SecurityScheme},
+        OpenApi as OpenApiDoc,
+    },
+    Modify, OpenApi,
+};
+use utoipa_axum::router::OpenApiRouter;
+
+use crate::error::{ErrorBody, ErrorCode, ErrorResponse, FieldError};
+
+pub const SPEC_PATH: &str = "/openapi.json";
+
+/// Name of the bearer JWT scheme, for `security(("bearer" = []))`.
+pub const BEARER: &str = "bearer";
+
+#[derive(OpenApi)]
+#[openapi(
+    components(schemas(ErrorResponse, ErrorBody, ErrorCode, FieldError)),
+    modifiers(&BearerAuth)
+)]
+struct BaseDoc;
+
+struct BearerAuth;
+
+impl Modify for BearerAuth {
+    fn modify(&self, openapi: &mut OpenApiDoc) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            BEARER,
+            SecurityScheme::Http(
+                HttpBuilder::new()
+                    .scheme(HttpAuthScheme::Bearer)
+                    .bearer_format("JWT")
+                    .build(),
+            ),
+        );
+    }
+}
+
+/// Base document shared by every service: error schemas and bearer auth.
+pub fn base_doc(title: &str) -> OpenApiDoc {
+    let mut doc = BaseDoc::openapi();
+    doc.info.title = title.to_string();
+    doc.info.version = env!("CARGO_PKG_VERSION").to_string();
+    doc.info.description = None;
+    doc.info.license = None;
+    doc
+}
+
+pub fn service_router<S>(service: &str) -> OpenApiRouter<S>
+where
+    S: Send + Sync + Clone + 'static,
+{
+    OpenApiRouter::with_openapi(base_doc(service))
+}
+
+/// Splits off the document and serves it next to the routes.
+pub fn into_router<S>(router: OpenApiRouter<S>) -> Router<S>
+where
+    S: Send + Sync + Clone + 'static,
+{
+    let (router, doc) = router.split_for_parts();
+    router.route(SPEC_PATH, get(move || async move { Json(doc) }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use utoipa_axum::routes;
+
+    #[utoipa::path(get, path = "/things/{id}", responses((status = 404, body = ErrorResponse)))]
+    async fn get_thing() {}
+
+    #[test]
+    fn test_service_document_lists_routes_and_shared_schemas() {
+        let (_, doc) = service_router::<()>("test-service")
+            .routes(routes!(get_thing))
+            .split_for_parts();
+        let doc = serde_json::to_value(doc).unwrap();
+
+        assert_eq!(doc["openapi"], "3.1.0");
+        assert_eq!(doc["info"]["title"], "test-service");
+        assert!(doc["paths"]["/things/{id}"]["get"].is_object());
+        assert!(doc["components"]["schemas"]["ErrorResponse"].is_object());
+        assert!(doc["components"]["securitySchemes"][BEARER].is_object());
+    }
+}
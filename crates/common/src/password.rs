@@ -0,0 +1,39 @@
+//! Argon2 password hashes, stored in PHC string format in
+//! `users.password_hash`.
+//!
+//! Besides login, sensitive actions such as handing over a server ask the
+//! user to enter their password again and check it with [`verify`].
+
+use argon2::{
+    password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+
+use crate::error::{AppError, Result};
+
+pub fn hash(password: &str) -> Result<String> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| AppError::InternalServerError(format!("Hashing password failed: {}", e)))
+}
+
+/// Whether `password` matches `hash`. A stored value that is not a PHC
+/// string, such as the `!` of accounts without a password, never matches.
+pub fn verify(password: &str, hash: &str) -> bool {
+    PasswordHash::new(hash).is_ok_and(|parsed| Argon2::default().verify_password(password.as_bytes(), &parsed).is_ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify() {
+        let hashed = hash("hunter22").unwrap();
+        assert!(verify("hunter22", &hashed));
+        assert!(!verify("hunter23", &hashed));
+        assert!(!verify("hunter22", "!"));
+    }
+}
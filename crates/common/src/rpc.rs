@@ -0,0 +1,309 @@
+//! Request/reply RPC over NATS for synchronous inter-service calls.
+//!
+//! Each call is described by an [`RpcMethod`] that pins the subject together
+//! with its request and response types. Callers use [`RpcClient::call`], the
+//! owning service mounts handlers on an [`RpcRouter`] and serves it on a queue
+//! group so replicas share the load.
+
+use std::{collections::HashMap, future::Future, sync::Arc, time::Duration};
+
+use async_nats::{Client, RequestErrorKind};
+use futures::{future::BoxFuture, stream, StreamExt};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use tracing::Instrument;
+
+use crate::{
+    error::{AppError, Result},
+    metrics, telemetry,
+};
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A typed RPC endpoint.
+pub trait RpcMethod {
+    const SUBJECT: &'static str;
+    type Request: Serialize + DeserializeOwned + Send + 'static;
+    type Response: Serialize + DeserializeOwned + Send + 'static;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RpcErrorKind {
+    NotFound,
+    Unauthorized,
+    Forbidden,
+    BadRequest,
+    Conflict,
+    PreconditionFailed,
+    TooManyRequests,
+    Internal,
+    Database,
+    Cache,
+    MessageQueue,
+    Storage,
+    Jwt,
+    Validation,
+}
+
+/// Error returned by a remote handler, carried back to the caller.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RpcError {
+    pub kind: RpcErrorKind,
+    pub message: String,
+}
+
+impl From<AppError> for RpcError {
+    fn from(err: AppError) -> Self {
+        let (kind, message) = match err {
+            AppError::NotFound(msg) => (RpcErrorKind::NotFound, msg),
+            AppError::Unauthorized(msg) => (RpcErrorKind::Unauthorized, msg),
+            AppError::Forbidden(msg) => (RpcErrorKind::Forbidden, msg),
+            AppError::BadRequest(msg) => (RpcErrorKind::BadRequest, msg),
+            AppError::Conflict(msg) => (RpcErrorKind::Conflict, msg),
+            AppError::PreconditionFailed(msg) => (RpcErrorKind::PreconditionFailed, msg),
+            AppError::TooManyRequests(msg) => (RpcErrorKind::TooManyRequests, msg),
+            AppError::InternalServerError(msg) => (RpcErrorKind::Internal, msg),
+            AppError::Database(msg) => (RpcErrorKind::Database, msg),
+            AppError::Cache(msg) => (RpcErrorKind::Cache, msg),
+            AppError::MessageQueue(msg) => (RpcErrorKind::MessageQueue, msg),
+            AppError::Storage(msg) => (RpcErrorKind::Storage, msg),
+            AppError::Jwt(msg) => (RpcErrorKind::Jwt, msg),
+            AppError::Validation(msg) => (RpcErrorKind::Validation, msg),
+            AppError::InvalidFields(errors) => (RpcErrorKind::Validation, errors.to_string()),
+        };
+        Self { kind, message }
+    }
+}
+
+impl From<RpcError> for AppError {
+    fn from(err: RpcError) -> Self {
+        let msg = err.message;
+        match err.kind {
+            RpcErrorKind::NotFound => AppError::NotFound(msg),
+            RpcErrorKind::Unauthorized => AppError::Unauthorized(msg),
+            RpcErrorKind::Forbidden => AppError::Forbidden(msg),
+            RpcErrorKind::BadRequest => AppError::BadRequest(msg),
+            RpcErrorKind::Conflict => AppError::Conflict(msg),
+            RpcErrorKind::PreconditionFailed => AppError::PreconditionFailed(msg),
+            RpcErrorKind::TooManyRequests => AppError::TooManyRequests(msg),
+            RpcErrorKind::Internal => AppError::InternalServerError(msg),
+            RpcErrorKind::Database => AppError::Database(msg),
+            RpcErrorKind::Cache => AppError::Cache(msg),
+            RpcErrorKind::MessageQueue => AppError::MessageQueue(msg),
+            RpcErrorKind::Storage => AppError::Storage(msg),
+            RpcErrorKind::Jwt => AppError::Jwt(msg),
+            RpcErrorKind::Validation => AppError::Validation(msg),
+        }
+    }
+}
+
+/// Wire envelope for replies.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+enum RpcReply<T> {
+    Ok { data: T },
+    Error { error: RpcError },
+}
+
+#[derive(Clone)]
+pub struct RpcClient {
+    client: Client,
+    timeout: Duration,
+}
+
+impl RpcClient {
+    pub fn new(client: Client) -> Self {
+        Self {
+            client,
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub async fn call<M: RpcMethod>(&self, request: &M::Request) -> Result<M::Response> {
+        let payload = serde_json::to_vec(request)
+            .map_err(|e| AppError::MessageQueue(e.to_string()))?;
+
+        let request = async_nats::Request::new()
+            .headers(telemetry::nats_headers())
+            .payload(payload.into())
+            .timeout(Some(self.timeout));
+
+        let message = self
+            .client
+            .send_request(M::SUBJECT, request)
+            .await
+            .map_err(|e| match e.kind() {
+                RequestErrorKind::TimedOut => {
+                    AppError::MessageQueue(format!("rpc {} timed out", M::SUBJECT))
+                }
+                RequestErrorKind::NoResponders => {
+                    AppError::MessageQueue(format!("rpc {} has no responders", M::SUBJECT))
+                }
+                RequestErrorKind::Other => AppError::MessageQueue(e.to_string()),
+            })?;
+
+        let reply: RpcReply<M::Response> = serde_json::from_slice(&message.payload)
+            .map_err(|e| AppError::MessageQueue(e.to_string()))?;
+
+        match reply {
+            RpcReply::Ok { data } => Ok(data),
+            RpcReply::Error { error } => Err(error.into()),
+        }
+    }
+}
+
+type Handler<S> = Arc<dyn Fn(S, Vec<u8>) -> BoxFuture<'static, Vec<u8>> + Send + Sync>;
+
+/// Service-side dispatcher mapping subjects to typed handlers.
+pub struct RpcRouter<S> {
+    state: S,
+    handlers: HashMap<&'static str, Handler<S>>,
+}
+
+impl<S> RpcRouter<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    pub fn new(state: S) -> Self {
+        Self {
+            state,
+            handlers: HashMap::new(),
+        }
+    }
+
+    pub fn route<M, F, Fut>(mut self, handler: F) -> Self
+    where
+        M: RpcMethod,
+        F: Fn(S, M::Request) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<M::Response>> + Send + 'static,
+    {
+        let handler = Arc::new(handler);
+        let erased: Handler<S> = Arc::new(move |state, payload| {
+            let handler = handler.clone();
+            Box::pin(async move {
+                let reply = match serde_json::from_slice::<M::Request>(&payload) {
+                    Ok(request) => match handler(state, request).await {
+                        Ok(data) => RpcReply::Ok { data },
+                        Err(e) => RpcReply::Error { error: e.into() },
+                    },
+                    Err(e) => RpcReply::Error {
+                        error: AppError::BadRequest(e.to_string()).into(),
+                    },
+                };
+                serde_json::to_vec(&reply).unwrap_or_default()
+            })
+        });
+        self.handlers.insert(M::SUBJECT, erased);
+        self
+    }
+
+    /// Subscribes every registered subject on `queue_group` and answers
+    /// requests until the connection closes.
+    pub async fn serve(self, client: Client, queue_group: &str) -> Result<()> {
+        let mut subscribers = Vec::with_capacity(self.handlers.len());
+        for subject in self.handlers.keys() {
+            let subscriber = client
+                .queue_subscribe(*subject, queue_group.to_string())
+                .await
+                .map_err(|e| AppError::MessageQueue(e.to_string()))?;
+            subscribers.push(subscriber);
+        }
+
+        let mut messages = stream::select_all(subscribers);
+        while let Some(message) = messages.next().await {
+            let span = telemetry::consumer_span(&message);
+            let Some(reply_to) = message.reply else {
+                tracing::warn!("Dropping RPC message without reply subject on {}", message.subject);
+                continue;
+            };
+            let Some(handler) = self.handlers.get(message.subject.as_str()).cloned() else {
+                continue;
+            };
+            metrics::record_consumed(&message.subject);
+
+            let state = self.state.clone();
+            let client = client.clone();
+            tokio::spawn(async move {
+                let response = handler(state, message.payload.to_vec()).await;
+                if let Err(e) = client.publish(reply_to, response.into()).await {
+                    tracing::error!("Failed to send RPC reply: {}", e);
+                }
+            }.instrument(span));
+        }
+
+        Ok(())
+    }
+}
+
+/// Calls served by channel-service.
+pub mod channel {
+    use serde::{Deserialize, Serialize};
+    use uuid::Uuid;
+
+    use super::RpcMethod;
+
+    pub struct CheckMembership;
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct CheckMembershipRequest {
+        pub server_id: Uuid,
+        pub user_id: Uuid,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct CheckMembershipResponse {
+        pub is_member: bool,
+    }
+
+    impl RpcMethod for CheckMembership {
+        const SUBJECT: &'static str = "rpc.channel.check_membership";
+        type Request = CheckMembershipRequest;
+        type Response = CheckMembershipResponse;
+    }
+
+    pub struct CheckPermissions;
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct CheckPermissionsRequest {
+        pub channel_id: Uuid,
+        pub user_id: Uuid,
+        /// Required bits from [`crate::models::permissions`].
+        pub permissions: i64,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct CheckPermissionsResponse {
+        pub allowed: bool,
+    }
+
+    impl RpcMethod for CheckPermissions {
+        const SUBJECT: &'static str = "rpc.channel.check_permissions";
+        type Request = CheckPermissionsRequest;
+        type Response = CheckPermissionsResponse;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_round_trip() {
+        let reply: RpcReply<()> = RpcReply::Error {
+            error: AppError::Forbidden("missing permission".to_string()).into(),
+        };
+        let bytes = serde_json::to_vec(&reply).unwrap();
+
+        match serde_json::from_slice::<RpcReply<()>>(&bytes).unwrap() {
+            RpcReply::Error { error } => {
+                assert!(matches!(AppError::from(error), AppError::Forbidden(msg) if msg == "missing permission"));
+            }
+            RpcReply::Ok { .. } => panic!("expected error reply"),
+        }
+    }
+}
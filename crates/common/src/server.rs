@@ -0,0 +1,300 @@
+//! Shared service bootstrap.
+//!
+//! [`ServiceBuilder::run`] loads `.env` and [`Config`], initialises
+//! [`telemetry`](crate::telemetry),
+//! builds an [`AppState`] holding only the dependencies the service declared,
+//! creates the JetStream event stream when it uses NATS, mounts the health endpoints and serves the router until SIGINT/SIGTERM,
+//! then drains in-flight connections.
+//!
+//! - `GET /health/live` (and `/health`) answers as long as the process runs.
+//! - `GET /health/ready` checks each declared dependency, and turns 503 as
+//!   soon as shutdown starts so load balancers stop routing new traffic.
+//! - `GET /metrics` exposes Prometheus metrics, see [`crate::metrics`].
+
+use std::{
+    future::Future,
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use axum::{extract::State, http::StatusCode, middleware, routing::get, Json, Router};
+use serde_json::{json, Map, Value};
+use tokio_util::sync::CancellationToken;
+use tower_http::{
+    request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer},
+    trace::TraceLayer,
+};
+
+use crate::{
+    db, error,
+    message_queue::{MessageQueue, EVENT_STREAM},
+    metrics, telemetry, AppState, Config,
+};
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Dependencies {
+    postgres: bool,
+    redis: bool,
+    nats: bool,
+    storage: bool,
+}
+
+const CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+const DEFAULT_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(30);
+
+pub struct ServiceBuilder {
+    name: &'static str,
+    default_port: u16,
+    dependencies: Dependencies,
+    shutdown_timeout: Duration,
+}
+
+impl ServiceBuilder {
+    pub fn new(name: &'static str, default_port: u16) -> Self {
+        Self {
+            name,
+            default_port,
+            dependencies: Dependencies::default(),
+            shutdown_timeout: DEFAULT_SHUTDOWN_TIMEOUT,
+        }
+    }
+
+    /// Connects to Postgres and runs migrations when `run_migrations` is set.
+    pub fn postgres(mut self) -> Self {
+        self.dependencies.postgres = true;
+        self
+    }
+
+    pub fn redis(mut self) -> Self {
+        self.dependencies.redis = true;
+        self
+    }
+
+    pub fn nats(mut self) -> Self {
+        self.dependencies.nats = true;
+        self
+    }
+
+    /// Configures S3-compatible object storage from the `minio_*` keys.
+    pub fn storage(mut self) -> Self {
+        self.dependencies.storage = true;
+        self
+    }
+
+    /// How long to wait for open connections after a shutdown signal.
+    pub fn shutdown_timeout(mut self, timeout: Duration) -> Self {
+        self.shutdown_timeout = timeout;
+        self
+    }
+
+    /// Boots the service and serves the router returned by `build`.
+    pub async fn run<F>(self, build: F) -> anyhow::Result<()>
+    where
+        F: FnOnce(&Config, AppState) -> Router,
+    {
+        dotenvy::dotenv().ok();
+
+        let config = Config::load(self.name, self.default_port)?;
+        telemetry::init(&config.service_name, config.otel_exporter_otlp_endpoint.as_deref())?;
+
+        let deps = self.dependencies;
+        let mut builder = AppState::builder();
+        if deps.postgres {
+            builder = builder.postgres(&config.database_url, config.pool_settings());
+        }
+        if deps.redis {
+            builder = builder.redis(&config.redis_url);
+        }
+        if deps.nats {
+            builder = builder.nats(&config.nats_url);
+        }
+        if deps.storage {
+            builder = builder.storage(config.storage_settings());
+        }
+        let state = builder.build().await?;
+
+        if deps.postgres && config.run_migrations {
+            state.run_migrations().await?;
+        }
+        if deps.nats {
+            // Events still flow without JetStream; they just aren't retained for replay.
+            if let Err(e) = MessageQueue::new(state.nats()?.clone()).ensure_event_stream().await {
+                tracing::warn!("Could not create the {} stream: {}", EVENT_STREAM, e);
+            }
+        }
+
+        let health = Arc::new(HealthState {
+            state: state.clone(),
+            draining: AtomicBool::new(false),
+        });
+        let app = wrap(build(&config, state), health.clone());
+
+        let addr = SocketAddr::from(([0, 0, 0, 0], config.port));
+        let listener = tokio::net::TcpListener::bind(addr).await?;
+        tracing::info!("{} listening on {}", config.service_name, addr);
+
+        let shutdown = CancellationToken::new();
+        tokio::spawn({
+            let shutdown = shutdown.clone();
+            async move {
+                shutdown_signal().await;
+                tracing::info!("Shutdown signal received, draining connections");
+                health.draining.store(true, Ordering::SeqCst);
+                shutdown.cancel();
+            }
+        });
+
+        let server = axum::serve(listener, app).with_graceful_shutdown(shutdown.clone().cancelled_owned());
+        let deadline = async {
+            shutdown.cancelled().await;
+            tokio::time::sleep(self.shutdown_timeout).await;
+        };
+
+        tokio::select! {
+            result = server => result?,
+            _ = deadline => tracing::warn!(
+                "Connections still open after {:?}, shutting down anyway",
+                self.shutdown_timeout
+            ),
+        }
+
+        tracing::info!("{} stopped", config.service_name);
+        tokio::task::spawn_blocking(telemetry::shutdown).await?;
+        Ok(())
+    }
+}
+
+/// Mounts health and metrics endpoints on `router` and adds the request id,
+/// tracing and HTTP metrics layers, as [`ServiceBuilder::run`] does. Lets a
+/// service be served in-process without the bootstrap around it.
+pub fn app(router: Router, state: AppState) -> Router {
+    wrap(
+        router,
+        Arc::new(HealthState {
+            state,
+            draining: AtomicBool::new(false),
+        }),
+    )
+}
+
+fn wrap(router: Router, health: Arc<HealthState>) -> Router {
+    let db = health.state.try_db().cloned();
+    router
+        .merge(health_router(health))
+        .merge(metrics::router(metrics::install(), db))
+        .layer(middleware::from_fn(error::scope_request_id))
+        .layer(middleware::from_fn(metrics::track_http))
+        .layer(TraceLayer::new_for_http().make_span_with(telemetry::http_span))
+        .layer(PropagateRequestIdLayer::x_request_id())
+        .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid))
+}
+
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            tracing::error!("Failed to listen for Ctrl+C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                tracing::error!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+}
+
+struct HealthState {
+    state: AppState,
+    draining: AtomicBool,
+}
+
+fn health_router(health: Arc<HealthState>) -> Router {
+    Router::new()
+        .route("/health", get(live))
+        .route("/health/live", get(live))
+        .route("/health/ready", get(ready))
+        .with_state(health)
+}
+
+async fn live() -> StatusCode {
+    StatusCode::OK
+}
+
+async fn ready(State(health): State<Arc<HealthState>>) -> (StatusCode, Json<Value>) {
+    if health.draining.load(Ordering::SeqCst) {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(json!({ "status": "draining" })),
+        );
+    }
+
+    let mut checks = Map::new();
+
+    if let Some(pool) = &health.state.db {
+        checks.insert("postgres".to_string(), probe(db::health_check(pool)).await);
+    }
+
+    if let Some(redis) = health.state.redis.clone() {
+        checks.insert(
+            "redis".to_string(),
+            probe(async move {
+                let mut conn = redis.get_multiplexed_async_connection().await?;
+                redis::cmd("PING").query_async::<_, String>(&mut conn).await
+            })
+            .await,
+        );
+    }
+
+    if let Some(nats) = &health.state.nats {
+        checks.insert(
+            "nats".to_string(),
+            match nats.connection_state() {
+                async_nats::connection::State::Connected => json!("ok"),
+                other => json!(other.to_string()),
+            },
+        );
+    }
+
+    if let Some(storage) = &health.state.storage {
+        checks.insert("storage".to_string(), probe(storage.health_check()).await);
+    }
+
+    let healthy = checks.values().all(|check| check == "ok");
+    let status = if healthy { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+
+    (
+        status,
+        Json(json!({
+            "status": if healthy { "ready" } else { "not_ready" },
+            "checks": checks,
+        })),
+    )
+}
+
+async fn probe<T, E: std::fmt::Display>(check: impl Future<Output = Result<T, E>>) -> Value {
+    match tokio::time::timeout(CHECK_TIMEOUT, check).await {
+        Ok(Ok(_)) => json!("ok"),
+        Ok(Err(e)) => json!(e.to_string()),
+        Err(_) => json!("timed out"),
+    }
+}
@@ -0,0 +1,152 @@
+//! Per-user client settings, synced across a user's sessions.
+//!
+//! The document is stored as JSON with a version that increments on every
+//! write. Clients send partial changes as a JSON merge patch (RFC 7396):
+//! objects merge recursively, `null` resets a field to its default, and any
+//! other value replaces it. The merged document must still parse and
+//! validate as [`UserSettings`].
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use utoipa::ToSchema;
+use uuid::Uuid;
+use validator::{Validate, ValidationError};
+
+const MAX_MUTED_SERVERS: u64 = 500;
+const MAX_KEYBINDS: usize = 100;
+const MAX_KEYBIND_LENGTH: usize = 64;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Validate, ToSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct UserSettings {
+    pub theme: Theme,
+    /// BCP 47 language tag, e.g. `en-US`.
+    #[validate(length(min = 2, max = 35))]
+    pub locale: String,
+    #[validate(nested)]
+    pub notifications: NotificationSettings,
+    pub dm_privacy: DmPrivacy,
+    #[validate(length(max = MAX_MUTED_SERVERS))]
+    pub muted_servers: Vec<Uuid>,
+    /// Action name to key combination.
+    #[validate(custom(function = "validate_keybinds"))]
+    pub keybinds: BTreeMap<String, String>,
+}
+
+impl Default for UserSettings {
+    fn default() -> Self {
+        Self {
+            theme: Theme::default(),
+            locale: "en-US".to_string(),
+            notifications: NotificationSettings::default(),
+            dm_privacy: DmPrivacy::default(),
+            muted_servers: Vec::new(),
+            keybinds: BTreeMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Theme {
+    #[default]
+    System,
+    Dark,
+    Light,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Validate, ToSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct NotificationSettings {
+    pub desktop: bool,
+    pub sounds: bool,
+    /// Notify for server messages only when mentioned.
+    pub mentions_only: bool,
+}
+
+impl Default for NotificationSettings {
+    fn default() -> Self {
+        Self {
+            desktop: true,
+            sounds: true,
+            mentions_only: false,
+        }
+    }
+}
+
+/// Who may open a DM with the user. Enforced by chat-service.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DmPrivacy {
+    #[default]
+    Everyone,
+    /// Friends and members of a server the user is in.
+    ServerMembers,
+    FriendsOnly,
+}
+
+fn validate_keybinds(keybinds: &BTreeMap<String, String>) -> Result<(), ValidationError> {
+    if keybinds.len() > MAX_KEYBINDS {
+        return Err(ValidationError::new("length"));
+    }
+    let valid = |s: &String| !s.is_empty() && s.len() <= MAX_KEYBIND_LENGTH;
+    if !keybinds.iter().all(|(action, keys)| valid(action) && valid(keys)) {
+        return Err(ValidationError::new("keybind"));
+    }
+    Ok(())
+}
+
+/// Applies a JSON merge patch to `target` in place.
+pub fn merge_patch(target: &mut Value, patch: &Value) {
+    let Value::Object(patch) = patch else {
+        *target = patch.clone();
+        return;
+    };
+    if !target.is_object() {
+        *target = Value::Object(Default::default());
+    }
+    let Value::Object(target) = target else { unreachable!() };
+    for (key, value) in patch {
+        if value.is_null() {
+            target.remove(key);
+        } else {
+            merge_patch(target.entry(key.clone()).or_insert(Value::Null), value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_merge_patch_resets_nulls_to_defaults() {
+        let current = UserSettings {
+            theme: Theme::Dark,
+            locale: "de-DE".to_string(),
+            ..Default::default()
+        };
+        let mut document = serde_json::to_value(&current).unwrap();
+        merge_patch(&mut document, &json!({ "locale": null, "notifications": { "sounds": false } }));
+
+        let merged: UserSettings = serde_json::from_value(document).unwrap();
+        assert_eq!(merged.theme, Theme::Dark);
+        assert_eq!(merged.locale, "en-US");
+        assert!(!merged.notifications.sounds && merged.notifications.desktop);
+    }
+
+    #[test]
+    fn test_rejects_unknown_and_invalid_fields() {
+        assert!(serde_json::from_value::<UserSettings>(json!({ "colour": "red" })).is_err());
+
+        let keybinds = (0..=MAX_KEYBINDS).map(|i| (format!("action{i}"), "Ctrl+K".to_string()));
+        let settings = UserSettings {
+            keybinds: keybinds.collect(),
+            ..Default::default()
+        };
+        assert!(settings.validate().unwrap_err().field_errors().contains_key("keybinds"));
+    }
+}
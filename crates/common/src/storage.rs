@@ -0,0 +1,166 @@
+//! S3-compatible object storage (MinIO in development).
+//!
+//! One bucket is shared by all services; objects are namespaced by key
+//! prefix, e.g. `avatars/`, `attachments/`, `exports/`.
+
+use std::{sync::Arc, time::Duration};
+
+use axum::http::Method;
+use object_store::{
+    aws::{AmazonS3, AmazonS3Builder},
+    path::Path,
+    signer::Signer,
+    Attribute, Attributes, ObjectStore, PutMultipartOpts, PutOptions, PutPayload, WriteMultipart,
+};
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+use crate::error::{AppError, Result};
+
+/// Size of each part of a [`ObjectStorage::put_stream`] upload; S3 needs at
+/// least 5 MiB for every part but the last.
+const PART_SIZE: usize = 8 * 1024 * 1024;
+/// Parts of one upload sent at the same time.
+const PARTS_IN_FLIGHT: usize = 4;
+
+#[derive(Debug, Clone)]
+pub struct StorageSettings {
+    pub endpoint: String,
+    pub access_key: String,
+    pub secret_key: String,
+    pub bucket: String,
+    pub region: String,
+}
+
+/// Cheap to clone; no connection is made until the first request.
+#[derive(Clone)]
+pub struct ObjectStorage {
+    store: Arc<AmazonS3>,
+}
+
+impl ObjectStorage {
+    pub fn new(settings: &StorageSettings) -> Result<Self> {
+        let endpoint = if settings.endpoint.contains("://") {
+            settings.endpoint.clone()
+        } else {
+            format!("http://{}", settings.endpoint)
+        };
+
+        let store = AmazonS3Builder::new()
+            .with_endpoint(&endpoint)
+            .with_allow_http(endpoint.starts_with("http://"))
+            .with_bucket_name(&settings.bucket)
+            .with_region(&settings.region)
+            .with_access_key_id(&settings.access_key)
+            .with_secret_access_key(&settings.secret_key)
+            .build()
+            .map_err(storage_error)?;
+
+        Ok(Self { store: Arc::new(store) })
+    }
+
+    pub async fn put(&self, key: &str, bytes: Vec<u8>, content_type: &str) -> Result<()> {
+        let mut attributes = Attributes::new();
+        attributes.insert(Attribute::ContentType, content_type.to_string().into());
+
+        self.store
+            .put_opts(
+                &Path::from(key),
+                PutPayload::from(bytes),
+                PutOptions {
+                    attributes,
+                    ..Default::default()
+                },
+            )
+            .await
+            .map_err(storage_error)?;
+        Ok(())
+    }
+
+    /// Uploads everything `reader` yields as a multipart upload, holding at
+    /// most [`PARTS_IN_FLIGHT`] parts in memory, for objects too large to
+    /// [`put`](Self::put). A failed upload is aborted.
+    pub async fn put_stream(&self, key: &str, mut reader: impl AsyncRead + Unpin, content_type: &str) -> Result<()> {
+        let mut attributes = Attributes::new();
+        attributes.insert(Attribute::ContentType, content_type.to_string().into());
+        let upload = self
+            .store
+            .put_multipart_opts(
+                &Path::from(key),
+                PutMultipartOpts {
+                    attributes,
+                    ..Default::default()
+                },
+            )
+            .await
+            .map_err(storage_error)?;
+
+        let mut writer = WriteMultipart::new_with_chunk_size(upload, PART_SIZE);
+        let mut buf = vec![0; 64 * 1024];
+        let written = async {
+            loop {
+                let read = reader
+                    .read(&mut buf)
+                    .await
+                    .map_err(|e| AppError::Storage(format!("Reading upload for {} failed: {}", key, e)))?;
+                if read == 0 {
+                    return Ok(());
+                }
+                writer.wait_for_capacity(PARTS_IN_FLIGHT).await.map_err(storage_error)?;
+                writer.write(&buf[..read]);
+            }
+        }
+        .await;
+        match written {
+            Ok(()) => writer.finish().await.map(|_| ()).map_err(storage_error),
+            Err(e) => {
+                if let Err(abort) = writer.abort().await {
+                    tracing::warn!("Failed to abort upload of {}: {}", key, abort);
+                }
+                Err(e)
+            }
+        }
+    }
+
+    pub async fn get(&self, key: &str) -> Result<Vec<u8>> {
+        let result = self.store.get(&Path::from(key)).await.map_err(|e| match e {
+            object_store::Error::NotFound { .. } => AppError::NotFound(format!("Object {} not found", key)),
+            other => storage_error(other),
+        })?;
+        let bytes = result.bytes().await.map_err(storage_error)?;
+        Ok(bytes.to_vec())
+    }
+
+    pub async fn exists(&self, key: &str) -> Result<bool> {
+        match self.store.head(&Path::from(key)).await {
+            Ok(_) => Ok(true),
+            Err(object_store::Error::NotFound { .. }) => Ok(false),
+            Err(e) => Err(storage_error(e)),
+        }
+    }
+
+    pub async fn delete(&self, key: &str) -> Result<()> {
+        self.store.delete(&Path::from(key)).await.map_err(storage_error)
+    }
+
+    /// Pre-signed GET URL valid for `expires_in`.
+    pub async fn signed_url(&self, key: &str, expires_in: Duration) -> Result<String> {
+        let url = self
+            .store
+            .signed_url(Method::GET, &Path::from(key), expires_in)
+            .await
+            .map_err(storage_error)?;
+        Ok(url.to_string())
+    }
+
+    pub async fn health_check(&self) -> Result<()> {
+        self.store
+            .list_with_delimiter(None)
+            .await
+            .map(|_| ())
+            .map_err(storage_error)
+    }
+}
+
+fn storage_error(err: object_store::Error) -> AppError {
+    AppError::Storage(err.to_string())
+}
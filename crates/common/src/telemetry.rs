@@ -0,0 +1,302 @@
+//! Logging and distributed tracing.
+//!
+//! Every service logs one JSON object per line carrying the `trace_id` and
+//! `span_id` of the span it was emitted in. Spans are exported over OTLP when
+//! `OTEL_EXPORTER_OTLP_ENDPOINT` is set; trace ids are generated and
+//! propagated either way, so logs can be correlated without a collector.
+//!
+//! Trace context crosses process boundaries as W3C `traceparent` headers:
+//! [`http_span`] picks it up from incoming HTTP requests, [`nats_headers`]
+//! attaches it to outgoing NATS messages and [`consumer_span`] continues it
+//! on the receiving side.
+
+use std::fmt;
+
+use async_nats::{HeaderMap as NatsHeaderMap, Message};
+use axum::http::{HeaderMap, Request};
+use opentelemetry::{
+    global,
+    propagation::{Extractor, Injector},
+    trace::{TraceContextExt, TraceError, TracerProvider as _},
+    Context, KeyValue,
+};
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::{propagation::TraceContextPropagator, runtime, trace, Resource};
+use serde_json::{Map, Value};
+use tracing::{field::Field, Event, Span, Subscriber};
+use tracing_opentelemetry::{OpenTelemetrySpanExt, OtelData};
+use tracing_subscriber::{
+    fmt::{
+        format::{JsonFields, Writer},
+        FmtContext, FormatEvent, FormatFields, FormattedFields,
+    },
+    layer::SubscriberExt,
+    registry::LookupSpan,
+    util::SubscriberInitExt,
+    EnvFilter,
+};
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Installs the global subscriber and tracer provider for `service_name`.
+pub fn init(service_name: &str, otlp_endpoint: Option<&str>) -> Result<(), TraceError> {
+    global::set_text_map_propagator(TraceContextPropagator::new());
+
+    let mut provider = trace::TracerProvider::builder().with_config(
+        trace::Config::default().with_resource(Resource::new([KeyValue::new(
+            "service.name",
+            service_name.to_string(),
+        )])),
+    );
+    if let Some(endpoint) = otlp_endpoint {
+        let exporter = opentelemetry_otlp::new_exporter()
+            .tonic()
+            .with_endpoint(endpoint)
+            .build_span_exporter()?;
+        provider = provider.with_batch_exporter(exporter, runtime::Tokio);
+    }
+    let provider = provider.build();
+    let tracer = provider.tracer(service_name.to_string());
+    global::set_tracer_provider(provider);
+
+    tracing_subscriber::registry()
+        .with(EnvFilter::new(
+            std::env::var("RUST_LOG").unwrap_or_else(|_| "info".into()),
+        ))
+        .with(
+            tracing_subscriber::fmt::layer()
+                .fmt_fields(JsonFields::new())
+                .event_format(JsonFormat {
+                    service: service_name.to_string(),
+                }),
+        )
+        .with(tracing_opentelemetry::layer().with_tracer(tracer))
+        .try_init()
+        .map_err(|e| TraceError::Other(e.into()))
+}
+
+/// Flushes pending spans. Blocks, so call it from `spawn_blocking`.
+pub fn shutdown() {
+    global::shutdown_tracer_provider();
+}
+
+/// Root span for an incoming HTTP request, continuing the caller's trace.
+pub fn http_span<B>(request: &Request<B>) -> Span {
+    let request_id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+
+    let span = tracing::info_span!(
+        "http.request",
+        otel.kind = "server",
+        method = %request.method(),
+        path = %request.uri().path(),
+        request_id = %request_id,
+    );
+    span.set_parent(global::get_text_map_propagator(|propagator| {
+        propagator.extract(&HttpHeaders(request.headers()))
+    }));
+    span
+}
+
+/// Adds the current trace context to outgoing HTTP headers.
+pub fn inject_http_headers(headers: &mut HeaderMap) {
+    let context = Span::current().context();
+    global::get_text_map_propagator(|propagator| {
+        propagator.inject_context(&context, &mut HttpHeadersMut(headers))
+    });
+}
+
+/// Headers carrying the current trace context for a NATS publish.
+pub fn nats_headers() -> NatsHeaderMap {
+    let mut headers = NatsHeaderMap::new();
+    let context = Span::current().context();
+    global::get_text_map_propagator(|propagator| {
+        propagator.inject_context(&context, &mut NatsHeaders(&mut headers))
+    });
+    headers
+}
+
+/// Span for handling a NATS message, continuing the publisher's trace.
+pub fn consumer_span(message: &Message) -> Span {
+    let span = tracing::info_span!(
+        "nats.consume",
+        otel.kind = "consumer",
+        subject = %message.subject,
+    );
+    if let Some(headers) = &message.headers {
+        span.set_parent(extract_nats(headers));
+    }
+    span
+}
+
+fn extract_nats(headers: &NatsHeaderMap) -> Context {
+    global::get_text_map_propagator(|propagator| propagator.extract(&NatsHeadersRef(headers)))
+}
+
+struct HttpHeaders<'a>(&'a HeaderMap);
+
+impl Extractor for HttpHeaders<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|key| key.as_str()).collect()
+    }
+}
+
+struct HttpHeadersMut<'a>(&'a mut HeaderMap);
+
+impl Injector for HttpHeadersMut<'_> {
+    fn set(&mut self, key: &str, value: String) {
+        if let (Ok(name), Ok(value)) = (
+            axum::http::HeaderName::from_bytes(key.as_bytes()),
+            axum::http::HeaderValue::from_str(&value),
+        ) {
+            self.0.insert(name, value);
+        }
+    }
+}
+
+struct NatsHeaders<'a>(&'a mut NatsHeaderMap);
+
+impl Injector for NatsHeaders<'_> {
+    fn set(&mut self, key: &str, value: String) {
+        self.0.insert(key, value.as_str());
+    }
+}
+
+struct NatsHeadersRef<'a>(&'a NatsHeaderMap);
+
+impl Extractor for NatsHeadersRef<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).map(|value| value.as_str())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.iter().map(|(key, _)| key.as_ref()).collect()
+    }
+}
+
+/// One JSON object per event: timestamp, level, service, target, trace ids,
+/// the fields of every enclosing span and the event's own fields.
+struct JsonFormat {
+    service: String,
+}
+
+impl<S, N> FormatEvent<S, N> for JsonFormat
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(&self, ctx: &FmtContext<'_, S, N>, mut writer: Writer<'_>, event: &Event<'_>) -> fmt::Result {
+        let metadata = event.metadata();
+        let mut entry = Map::new();
+        entry.insert(
+            "timestamp".to_string(),
+            chrono::Utc::now()
+                .to_rfc3339_opts(chrono::SecondsFormat::Millis, true)
+                .into(),
+        );
+        entry.insert("level".to_string(), metadata.level().as_str().into());
+        entry.insert("service".to_string(), self.service.clone().into());
+        entry.insert("target".to_string(), metadata.target().into());
+
+        if let Some(scope) = ctx.event_scope() {
+            let mut spans: Vec<_> = scope.collect();
+            spans.reverse();
+
+            for span in &spans {
+                let extensions = span.extensions();
+                if let Some(fields) = extensions.get::<FormattedFields<N>>() {
+                    if let Ok(Value::Object(fields)) = serde_json::from_str::<Value>(fields) {
+                        entry.extend(fields);
+                    }
+                }
+            }
+
+            if let Some(span) = spans.last() {
+                if let Some(otel) = span.extensions().get::<OtelData>() {
+                    let trace_id = if otel.parent_cx.has_active_span() {
+                        Some(otel.parent_cx.span().span_context().trace_id())
+                    } else {
+                        otel.builder.trace_id
+                    };
+                    if let Some(trace_id) = trace_id {
+                        entry.insert("trace_id".to_string(), trace_id.to_string().into());
+                    }
+                    if let Some(span_id) = otel.builder.span_id {
+                        entry.insert("span_id".to_string(), span_id.to_string().into());
+                    }
+                }
+            }
+        }
+
+        let mut fields = JsonVisitor(Map::new());
+        event.record(&mut fields);
+        entry.extend(fields.0);
+
+        writeln!(writer, "{}", Value::Object(entry))
+    }
+}
+
+struct JsonVisitor(Map<String, Value>);
+
+impl tracing::field::Visit for JsonVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0.insert(field.name().to_string(), format!("{:?}", value).into());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trace_context_round_trips_through_nats_headers() {
+        let traceparent = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+        let mut headers = NatsHeaderMap::new();
+        headers.insert("traceparent", traceparent);
+
+        let propagator = TraceContextPropagator::new();
+        let context = opentelemetry::propagation::TextMapPropagator::extract(
+            &propagator,
+            &NatsHeadersRef(&headers),
+        );
+        assert_eq!(
+            context.span().span_context().trace_id().to_string(),
+            "4bf92f3577b34da6a3ce929d0e0e4736"
+        );
+
+        let mut injected = NatsHeaderMap::new();
+        opentelemetry::propagation::TextMapPropagator::inject_context(
+            &propagator,
+            &context,
+            &mut NatsHeaders(&mut injected),
+        );
+        assert_eq!(injected.get("traceparent").map(|v| v.as_str()), Some(traceparent));
+    }
+}
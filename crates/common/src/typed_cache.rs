@@ -0,0 +1,351 @@
+//! Typed cache-aside layer on top of a [`KeyValueCache`], Redis in production.
+//!
+//! Values are stored as JSON under `{namespace}:{key}`. Concurrent misses for
+//! the same key within a process share one loader call, and TTLs are jittered
+//! so entries written together don't expire together. An optional in-process
+//! L1 tier sits in front of Redis and is kept coherent by invalidation
+//! messages on `cache.invalidate.{namespace}`, sent on every write and
+//! invalidation; an instance ignores the ones it sent itself. An empty
+//! message clears the whole tier.
+
+use std::{
+    collections::HashMap,
+    future::Future,
+    marker::PhantomData,
+    sync::{Arc, Mutex, RwLock},
+    time::{Duration, Instant},
+};
+
+use async_nats::{Client, HeaderMap};
+use futures::StreamExt;
+use rand::Rng;
+use serde::{de::DeserializeOwned, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    cache::KeyValueCache,
+    error::{AppError, Result},
+    metrics,
+};
+
+/// Fraction of the TTL that may be shaved off each write.
+const TTL_JITTER: f64 = 0.1;
+/// Header naming the cache instance that sent an invalidation.
+const ORIGIN_HEADER: &str = "Cache-Origin";
+
+pub fn invalidation_subject(namespace: &str) -> String {
+    format!("cache.invalidate.{}", namespace)
+}
+
+struct LocalTier<T> {
+    entries: RwLock<HashMap<String, (T, Instant)>>,
+    capacity: usize,
+    ttl: Duration,
+}
+
+impl<T: Clone> LocalTier<T> {
+    fn get(&self, key: &str) -> Option<T> {
+        let entries = self.entries.read().unwrap();
+        entries
+            .get(key)
+            .filter(|(_, expires_at)| *expires_at > Instant::now())
+            .map(|(value, _)| value.clone())
+    }
+
+    fn insert(&self, key: &str, value: T) {
+        let mut entries = self.entries.write().unwrap();
+        if entries.len() >= self.capacity && !entries.contains_key(key) {
+            let now = Instant::now();
+            entries.retain(|_, (_, expires_at)| *expires_at > now);
+            if entries.len() >= self.capacity {
+                if let Some(evict) = entries.keys().next().cloned() {
+                    entries.remove(&evict);
+                }
+            }
+        }
+        entries.insert(key.to_string(), (value, Instant::now() + self.ttl));
+    }
+
+    fn remove(&self, key: &str) {
+        self.entries.write().unwrap().remove(key);
+    }
+
+    fn clear(&self) {
+        self.entries.write().unwrap().clear();
+    }
+}
+
+pub struct Cache<T> {
+    redis: Arc<dyn KeyValueCache>,
+    namespace: String,
+    nats: Option<Client>,
+    local: Option<Arc<LocalTier<T>>>,
+    /// Identifies this instance's invalidations to its own subscriber.
+    origin: String,
+    inflight: Arc<Inflight>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+type Inflight = Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>;
+
+/// A caller's claim on the gate for one key. Dropping it, including when
+/// the caller is cancelled, removes the gate once no one else holds it.
+struct InflightClaim<'a> {
+    inflight: &'a Inflight,
+    key: &'a str,
+    gate: Arc<tokio::sync::Mutex<()>>,
+}
+
+impl<'a> InflightClaim<'a> {
+    fn new(inflight: &'a Inflight, key: &'a str) -> Self {
+        let gate = inflight.lock().unwrap().entry(key.to_string()).or_default().clone();
+        Self { inflight, key, gate }
+    }
+}
+
+impl Drop for InflightClaim<'_> {
+    fn drop(&mut self) {
+        let mut inflight = self.inflight.lock().unwrap();
+        // The map holds one reference and this claim another.
+        let idle = inflight
+            .get(self.key)
+            .is_some_and(|current| Arc::ptr_eq(current, &self.gate) && Arc::strong_count(&self.gate) == 2);
+        if idle {
+            inflight.remove(self.key);
+        }
+    }
+}
+
+impl<T> Clone for Cache<T> {
+    fn clone(&self) -> Self {
+        Self {
+            redis: self.redis.clone(),
+            namespace: self.namespace.clone(),
+            nats: self.nats.clone(),
+            local: self.local.clone(),
+            origin: self.origin.clone(),
+            inflight: self.inflight.clone(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T> Cache<T>
+where
+    T: Serialize + DeserializeOwned + Clone + Send + Sync + 'static,
+{
+    pub fn new(redis: Arc<dyn KeyValueCache>, namespace: impl Into<String>) -> Self {
+        Self {
+            redis,
+            namespace: namespace.into(),
+            nats: None,
+            local: None,
+            origin: Uuid::new_v4().to_string(),
+            inflight: Arc::new(Mutex::new(HashMap::new())),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Adds an in-process tier holding up to `capacity` entries for `ttl`,
+    /// evicted whenever another instance writes or invalidates the key.
+    pub async fn with_local_tier(mut self, nats: Client, capacity: usize, ttl: Duration) -> Result<Self> {
+        let local = Arc::new(LocalTier {
+            entries: RwLock::new(HashMap::new()),
+            capacity: capacity.max(1),
+            ttl,
+        });
+
+        let mut subscriber = nats
+            .subscribe(invalidation_subject(&self.namespace))
+            .await
+            .map_err(|e| AppError::MessageQueue(e.to_string()))?;
+
+        let tier = Arc::downgrade(&local);
+        let origin = self.origin.clone();
+        tokio::spawn(async move {
+            while let Some(message) = subscriber.next().await {
+                let Some(tier) = tier.upgrade() else { break };
+                let sender = message.headers.as_ref().and_then(|headers| headers.get(ORIGIN_HEADER));
+                if sender.is_some_and(|sender| sender.as_str() == origin) {
+                    continue;
+                }
+                match std::str::from_utf8(&message.payload) {
+                    Ok("") => tier.clear(),
+                    Ok(key) => tier.remove(key),
+                    Err(_) => tracing::warn!("Ignoring non-UTF-8 cache invalidation"),
+                }
+            }
+        });
+
+        self.nats = Some(nats);
+        self.local = Some(local);
+        Ok(self)
+    }
+
+    pub async fn get(&self, key: &str) -> Result<Option<T>> {
+        if let Some(value) = self.local.as_ref().and_then(|local| local.get(key)) {
+            self.record("local_hit");
+            return Ok(Some(value));
+        }
+
+        let Some(raw) = self.redis.get(&self.redis_key(key)).await? else {
+            self.record("miss");
+            return Ok(None);
+        };
+
+        match serde_json::from_str::<T>(&raw) {
+            Ok(value) => {
+                if let Some(local) = &self.local {
+                    local.insert(key, value.clone());
+                }
+                self.record("hit");
+                Ok(Some(value))
+            }
+            Err(e) => {
+                // Treat entries written by an older schema as misses.
+                self.record("miss");
+                tracing::warn!("Discarding undecodable cache entry {}: {}", self.redis_key(key), e);
+                Ok(None)
+            }
+        }
+    }
+
+    /// Writes the entry and evicts older copies from other instances'
+    /// local tiers.
+    pub async fn set(&self, key: &str, value: &T, ttl: Duration) -> Result<()> {
+        let raw = serde_json::to_string(value).map_err(|e| AppError::Cache(e.to_string()))?;
+        self.redis.set(&self.redis_key(key), &raw, Some(jittered(ttl))).await?;
+
+        if let Some(local) = &self.local {
+            local.insert(key, value.clone());
+        }
+        self.broadcast_invalidation(key).await
+    }
+
+    /// Returns the cached value or runs `loader` once per key across
+    /// concurrent callers and caches its result. Cache failures are logged
+    /// and fall through to the loader.
+    pub async fn get_or_load<F, Fut>(&self, key: &str, ttl: Duration, loader: F) -> Result<T>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        if let Some(value) = self.get_lenient(key).await {
+            return Ok(value);
+        }
+
+        let claim = InflightClaim::new(&self.inflight, key);
+        let _guard = claim.gate.lock().await;
+
+        // Another caller may have filled the entry while we waited.
+        if let Some(value) = self.get_lenient(key).await {
+            return Ok(value);
+        }
+
+        let value = loader().await?;
+        if let Err(e) = self.set(key, &value, ttl).await {
+            tracing::warn!("Failed to cache {}: {}", self.redis_key(key), e);
+        }
+        Ok(value)
+    }
+
+    /// Drops the entry from Redis and from every instance's local tier.
+    pub async fn invalidate(&self, key: &str) -> Result<()> {
+        self.redis.delete(&self.redis_key(key)).await?;
+
+        if let Some(local) = &self.local {
+            local.remove(key);
+        }
+        self.broadcast_invalidation(key).await
+    }
+
+    /// Drops every entry in the namespace from Redis and from every
+    /// instance's local tier, e.g. before rebuilding it from events.
+    pub async fn clear(&self) -> Result<()> {
+        self.redis.delete_prefix(&self.redis_key("")).await?;
+
+        if let Some(local) = &self.local {
+            local.clear();
+        }
+        self.broadcast_invalidation("").await
+    }
+
+    /// Tells the other instances sharing the local tier's subject to drop
+    /// `key`, or everything for an empty key. Does nothing without NATS.
+    async fn broadcast_invalidation(&self, key: &str) -> Result<()> {
+        let Some(nats) = &self.nats else {
+            return Ok(());
+        };
+        let mut headers = HeaderMap::new();
+        headers.insert(ORIGIN_HEADER, self.origin.as_str());
+        nats.publish_with_headers(invalidation_subject(&self.namespace), headers, key.to_string().into())
+            .await
+            .map_err(|e| AppError::MessageQueue(e.to_string()))
+    }
+
+    async fn get_lenient(&self, key: &str) -> Option<T> {
+        self.get(key).await.unwrap_or_else(|e| {
+            tracing::warn!("Cache read failed for {}: {}", self.redis_key(key), e);
+            None
+        })
+    }
+
+    fn record(&self, result: &'static str) {
+        metrics::counter!(
+            "cache_requests_total",
+            "namespace" => self.namespace.clone(),
+            "result" => result
+        )
+        .increment(1);
+    }
+
+    fn redis_key(&self, key: &str) -> String {
+        format!("{}:{}", self.namespace, key)
+    }
+}
+
+fn jittered(ttl: Duration) -> Duration {
+    let factor = rand::thread_rng().gen_range((1.0 - TTL_JITTER)..=1.0);
+    ttl.mul_f64(factor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::InMemoryCache;
+
+    #[test]
+    fn test_jitter_stays_within_bounds() {
+        let ttl = Duration::from_secs(3600);
+        for _ in 0..100 {
+            let jittered = jittered(ttl);
+            assert!(jittered <= ttl);
+            assert!(jittered >= ttl.mul_f64(1.0 - TTL_JITTER));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_load_releases_its_gate() {
+        let cache: Cache<u32> = Cache::new(Arc::new(InMemoryCache::new()), "test");
+        let load = cache.get_or_load("key", Duration::from_secs(60), std::future::pending);
+        assert!(tokio::time::timeout(Duration::from_millis(10), load).await.is_err());
+        assert!(cache.inflight.lock().unwrap().is_empty());
+
+        let value = cache.get_or_load("key", Duration::from_secs(60), || async { Ok(1) }).await.unwrap();
+        assert_eq!(value, 1);
+        assert!(cache.inflight.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_clear_drops_only_its_namespace() {
+        let redis = Arc::new(InMemoryCache::new());
+        let cache: Cache<u32> = Cache::new(redis.clone(), "test");
+        let other: Cache<u32> = Cache::new(redis.clone(), "other");
+        cache.set("a", &1, Duration::from_secs(60)).await.unwrap();
+        cache.set("b", &2, Duration::from_secs(60)).await.unwrap();
+        other.set("a", &3, Duration::from_secs(60)).await.unwrap();
+
+        cache.clear().await.unwrap();
+        assert_eq!(redis.keys(), ["other:a"]);
+    }
+}
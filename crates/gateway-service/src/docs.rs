@@ -0,0 +1,82 @@
+//! Merged OpenAPI document for the public API and a Swagger UI on top of it.
+//!
+//! Each backend serves its own `/openapi.json`; the gateway fetches them on
+//! request and nests them under `/api`, matching how it proxies traffic.
+//! A backend that cannot be reached is left out and logged.
+
+use std::{sync::Arc, time::Duration};
+
+use axum::{extract::State, response::Html, routing::get, Json, Router};
+use common::{
+    openapi::{self, SPEC_PATH},
+    Config,
+};
+use futures::future::join_all;
+use utoipa::openapi::OpenApi;
+
+const FETCH_TIMEOUT: Duration = Duration::from_secs(3);
+
+const SWAGGER_UI_VERSION: &str = "5.17.14";
+
+/// Service name and base URL of every backend exposing a REST API.
+type Upstreams = Arc<Vec<(&'static str, String)>>;
+
+/// Serves the merged spec of the upstreams in `config`.
+pub fn router(config: &Config) -> Router {
+    let upstreams: Upstreams = Arc::new(
+        config.upstreams().into_iter().map(|(service, url)| (service, url.to_string())).collect(),
+    );
+    Router::new()
+        .route(SPEC_PATH, get(merged_spec))
+        .route("/docs", get(swagger_ui))
+        .with_state(upstreams)
+}
+
+async fn merged_spec(State(upstreams): State<Upstreams>) -> Json<OpenApi> {
+    let client = reqwest::Client::builder()
+        .timeout(FETCH_TIMEOUT)
+        .build()
+        .unwrap_or_default();
+
+    let specs = join_all(upstreams.iter().map(|(service, base)| {
+        let client = client.clone();
+        async move {
+            let url = format!("{}{}", base.trim_end_matches('/'), SPEC_PATH);
+            let result = async { client.get(&url).send().await?.error_for_status()?.json::<OpenApi>().await }.await;
+            result
+                .map_err(|e| tracing::warn!("Leaving {} out of the API spec: {}", service, e))
+                .ok()
+        }
+    }))
+    .await;
+
+    let merged = specs
+        .into_iter()
+        .flatten()
+        .fold(openapi::base_doc("Hermes API"), |merged, spec| merged.nest("/api", spec));
+    Json(merged)
+}
+
+async fn swagger_ui() -> Html<String> {
+    Html(format!(
+        r##"<!doctype html>
+<html>
+<head>
+  <meta charset="utf-8">
+  <title>Hermes API</title>
+  <link rel="stylesheet" href="https://unpkg.com/swagger-ui-dist@{version}/swagger-ui.css">
+</head>
+<body>
+  <div id="swagger-ui"></div>
+  <script src="https://unpkg.com/swagger-ui-dist@{version}/swagger-ui-bundle.js" crossorigin></script>
+  <script>
+    window.onload = () => {{
+      window.ui = SwaggerUIBundle({{ url: "{spec}", dom_id: "#swagger-ui" }});
+    }};
+  </script>
+</body>
+</html>"##,
+        version = SWAGGER_UI_VERSION,
+        spec = SPEC_PATH,
+    ))
+}
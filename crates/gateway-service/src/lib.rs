@@ -0,0 +1,72 @@
+use axum::{
+    routing::{get, any},
+    Router,
+    http::StatusCode,
+    extract::{
+        ws::{WebSocket, WebSocketUpgrade},
+        State,
+    },
+    response::IntoResponse,
+};
+use std::sync::Arc;
+use common::{metrics, AppState, Config};
+
+mod docs;
+
+#[allow(dead_code)]
+#[derive(Clone)]
+struct GatewayState {
+    app_state: AppState,
+}
+
+pub fn router(config: &Config, app_state: AppState) -> Router {
+    tracing::info!("WebSocket available at: ws://localhost:{}/ws", config.port);
+    tracing::info!("API docs available at: http://localhost:{}/docs", config.port);
+    metrics::describe_gauge!("gateway_connections", "Open WebSocket connections");
+
+    Router::new()
+        .route("/ws", get(websocket_handler))
+        .route("/api/*path", any(proxy_handler))
+        .with_state(Arc::new(GatewayState { app_state }))
+        .merge(docs::router(config))
+}
+
+async fn websocket_handler(
+    ws: WebSocketUpgrade,
+    State(_state): State<Arc<GatewayState>>,
+) -> impl IntoResponse {
+    ws.on_upgrade(handle_socket)
+}
+
+async fn handle_socket(mut socket: WebSocket) {
+    tracing::info!("WebSocket connection established");
+    metrics::gauge!("gateway_connections").increment(1.0);
+    
+    // TODO: Implement WebSocket message handling
+    // 1. Authenticate connection
+    // 2. Send READY event
+    // 3. Handle incoming messages (heartbeat, voice state, etc.)
+    // 4. Subscribe to NATS events and forward to client
+    
+    while let Some(msg) = socket.recv().await {
+        match msg {
+            Ok(msg) => {
+                tracing::debug!("Received WebSocket message: {:?}", msg);
+                // Handle message
+            }
+            Err(e) => {
+                tracing::error!("WebSocket error: {}", e);
+                break;
+            }
+        }
+    }
+    
+    metrics::gauge!("gateway_connections").decrement(1.0);
+    tracing::info!("WebSocket connection closed");
+}
+
+async fn proxy_handler() -> StatusCode {
+    // TODO: Implement API proxying to backend services
+    // Route requests to appropriate microservices based on path
+    StatusCode::NOT_IMPLEMENTED
+}
@@ -0,0 +1,26 @@
+use std::time::Duration;
+
+use test_support::{fixtures, GatewayClient, TestStack};
+
+#[tokio::test]
+#[ignore = "requires Docker"]
+async fn test_websocket_connections_are_counted() -> anyhow::Result<()> {
+    let stack = TestStack::start().await?;
+    let gateway = stack.spawn(gateway_service::router).await?;
+    let alice = fixtures::create_user(&stack, "alice").await?;
+
+    let client = GatewayClient::connect(&gateway, &alice.token).await?;
+    // The socket is handed to the gateway after the handshake completes.
+    let mut metrics = String::new();
+    for _ in 0..20 {
+        metrics = reqwest::get(gateway.url("/metrics")).await?.text().await?;
+        if metrics.contains("gateway_connections 1") {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    assert!(metrics.contains("gateway_connections 1"), "{}", metrics);
+
+    client.close().await?;
+    Ok(())
+}
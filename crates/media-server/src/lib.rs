@@ -0,0 +1,75 @@
+use axum::{http::StatusCode, Router};
+use std::sync::Arc;
+use utoipa_axum::routes;
+use common::{openapi, AppState, Config};
+
+pub fn router(_config: &Config, app_state: AppState) -> Router {
+    openapi::into_router(
+        openapi::service_router("media-server")
+            .routes(routes!(create_session))
+            .routes(routes!(close_session))
+            .routes(routes!(publish_track))
+            .routes(routes!(subscribe_track))
+            .routes(routes!(get_stats))
+            .with_state(Arc::new(app_state)),
+    )
+}
+
+#[utoipa::path(
+    post,
+    path = "/sessions",
+    tag = "media",
+    security(("bearer" = [])),
+    responses(
+        (status = 201, description = "Media session created"),
+    )
+)]
+async fn create_session() -> StatusCode { StatusCode::NOT_IMPLEMENTED }
+
+#[utoipa::path(
+    delete,
+    path = "/sessions/{id}",
+    tag = "media",
+    params(("id" = Uuid, Path, description = "Session id")),
+    security(("bearer" = [])),
+    responses(
+        (status = 204, description = "Session closed"),
+    )
+)]
+async fn close_session() -> StatusCode { StatusCode::NOT_IMPLEMENTED }
+
+#[utoipa::path(
+    post,
+    path = "/sessions/{id}/publish",
+    tag = "media",
+    params(("id" = Uuid, Path, description = "Session id")),
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "SDP answer for the published track"),
+    )
+)]
+async fn publish_track() -> StatusCode { StatusCode::NOT_IMPLEMENTED }
+
+#[utoipa::path(
+    post,
+    path = "/sessions/{id}/subscribe",
+    tag = "media",
+    params(("id" = Uuid, Path, description = "Session id")),
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "SDP offer for the subscribed track"),
+    )
+)]
+async fn subscribe_track() -> StatusCode { StatusCode::NOT_IMPLEMENTED }
+
+#[utoipa::path(
+    get,
+    path = "/sessions/{id}/stats",
+    tag = "media",
+    params(("id" = Uuid, Path, description = "Session id")),
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Transport and track statistics"),
+    )
+)]
+async fn get_stats() -> StatusCode { StatusCode::NOT_IMPLEMENTED }
@@ -0,0 +1,60 @@
+use axum::{http::StatusCode, Router};
+use std::sync::Arc;
+use utoipa_axum::routes;
+use common::{openapi, AppState, Config};
+
+pub fn router(_config: &Config, app_state: AppState) -> Router {
+    openapi::into_router(
+        openapi::service_router("presence-service")
+            .routes(routes!(update_status))
+            .routes(routes!(get_presence))
+            .routes(routes!(bulk_get_presence))
+            .routes(routes!(typing_indicator))
+            .with_state(Arc::new(app_state)),
+    )
+}
+
+#[utoipa::path(
+    post,
+    path = "/presence/status",
+    tag = "presence",
+    security(("bearer" = [])),
+    responses(
+        (status = 204, description = "Status updated"),
+    )
+)]
+async fn update_status() -> StatusCode { StatusCode::NOT_IMPLEMENTED }
+
+#[utoipa::path(
+    get,
+    path = "/presence/{id}",
+    tag = "presence",
+    params(("id" = Uuid, Path, description = "User id")),
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Current status of the user"),
+    )
+)]
+async fn get_presence() -> StatusCode { StatusCode::NOT_IMPLEMENTED }
+
+#[utoipa::path(
+    post,
+    path = "/presence/bulk",
+    tag = "presence",
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Statuses keyed by user id"),
+    )
+)]
+async fn bulk_get_presence() -> StatusCode { StatusCode::NOT_IMPLEMENTED }
+
+#[utoipa::path(
+    post,
+    path = "/presence/typing",
+    tag = "presence",
+    security(("bearer" = [])),
+    responses(
+        (status = 204, description = "Typing indicator broadcast"),
+    )
+)]
+async fn typing_indicator() -> StatusCode { StatusCode::NOT_IMPLEMENTED }
@@ -1,52 +1,10 @@
-use axum::{
-    routing::{get, post},
-    Router,
-    http::StatusCode,
-};
-use std::net::SocketAddr;
-use std::sync::Arc;
-use tower_http::trace::TraceLayer;
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
-use common::AppState;
+use common::server::ServiceBuilder;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    tracing_subscriber::registry()
-        .with(tracing_subscriber::EnvFilter::new(
-            std::env::var("RUST_LOG").unwrap_or_else(|_| "info".into()),
-        ))
-        .with(tracing_subscriber::fmt::layer())
-        .init();
-
-    dotenvy::dotenv().ok();
-    
-    let redis_url = std::env::var("REDIS_URL")
-        .unwrap_or_else(|_| "redis://:redis_dev_password@localhost:6379".to_string());
-    let nats_url = std::env::var("NATS_URL")
-        .unwrap_or_else(|_| "nats://localhost:4222".to_string());
-
-    let app_state = AppState::new("", &redis_url, &nats_url).await?;
-    let state = Arc::new(app_state);
-
-    let app = Router::new()
-        .route("/health", get(health_check))
-        .route("/presence/status", post(update_status))
-        .route("/presence/:id", get(get_presence))
-        .route("/presence/bulk", post(bulk_get_presence))
-        .route("/presence/typing", post(typing_indicator))
-        .layer(TraceLayer::new_for_http())
-        .with_state(state);
-
-    let port = std::env::var("PORT").unwrap_or_else(|_| "8087".to_string()).parse::<u16>()?;
-    let addr = SocketAddr::from(([0, 0, 0, 0], port));
-    tracing::info!("Presence Service listening on {}", addr);
-    let listener = tokio::net::TcpListener::bind(addr).await?;
-    axum::serve(listener, app).await?;
-    Ok(())
+    ServiceBuilder::new("presence-service", 8087)
+        .redis()
+        .nats()
+        .run(presence_service::router)
+        .await
 }
-
-async fn health_check() -> StatusCode { StatusCode::OK }
-async fn update_status() -> StatusCode { StatusCode::NOT_IMPLEMENTED }
-async fn get_presence() -> StatusCode { StatusCode::NOT_IMPLEMENTED }
-async fn bulk_get_presence() -> StatusCode { StatusCode::NOT_IMPLEMENTED }
-async fn typing_indicator() -> StatusCode { StatusCode::NOT_IMPLEMENTED }
@@ -0,0 +1,61 @@
+use axum::{http::StatusCode, Router};
+use std::sync::Arc;
+use utoipa_axum::routes;
+use common::{error::ErrorResponse, openapi, AppState, Config};
+
+pub fn router(_config: &Config, app_state: AppState) -> Router {
+    openapi::into_router(
+        openapi::service_router("stream-service")
+            .routes(routes!(start_stream))
+            .routes(routes!(stop_stream))
+            .routes(routes!(watch_stream))
+            .routes(routes!(update_quality))
+            .with_state(Arc::new(app_state)),
+    )
+}
+
+#[utoipa::path(
+    post,
+    path = "/stream/start",
+    tag = "streams",
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Stream started"),
+        (status = 409, body = ErrorResponse),
+    )
+)]
+async fn start_stream() -> StatusCode { StatusCode::NOT_IMPLEMENTED }
+
+#[utoipa::path(
+    post,
+    path = "/stream/stop",
+    tag = "streams",
+    security(("bearer" = [])),
+    responses(
+        (status = 204, description = "Stream stopped"),
+    )
+)]
+async fn stop_stream() -> StatusCode { StatusCode::NOT_IMPLEMENTED }
+
+#[utoipa::path(
+    post,
+    path = "/stream/watch",
+    tag = "streams",
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Viewer connection details"),
+    )
+)]
+async fn watch_stream() -> StatusCode { StatusCode::NOT_IMPLEMENTED }
+
+#[utoipa::path(
+    patch,
+    path = "/stream/{id}/quality",
+    tag = "streams",
+    params(("id" = Uuid, Path, description = "Stream id")),
+    security(("bearer" = [])),
+    responses(
+        (status = 204, description = "Quality updated"),
+    )
+)]
+async fn update_quality() -> StatusCode { StatusCode::NOT_IMPLEMENTED }
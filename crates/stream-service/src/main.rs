@@ -1,52 +1,10 @@
-use axum::{
-    routing::{get, post, patch},
-    Router,
-    http::StatusCode,
-};
-use std::net::SocketAddr;
-use std::sync::Arc;
-use tower_http::trace::TraceLayer;
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
-use common::AppState;
+use common::server::ServiceBuilder;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    tracing_subscriber::registry()
-        .with(tracing_subscriber::EnvFilter::new(
-            std::env::var("RUST_LOG").unwrap_or_else(|_| "info".into()),
-        ))
-        .with(tracing_subscriber::fmt::layer())
-        .init();
-
-    dotenvy::dotenv().ok();
-    
-    let redis_url = std::env::var("REDIS_URL")
-        .unwrap_or_else(|_| "redis://:redis_dev_password@localhost:6379".to_string());
-    let nats_url = std::env::var("NATS_URL")
-        .unwrap_or_else(|_| "nats://localhost:4222".to_string());
-
-    let app_state = AppState::new("", &redis_url, &nats_url).await?;
-    let state = Arc::new(app_state);
-
-    let app = Router::new()
-        .route("/health", get(health_check))
-        .route("/stream/start", post(start_stream))
-        .route("/stream/stop", post(stop_stream))
-        .route("/stream/watch", post(watch_stream))
-        .route("/stream/:id/quality", patch(update_quality))
-        .layer(TraceLayer::new_for_http())
-        .with_state(state);
-
-    let port = std::env::var("PORT").unwrap_or_else(|_| "8086".to_string()).parse::<u16>()?;
-    let addr = SocketAddr::from(([0, 0, 0, 0], port));
-    tracing::info!("Stream Service listening on {}", addr);
-    let listener = tokio::net::TcpListener::bind(addr).await?;
-    axum::serve(listener, app).await?;
-    Ok(())
+    ServiceBuilder::new("stream-service", 8086)
+        .redis()
+        .nats()
+        .run(stream_service::router)
+        .await
 }
-
-async fn health_check() -> StatusCode { StatusCode::OK }
-async fn start_stream() -> StatusCode { StatusCode::NOT_IMPLEMENTED }
-async fn stop_stream() -> StatusCode { StatusCode::NOT_IMPLEMENTED }
-async fn watch_stream() -> StatusCode { StatusCode::NOT_IMPLEMENTED }
-async fn update_quality() -> StatusCode { StatusCode::NOT_IMPLEMENTED }
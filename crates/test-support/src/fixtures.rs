@@ -0,0 +1,89 @@
+//! Rows most tests need, written straight to the database.
+
+use common::{
+    db,
+    models::{permissions, Channel, ChannelType, Server, User},
+};
+use uuid::Uuid;
+
+use crate::TestStack;
+
+/// Placeholder stored as the password hash; it never verifies, so fixture
+/// users can only authenticate with their issued token.
+const NO_PASSWORD: &str = "!";
+
+pub struct TestUser {
+    pub user: User,
+    /// Access token signed with the stack's `jwt_secret`.
+    pub token: String,
+}
+
+impl TestUser {
+    pub fn id(&self) -> Uuid {
+        self.user.id
+    }
+
+    pub fn bearer(&self) -> String {
+        format!("Bearer {}", self.token)
+    }
+}
+
+pub struct TestServer {
+    pub server: Server,
+    /// Default text channel, as created with every new server.
+    pub general: Channel,
+}
+
+impl TestServer {
+    pub fn id(&self) -> Uuid {
+        self.server.id
+    }
+}
+
+/// Registers `username` with an `@example.test` email.
+pub async fn create_user(stack: &TestStack, username: &str) -> anyhow::Result<TestUser> {
+    insert_user(stack, username, NO_PASSWORD).await
+}
+
+/// Like [`create_user`], for tests that re-enter `password`. Hashing is
+/// slow, so only use it where the password is checked.
+pub async fn create_user_with_password(stack: &TestStack, username: &str, password: &str) -> anyhow::Result<TestUser> {
+    insert_user(stack, username, &common::password::hash(password)?).await
+}
+
+async fn insert_user(stack: &TestStack, username: &str, password_hash: &str) -> anyhow::Result<TestUser> {
+    let email = format!("{}@example.test", username);
+    let user = db::users::create(
+        stack.state.db()?,
+        db::users::NewUser {
+            username,
+            email: &email,
+            password_hash,
+            display_name: username,
+        },
+    )
+    .await?;
+    let token = stack
+        .jwt()
+        .generate_token(user.id, &user.username, stack.config.jwt_access_expiry)?;
+    Ok(TestUser { user, token })
+}
+
+/// Creates a server owned by `owner` with an `@everyone` role allowed to
+/// send messages and a `general` text channel.
+pub async fn create_server(stack: &TestStack, owner: &TestUser, name: &str) -> anyhow::Result<TestServer> {
+    let mut tx = stack.state.db()?.begin().await?;
+
+    let server = db::servers::create(&mut *tx, name, None, owner.id()).await?;
+    db::roles::create(&mut *tx, server.id, db::roles::EVERYONE, permissions::SEND_MESSAGES).await?;
+    db::servers::add_member(&mut *tx, server.id, owner.id()).await?;
+    let general = db::channels::create(&mut *tx, server.id, "general", ChannelType::Text, None).await?;
+
+    tx.commit().await?;
+    Ok(TestServer { server, general })
+}
+
+pub async fn add_member(stack: &TestStack, server: &TestServer, user: &TestUser) -> anyhow::Result<()> {
+    db::servers::add_member(stack.state.db()?, server.id(), user.id()).await?;
+    Ok(())
+}
@@ -0,0 +1,70 @@
+//! WebSocket client for the gateway's `/ws` endpoint.
+
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Context};
+use futures::{SinkExt, StreamExt};
+use serde_json::Value;
+use tokio::net::TcpStream;
+use tokio_tungstenite::{
+    connect_async,
+    tungstenite::{client::IntoClientRequest, http::HeaderValue, Message},
+    MaybeTlsStream, WebSocketStream,
+};
+
+use crate::RunningService;
+
+/// How long [`GatewayClient::recv`] waits for a frame.
+pub const RECV_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Sends and receives JSON frames, skipping pings and binary frames.
+pub struct GatewayClient {
+    socket: WebSocketStream<MaybeTlsStream<TcpStream>>,
+}
+
+impl GatewayClient {
+    /// Opens `/ws` on `gateway`, authenticating the upgrade with `token`.
+    pub async fn connect(gateway: &RunningService, token: &str) -> anyhow::Result<Self> {
+        let mut request = gateway.ws_url("/ws").into_client_request()?;
+        request
+            .headers_mut()
+            .insert("authorization", HeaderValue::from_str(&format!("Bearer {}", token))?);
+        let (socket, _) = connect_async(request).await?;
+        Ok(Self { socket })
+    }
+
+    pub async fn send(&mut self, payload: &Value) -> anyhow::Result<()> {
+        self.socket.send(Message::Text(payload.to_string())).await?;
+        Ok(())
+    }
+
+    /// Next JSON frame, failing after [`RECV_TIMEOUT`] or on close.
+    pub async fn recv(&mut self) -> anyhow::Result<Value> {
+        loop {
+            let frame = tokio::time::timeout(RECV_TIMEOUT, self.socket.next())
+                .await
+                .context("timed out waiting for a gateway frame")?
+                .ok_or_else(|| anyhow!("gateway closed the connection"))??;
+            match frame {
+                Message::Text(text) => return Ok(serde_json::from_str(&text)?),
+                Message::Close(frame) => bail!("gateway closed the connection: {:?}", frame),
+                _ => continue,
+            }
+        }
+    }
+
+    /// Skips frames until one whose `t` field is `event`, e.g. `MESSAGE_CREATE`.
+    pub async fn wait_for(&mut self, event: &str) -> anyhow::Result<Value> {
+        loop {
+            let frame = self.recv().await?;
+            if frame["t"] == event {
+                return Ok(frame);
+            }
+        }
+    }
+
+    pub async fn close(mut self) -> anyhow::Result<()> {
+        self.socket.close(None).await?;
+        Ok(())
+    }
+}
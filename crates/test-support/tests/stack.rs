@@ -0,0 +1,56 @@
+use axum::Router;
+use serde_json::Value;
+use test_support::{fixtures, TestStack};
+
+#[tokio::test]
+#[ignore = "requires Docker"]
+async fn test_stack_is_ready_with_every_dependency() -> anyhow::Result<()> {
+    let stack = TestStack::start().await?;
+    let service = stack.spawn(|_, _| Router::new()).await?;
+
+    let response = reqwest::get(service.url("/health/ready")).await?;
+    assert_eq!(response.status(), 200);
+    let body: Value = response.json().await?;
+    for dependency in ["postgres", "redis", "nats"] {
+        assert_eq!(body["checks"][dependency], "ok", "{}", dependency);
+    }
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore = "requires Docker"]
+async fn test_fixture_tokens_verify() -> anyhow::Result<()> {
+    let stack = TestStack::start().await?;
+    let alice = fixtures::create_user(&stack, "alice").await?;
+    let guild = fixtures::create_server(&stack, &alice, "Test Server").await?;
+
+    assert_eq!(stack.jwt().verify_token(&alice.token)?.sub, alice.id());
+    assert_eq!(guild.server.owner_id, alice.id());
+    assert_eq!(guild.general.server_id, Some(guild.id()));
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore = "requires Docker"]
+async fn test_migrations_adopt_a_schema_from_init_sql() -> anyhow::Result<()> {
+    use sqlx::{Connection, Executor, PgConnection};
+
+    let stack = TestStack::start().await?;
+    stack.state.db()?.execute("CREATE DATABASE legacy").await?;
+    let (server_url, _) = stack.config.database_url.rsplit_once('/').expect("a database name");
+    let legacy_url = format!("{}/legacy", server_url);
+
+    // What the old init.sql left behind: the schema, but no migration history.
+    let initial = common::db::MIGRATOR.iter().next().expect("an initial migration");
+    let mut conn = PgConnection::connect(&legacy_url).await?;
+    conn.execute(&*initial.sql).await?;
+    conn.close().await?;
+
+    let pool = sqlx::PgPool::connect(&legacy_url).await?;
+    common::db::run_migrations(&pool).await?;
+    let applied: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM _sqlx_migrations WHERE success")
+        .fetch_one(&pool)
+        .await?;
+    assert_eq!(applied as usize, common::db::MIGRATOR.iter().count());
+    Ok(())
+}
@@ -0,0 +1,141 @@
+//! Blocking. A block is the blocker's `blocked` row in `friendships`;
+//! creating one ends any friendship or pending request between the pair.
+//! Other services enforce blocks through [`BlockList`](common::blocks::BlockList),
+//! whose cached entry for the blocker is invalidated on every change.
+
+use std::sync::Arc;
+
+use axum::{extract::State, http::StatusCode};
+use chrono::Utc;
+use serde::Deserialize;
+use utoipa::ToSchema;
+use utoipa_axum::{router::OpenApiRouter, routes};
+use uuid::Uuid;
+
+use common::{
+    auth::AuthUser,
+    db::{self, friendships},
+    error::ErrorResponse,
+    events::UserBlockedEvent,
+    extract::{Json, Path},
+    message_queue::MessageQueue,
+    models::{FriendshipStatus, PublicUser},
+    AppError, Event, Result,
+};
+
+use crate::{friends::publish_pair, UserState};
+
+pub(crate) fn routes() -> OpenApiRouter<Arc<UserState>> {
+    OpenApiRouter::new()
+        .routes(routes!(get_blocked, block_user))
+        .routes(routes!(unblock_user))
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct BlockRequest {
+    pub user_id: Uuid,
+}
+
+#[utoipa::path(
+    get,
+    path = "/users/@me/blocked",
+    tag = "users",
+    security(("bearer" = [])),
+    responses(
+        (status = 200, body = Vec<PublicUser>),
+    )
+)]
+async fn get_blocked(State(state): State<Arc<UserState>>, user: AuthUser) -> Result<Json<Vec<PublicUser>>> {
+    Ok(Json(friendships::list_blocked(state.app_state.db()?, user.id).await?))
+}
+
+#[utoipa::path(
+    post,
+    path = "/users/@me/blocked",
+    tag = "users",
+    request_body = BlockRequest,
+    security(("bearer" = [])),
+    responses(
+        (status = 204, description = "User blocked"),
+        (status = 400, body = ErrorResponse),
+        (status = 404, body = ErrorResponse),
+    )
+)]
+async fn block_user(
+    State(state): State<Arc<UserState>>,
+    user: AuthUser,
+    Json(payload): Json<BlockRequest>,
+) -> Result<StatusCode> {
+    let target_id = payload.user_id;
+    if target_id == user.id {
+        return Err(AppError::BadRequest("You cannot block yourself".to_string()));
+    }
+    let db = state.app_state.db()?;
+    if db::users::find_by_id(db, target_id).await?.is_none() {
+        return Err(AppError::NotFound("User not found".to_string()));
+    }
+
+    let mut tx = db.begin().await?;
+    friendships::lock_pair(&mut *tx, user.id, target_id).await?;
+    let were_friends = friendships::unlink(&mut *tx, user.id, target_id).await?;
+    friendships::upsert(&mut *tx, user.id, target_id, FriendshipStatus::Blocked).await?;
+    tx.commit().await?;
+
+    invalidate(&state, user.id).await;
+    if were_friends {
+        publish_pair(&state.app_state, user.id, target_id, Event::FriendRemoved).await;
+    }
+    let event = Event::UserBlocked(UserBlockedEvent {
+        user_id: user.id,
+        blocked_user_id: target_id,
+        timestamp: Utc::now(),
+    });
+    state.app_state.publish_best_effort(&event).await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    delete,
+    path = "/users/@me/blocked/{id}",
+    tag = "users",
+    params(("id" = Uuid, Path, description = "Blocked user id")),
+    security(("bearer" = [])),
+    responses(
+        (status = 204, description = "User unblocked"),
+        (status = 404, description = "User is not blocked", body = ErrorResponse),
+    )
+)]
+async fn unblock_user(
+    State(state): State<Arc<UserState>>,
+    user: AuthUser,
+    Path(target_id): Path<Uuid>,
+) -> Result<StatusCode> {
+    let deleted = friendships::delete(state.app_state.db()?, user.id, target_id, FriendshipStatus::Blocked).await?;
+    if !deleted {
+        return Err(AppError::NotFound("User is not blocked".to_string()));
+    }
+    invalidate(&state, user.id).await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Keeps the block list cache rebuildable from events; see
+/// [`common::blocks`]. Without NATS there is nothing to consume.
+pub(crate) fn spawn_projection(state: &UserState) {
+    let Ok(nats) = state.app_state.nats() else {
+        return;
+    };
+    let queue = MessageQueue::new(nats.clone());
+    let blocks = state.blocks.clone();
+    tokio::spawn(async move {
+        if let Err(e) = queue.spawn_projection(Arc::new(blocks)).await {
+            tracing::error!("Failed to start the block list projection: {}", e);
+        }
+    });
+}
+
+/// The block is already committed; a stale entry expires with its TTL.
+async fn invalidate(state: &UserState, user_id: Uuid) {
+    if let Err(e) = state.blocks.invalidate(user_id).await {
+        tracing::error!("Failed to invalidate block list for {}: {}", user_id, e);
+    }
+}
@@ -0,0 +1,375 @@
+//! Data exports: everything stored about a user, for access requests.
+//!
+//! A request only queues a job in `data_exports`; a worker in every
+//! user-service instance picks jobs up (see [`db::exports::claim_next`]),
+//! collects the user's data into a zip and uploads it under `exports/`,
+//! reporting every [`HEARTBEAT_INTERVAL`] so the job is not handed to
+//! another worker while it runs. The owner is then sent `DataExportReady`,
+//! which carries no link since events can be replayed; links valid for
+//! [`LINK_TTL`] come from `GET /users/@me/exports/{id}` until the archive
+//! itself expires after [`ARCHIVE_TTL`]. Removing expired objects is left to
+//! a bucket lifecycle rule on the `exports/` prefix.
+//!
+//! The archive holds `profile.json` (account, settings, connections and
+//! notes) and one NDJSON file per [`Section`]. It is written page by page to
+//! a temporary file and uploaded from there in parts, so a large history is
+//! never held in memory.
+
+use std::{
+    future::Future,
+    io::{Seek, SeekFrom, Write},
+    sync::Arc,
+    time::Duration,
+};
+
+use axum::{extract::State, http::StatusCode};
+use chrono::Utc;
+use serde::Serialize;
+use serde_json::{json, Value};
+use sqlx::types::Json as JsonColumn;
+use tokio::time::MissedTickBehavior;
+use utoipa::ToSchema;
+use utoipa_axum::{router::OpenApiRouter, routes};
+use uuid::Uuid;
+use zip::{write::SimpleFileOptions, CompressionMethod, ZipWriter};
+
+use common::{
+    auth::AuthUser,
+    db::{self, exports::Section},
+    error::ErrorResponse,
+    events::DataExportReadyEvent,
+    extract::{Json, Path},
+    models::{DataExport, ExportStatus},
+    AppError, Event, Result,
+};
+
+use crate::UserState;
+
+/// How long a download link works.
+const LINK_TTL: Duration = Duration::from_secs(24 * 3600);
+/// How long after completion an archive can be downloaded.
+const ARCHIVE_TTL: Duration = Duration::from_secs(7 * 24 * 3600);
+/// Runs before a job is given up on.
+const MAX_ATTEMPTS: i32 = 3;
+/// Wait before the second run, doubled for each one after.
+const RETRY_BACKOFF: Duration = Duration::from_secs(5 * 60);
+/// How often a running job is reported as alive.
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(60);
+/// A running job not reported for this long is assumed to have lost its
+/// worker.
+const STALE_AFTER: Duration = Duration::from_secs(10 * 60);
+/// How often idle workers look for jobs queued by other instances.
+const POLL_INTERVAL: Duration = Duration::from_secs(30);
+const PAGE_SIZE: i64 = 1000;
+const HISTORY_LIMIT: i64 = 10;
+
+pub(crate) fn routes() -> OpenApiRouter<Arc<UserState>> {
+    OpenApiRouter::new()
+        .routes(routes!(list_exports, request_export))
+        .routes(routes!(get_export))
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ExportView {
+    #[serde(flatten)]
+    pub export: DataExport,
+    /// Set while a completed archive is still available.
+    pub download_url: Option<String>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/users/@me/exports",
+    tag = "users",
+    security(("bearer" = [])),
+    responses(
+        (status = 202, description = "Export queued", body = DataExport),
+        (status = 409, description = "An export is already pending or running", body = ErrorResponse),
+    )
+)]
+async fn request_export(
+    State(state): State<Arc<UserState>>,
+    user: AuthUser,
+) -> Result<(StatusCode, Json<DataExport>)> {
+    let export = db::exports::create(state.app_state.db()?, user.id)
+        .await?
+        .ok_or_else(|| AppError::Conflict("An export is already in progress".to_string()))?;
+    state.exports_queued.notify_one();
+    Ok((StatusCode::ACCEPTED, Json(export)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/users/@me/exports",
+    tag = "users",
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Recent exports, newest first", body = Vec<ExportView>),
+    )
+)]
+async fn list_exports(State(state): State<Arc<UserState>>, user: AuthUser) -> Result<Json<Vec<ExportView>>> {
+    let exports = db::exports::list_for_user(state.app_state.db()?, user.id, HISTORY_LIMIT).await?;
+    let mut views = Vec::with_capacity(exports.len());
+    for export in exports {
+        views.push(view(&state, export).await?);
+    }
+    Ok(Json(views))
+}
+
+#[utoipa::path(
+    get,
+    path = "/users/@me/exports/{id}",
+    tag = "users",
+    params(("id" = Uuid, Path, description = "Export id")),
+    security(("bearer" = [])),
+    responses(
+        (status = 200, body = ExportView),
+        (status = 404, body = ErrorResponse),
+    )
+)]
+async fn get_export(
+    State(state): State<Arc<UserState>>,
+    user: AuthUser,
+    Path(export_id): Path<Uuid>,
+) -> Result<Json<ExportView>> {
+    let export = db::exports::find_for_user(state.app_state.db()?, user.id, export_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Export not found".to_string()))?;
+    Ok(Json(view(&state, export).await?))
+}
+
+async fn view(state: &UserState, export: DataExport) -> Result<ExportView> {
+    let available = export.status == ExportStatus::Completed && export.expires_at.is_some_and(|at| at > Utc::now());
+    let download_url = match (&export.object_key, available) {
+        (Some(key), true) => Some(state.app_state.storage()?.signed_url(key, LINK_TTL).await?),
+        _ => None,
+    };
+    Ok(ExportView { export, download_url })
+}
+
+/// Runs jobs until the process exits. Needs Postgres and storage; without
+/// them requests still queue, for an instance that has both.
+pub(crate) fn spawn_worker(state: Arc<UserState>) {
+    if state.app_state.try_db().is_none() || state.app_state.storage().is_err() {
+        tracing::warn!("Postgres or storage not configured; data export worker not started");
+        return;
+    }
+    tokio::spawn(async move {
+        loop {
+            match next_job(&state).await {
+                Ok(Some(export)) => run(&state, export).await,
+                Ok(None) => {
+                    tokio::select! {
+                        _ = state.exports_queued.notified() => {}
+                        _ = tokio::time::sleep(POLL_INTERVAL) => {}
+                    }
+                }
+                Err(e) => {
+                    tracing::error!("Failed to claim a data export: {}", e);
+                    tokio::time::sleep(POLL_INTERVAL).await;
+                }
+            }
+        }
+    });
+}
+
+async fn next_job(state: &UserState) -> Result<Option<DataExport>> {
+    db::exports::claim_next(state.app_state.db()?, STALE_AFTER).await
+}
+
+async fn run(state: &UserState, export: DataExport) {
+    // A job whose worker died during its last attempt comes back stale.
+    let result = if retry_delay(export.attempts - 1).is_none() {
+        Err(AppError::InternalServerError("Too many attempts".to_string()))
+    } else {
+        with_heartbeat(state, &export, build_and_upload(state, &export)).await
+    };
+    let error = match result {
+        Ok(()) => {
+            tracing::info!("Data export {} for {} completed", export.id, export.user_id);
+            return;
+        }
+        Err(e) => e.to_string(),
+    };
+
+    tracing::warn!("Data export {} failed (attempt {}): {}", export.id, export.attempts, error);
+    let recorded = match state.app_state.db() {
+        Ok(db) => db::exports::fail(db, export.id, export.attempts, &error, retry_delay(export.attempts)).await,
+        Err(e) => Err(e),
+    };
+    match recorded {
+        Ok(true) => {}
+        Ok(false) => tracing::info!("Data export {} was reclaimed; attempt {} dropped", export.id, export.attempts),
+        Err(e) => tracing::error!("Failed to record data export {} failure: {}", export.id, e),
+    }
+}
+
+/// Runs `work` while reporting the job as alive, and gives up on it as soon
+/// as another worker has reclaimed the job.
+async fn with_heartbeat(state: &UserState, export: &DataExport, work: impl Future<Output = Result<()>>) -> Result<()> {
+    let heartbeat = async {
+        let mut ticks = tokio::time::interval(HEARTBEAT_INTERVAL);
+        ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        // The first tick is immediate and the claim has only just been made.
+        ticks.tick().await;
+        loop {
+            ticks.tick().await;
+            match db::exports::heartbeat(state.app_state.db()?, export.id, export.attempts).await {
+                Ok(true) => {}
+                Ok(false) => return Err(reclaimed()),
+                // Missing one is harmless; the job only goes stale after several.
+                Err(e) => tracing::warn!("Failed to report data export {} as running: {}", export.id, e),
+            }
+        }
+    };
+    tokio::select! {
+        result = work => result,
+        result = heartbeat => result,
+    }
+}
+
+fn reclaimed() -> AppError {
+    AppError::Conflict("Data export was reclaimed by another worker".to_string())
+}
+
+/// How long to wait after a job's `attempts`th run failed, or `None` once it
+/// has had all of them.
+fn retry_delay(attempts: i32) -> Option<Duration> {
+    (attempts < MAX_ATTEMPTS).then(|| RETRY_BACKOFF * 2u32.pow(attempts.max(1) as u32 - 1))
+}
+
+async fn build_and_upload(state: &UserState, export: &DataExport) -> Result<()> {
+    let db = state.app_state.db()?;
+    let storage = state.app_state.storage()?;
+    let user_id = export.user_id;
+
+    let profile = profile_json(state, user_id).await?;
+    let mut zip = blocking(move || {
+        let mut zip = ZipWriter::new(tempfile::tempfile().map_err(io_error)?);
+        start_file(&mut zip, "profile.json")?;
+        zip.write_all(&profile).map_err(io_error)?;
+        Ok(zip)
+    })
+    .await?;
+    for section in Section::ALL {
+        zip = blocking(move || start_file(&mut zip, section.file_name()).map(|()| zip)).await?;
+        let mut after = Uuid::nil();
+        loop {
+            let page = db::exports::section_page(db, section, user_id, after, PAGE_SIZE).await?;
+            let next = match page.last() {
+                Some((id, _)) if page.len() as i64 == PAGE_SIZE => Some(*id),
+                _ => None,
+            };
+            zip = blocking(move || write_rows(&mut zip, &page).map(|()| zip)).await?;
+            match next {
+                Some(id) => after = id,
+                None => break,
+            }
+        }
+    }
+    let archive = blocking(move || {
+        let mut file = zip.finish().map_err(zip_error)?;
+        file.seek(SeekFrom::Start(0)).map_err(io_error)?;
+        Ok(file)
+    })
+    .await?;
+
+    let key = format!("exports/{}/{}.zip", user_id, export.id);
+    storage.put_stream(&key, tokio::fs::File::from_std(archive), "application/zip").await?;
+
+    let completed_at = Utc::now();
+    let expires_at = completed_at + ARCHIVE_TTL;
+    if !db::exports::complete(db, export.id, export.attempts, &key, expires_at).await? {
+        return Err(reclaimed());
+    }
+
+    let event = Event::DataExportReady(DataExportReadyEvent {
+        user_id,
+        export_id: export.id,
+        timestamp: completed_at,
+    });
+    state.app_state.publish_best_effort(&event).await;
+    Ok(())
+}
+
+async fn profile_json(state: &UserState, user_id: Uuid) -> Result<Vec<u8>> {
+    let db = state.app_state.db()?;
+    let user = db::users::find_by_id(db, user_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+    let (settings, _) = db::settings::find(db, user_id).await?;
+    let profile = json!({
+        "user": user,
+        "discoverable": db::users::is_discoverable(db, user_id).await?,
+        "settings": settings,
+        "connections": db::connections::list_for_user(db, user_id, false).await?,
+        "notes": db::notes::list_for_owner(db, user_id).await?,
+        "exported_at": Utc::now(),
+    });
+    serde_json::to_vec_pretty(&profile).map_err(json_error)
+}
+
+/// Runs `f` off the async workers; compressing and writing the archive
+/// both block.
+async fn blocking<T: Send + 'static>(f: impl FnOnce() -> Result<T> + Send + 'static) -> Result<T> {
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(|e| AppError::InternalServerError(format!("Archiving panicked: {}", e)))?
+}
+
+fn start_file<W: Write + Seek>(zip: &mut ZipWriter<W>, name: &str) -> Result<()> {
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    zip.start_file(name, options).map_err(zip_error)
+}
+
+/// Appends `rows` to the current file, one JSON object per line.
+fn write_rows<W: Write + Seek>(zip: &mut ZipWriter<W>, rows: &[(Uuid, JsonColumn<Value>)]) -> Result<()> {
+    for (_, row) in rows {
+        serde_json::to_writer(&mut *zip, &row.0).map_err(json_error)?;
+        zip.write_all(b"\n").map_err(io_error)?;
+    }
+    Ok(())
+}
+
+fn zip_error(err: zip::result::ZipError) -> AppError {
+    AppError::InternalServerError(format!("Archiving failed: {}", err))
+}
+
+fn io_error(err: std::io::Error) -> AppError {
+    AppError::InternalServerError(format!("Archiving failed: {}", err))
+}
+
+fn json_error(err: serde_json::Error) -> AppError {
+    AppError::InternalServerError(format!("Serialising export failed: {}", err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Cursor, Read};
+
+    #[test]
+    fn test_archive_holds_every_file() {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        start_file(&mut zip, "profile.json").unwrap();
+        zip.write_all(b"{}").unwrap();
+        start_file(&mut zip, "messages.ndjson").unwrap();
+        for id in [1, 2] {
+            write_rows(&mut zip, &[(Uuid::nil(), JsonColumn(json!({ "id": id })))]).unwrap();
+        }
+        let bytes = zip.finish().unwrap().into_inner();
+
+        let mut zip = zip::ZipArchive::new(Cursor::new(bytes)).unwrap();
+        assert_eq!(zip.len(), 2);
+        let mut messages = String::new();
+        zip.by_name("messages.ndjson").unwrap().read_to_string(&mut messages).unwrap();
+        assert_eq!(messages, "{\"id\":1}\n{\"id\":2}\n");
+    }
+
+    #[test]
+    fn test_retry_delay() {
+        assert_eq!(retry_delay(1), Some(RETRY_BACKOFF));
+        assert_eq!(retry_delay(2), Some(RETRY_BACKOFF * 2));
+        assert_eq!(retry_delay(MAX_ATTEMPTS), None);
+    }
+}
@@ -0,0 +1,299 @@
+//! Friend requests and friendships.
+//!
+//! A request is a `pending` row from requester to addressee. Accepting it
+//! turns it into a pair of `accepted` rows; declining or cancelling deletes
+//! it. Sending a request to someone who already asked you accepts theirs.
+//! Every change to a pair runs in one transaction under
+//! [`friendships::lock_pair`], and `FriendAdded`/`FriendRemoved` are
+//! published once for each side after it commits.
+
+use std::sync::Arc;
+
+use axum::{extract::State, http::StatusCode};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use utoipa_axum::{router::OpenApiRouter, routes};
+use uuid::Uuid;
+
+use common::{
+    auth::AuthUser,
+    db::{
+        self,
+        friendships::{self, Relationship},
+    },
+    error::ErrorResponse,
+    events::FriendEvent,
+    extract::{Json, Path},
+    models::{Friend, FriendRequest, FriendshipStatus, PublicUser},
+    AppError, AppState, Event, Result,
+};
+
+use crate::UserState;
+
+pub(crate) fn routes() -> OpenApiRouter<Arc<UserState>> {
+    OpenApiRouter::new()
+        .routes(routes!(get_friends, add_friend))
+        .routes(routes!(remove_friend))
+        .routes(routes!(get_requests))
+        .routes(routes!(accept_request))
+        .routes(routes!(decline_request))
+        .routes(routes!(cancel_request))
+}
+
+/// Either field identifies the user to befriend.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct AddFriendRequest {
+    pub user_id: Option<Uuid>,
+    pub username: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AddFriendResponse {
+    pub user: PublicUser,
+    /// `pending` for a new request, `accepted` if it answered theirs.
+    pub status: FriendshipStatus,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct FriendRequests {
+    pub incoming: Vec<FriendRequest>,
+    pub outgoing: Vec<FriendRequest>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/users/@me/friends",
+    tag = "friends",
+    security(("bearer" = [])),
+    responses(
+        (status = 200, body = Vec<Friend>),
+    )
+)]
+async fn get_friends(State(state): State<Arc<UserState>>, user: AuthUser) -> Result<Json<Vec<Friend>>> {
+    Ok(Json(friendships::list_friends(state.app_state.db()?, user.id).await?))
+}
+
+#[utoipa::path(
+    post,
+    path = "/users/@me/friends",
+    tag = "friends",
+    request_body = AddFriendRequest,
+    security(("bearer" = [])),
+    responses(
+        (status = 200, body = AddFriendResponse),
+        (status = 400, body = ErrorResponse),
+        (status = 403, description = "Either side has blocked the other", body = ErrorResponse),
+        (status = 404, body = ErrorResponse),
+        (status = 409, description = "Already friends or already requested", body = ErrorResponse),
+    )
+)]
+async fn add_friend(
+    State(state): State<Arc<UserState>>,
+    user: AuthUser,
+    Json(payload): Json<AddFriendRequest>,
+) -> Result<Json<AddFriendResponse>> {
+    let db = state.app_state.db()?;
+    let target = match (payload.user_id, payload.username.as_deref()) {
+        (Some(id), _) => db::users::find_by_id(db, id).await?,
+        (None, Some(username)) => db::users::find_by_username(db, username).await?,
+        (None, None) => return Err(AppError::BadRequest("Provide user_id or username".to_string())),
+    }
+    .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+
+    if target.id == user.id {
+        return Err(AppError::BadRequest("You cannot add yourself as a friend".to_string()));
+    }
+
+    let mut tx = db.begin().await?;
+    friendships::lock_pair(&mut *tx, user.id, target.id).await?;
+    let relationship = friendships::relationship(&mut *tx, user.id, target.id).await?;
+
+    let status = request_status(relationship)?;
+    match status {
+        FriendshipStatus::Accepted => befriend(&mut tx, user.id, target.id).await?,
+        _ => friendships::upsert(&mut *tx, user.id, target.id, FriendshipStatus::Pending).await?,
+    }
+    tx.commit().await?;
+
+    if status == FriendshipStatus::Accepted {
+        publish_pair(&state.app_state, user.id, target.id, Event::FriendAdded).await;
+    }
+    Ok(Json(AddFriendResponse { user: target.into(), status }))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/users/@me/friends/{id}",
+    tag = "friends",
+    params(("id" = Uuid, Path, description = "Friend user id")),
+    security(("bearer" = [])),
+    responses(
+        (status = 204, description = "Friend removed"),
+        (status = 404, body = ErrorResponse),
+    )
+)]
+async fn remove_friend(
+    State(state): State<Arc<UserState>>,
+    user: AuthUser,
+    Path(friend_id): Path<Uuid>,
+) -> Result<StatusCode> {
+    let mut tx = state.app_state.db()?.begin().await?;
+    friendships::lock_pair(&mut *tx, user.id, friend_id).await?;
+    let removed = friendships::delete(&mut *tx, user.id, friend_id, FriendshipStatus::Accepted).await?;
+    if !removed {
+        return Err(AppError::NotFound("Not friends with this user".to_string()));
+    }
+    friendships::delete(&mut *tx, friend_id, user.id, FriendshipStatus::Accepted).await?;
+    tx.commit().await?;
+
+    publish_pair(&state.app_state, user.id, friend_id, Event::FriendRemoved).await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    get,
+    path = "/users/@me/friends/requests",
+    tag = "friends",
+    security(("bearer" = [])),
+    responses(
+        (status = 200, body = FriendRequests),
+    )
+)]
+async fn get_requests(State(state): State<Arc<UserState>>, user: AuthUser) -> Result<Json<FriendRequests>> {
+    let db = state.app_state.db()?;
+    let (incoming, outgoing) = tokio::try_join!(
+        friendships::incoming_requests(db, user.id),
+        friendships::outgoing_requests(db, user.id),
+    )?;
+    Ok(Json(FriendRequests { incoming, outgoing }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/users/@me/friends/requests/{id}/accept",
+    tag = "friends",
+    params(("id" = Uuid, Path, description = "Requester user id")),
+    security(("bearer" = [])),
+    responses(
+        (status = 204, description = "Request accepted"),
+        (status = 404, description = "No pending request from this user", body = ErrorResponse),
+    )
+)]
+async fn accept_request(
+    State(state): State<Arc<UserState>>,
+    user: AuthUser,
+    Path(requester_id): Path<Uuid>,
+) -> Result<StatusCode> {
+    let mut tx = state.app_state.db()?.begin().await?;
+    friendships::lock_pair(&mut *tx, user.id, requester_id).await?;
+    let relationship = friendships::relationship(&mut *tx, user.id, requester_id).await?;
+    if relationship.incoming != Some(FriendshipStatus::Pending) {
+        return Err(AppError::NotFound("No pending request from this user".to_string()));
+    }
+    befriend(&mut tx, user.id, requester_id).await?;
+    tx.commit().await?;
+
+    publish_pair(&state.app_state, user.id, requester_id, Event::FriendAdded).await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    post,
+    path = "/users/@me/friends/requests/{id}/decline",
+    tag = "friends",
+    params(("id" = Uuid, Path, description = "Requester user id")),
+    security(("bearer" = [])),
+    responses(
+        (status = 204, description = "Request declined"),
+        (status = 404, description = "No pending request from this user", body = ErrorResponse),
+    )
+)]
+async fn decline_request(
+    State(state): State<Arc<UserState>>,
+    user: AuthUser,
+    Path(requester_id): Path<Uuid>,
+) -> Result<StatusCode> {
+    let deleted = friendships::delete(state.app_state.db()?, requester_id, user.id, FriendshipStatus::Pending).await?;
+    if !deleted {
+        return Err(AppError::NotFound("No pending request from this user".to_string()));
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    delete,
+    path = "/users/@me/friends/requests/{id}",
+    tag = "friends",
+    params(("id" = Uuid, Path, description = "Addressee user id")),
+    security(("bearer" = [])),
+    responses(
+        (status = 204, description = "Request cancelled"),
+        (status = 404, description = "No pending request to this user", body = ErrorResponse),
+    )
+)]
+async fn cancel_request(
+    State(state): State<Arc<UserState>>,
+    user: AuthUser,
+    Path(addressee_id): Path<Uuid>,
+) -> Result<StatusCode> {
+    let deleted = friendships::delete(state.app_state.db()?, user.id, addressee_id, FriendshipStatus::Pending).await?;
+    if !deleted {
+        return Err(AppError::NotFound("No pending request to this user".to_string()));
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// What a friend request does to the pair: `Accepted` when it answers
+/// their pending request, `Pending` when it starts a new one.
+fn request_status(relationship: Relationship) -> Result<FriendshipStatus> {
+    if relationship.is_blocked() {
+        return Err(AppError::Forbidden("You cannot send a friend request to this user".to_string()));
+    }
+    match (relationship.outgoing, relationship.incoming) {
+        (Some(FriendshipStatus::Accepted), _) => Err(AppError::Conflict("You are already friends".to_string())),
+        (Some(FriendshipStatus::Pending), _) => Err(AppError::Conflict("Friend request already sent".to_string())),
+        (_, Some(FriendshipStatus::Pending)) => Ok(FriendshipStatus::Accepted),
+        _ => Ok(FriendshipStatus::Pending),
+    }
+}
+
+/// Replaces the pending request between the pair with mutual friendship.
+async fn befriend(tx: &mut sqlx::PgConnection, a: Uuid, b: Uuid) -> Result<()> {
+    friendships::upsert(&mut *tx, a, b, FriendshipStatus::Accepted).await?;
+    friendships::upsert(&mut *tx, b, a, FriendshipStatus::Accepted).await
+}
+
+/// Publishes `event` addressed to each side.
+pub(crate) async fn publish_pair(state: &AppState, a: Uuid, b: Uuid, event: fn(FriendEvent) -> Event) {
+    let timestamp = Utc::now();
+    for (user_id, friend_id) in [(a, b), (b, a)] {
+        state.publish_best_effort(&event(FriendEvent { user_id, friend_id, timestamp })).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn relationship(outgoing: Option<FriendshipStatus>, incoming: Option<FriendshipStatus>) -> Relationship {
+        Relationship { outgoing, incoming }
+    }
+
+    #[test]
+    fn test_request_status() {
+        use FriendshipStatus::*;
+
+        assert_eq!(request_status(relationship(None, None)).unwrap(), Pending);
+        assert_eq!(request_status(relationship(None, Some(Pending))).unwrap(), Accepted);
+        let conflicts = [(Some(Accepted), Some(Accepted)), (Some(Pending), None), (Some(Pending), Some(Pending))];
+        for (outgoing, incoming) in conflicts {
+            let result = request_status(relationship(outgoing, incoming));
+            assert!(matches!(result, Err(AppError::Conflict(_))), "{:?} {:?}", outgoing, incoming);
+        }
+        for (outgoing, incoming) in [(Some(Blocked), None), (None, Some(Blocked)), (Some(Pending), Some(Blocked))] {
+            let result = request_status(relationship(outgoing, incoming));
+            assert!(matches!(result, Err(AppError::Forbidden(_))), "{:?} {:?}", outgoing, incoming);
+        }
+    }
+}
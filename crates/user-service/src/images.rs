@@ -0,0 +1,304 @@
+//! Processing for uploaded profile images.
+//!
+//! The format is sniffed from the bytes, never taken from the client. The
+//! image is decoded under size limits, rotated per its EXIF orientation,
+//! centre-cropped to the target aspect ratio and resized to each of the
+//! spec's widths. Re-encoding from decoded pixels drops EXIF and all other
+//! metadata. Every upload gets static WebP variants; animated GIF and WebP
+//! uploads also get animated GIF variants.
+
+use std::io::Cursor;
+
+use image::{
+    codecs::{
+        gif::{GifDecoder, GifEncoder, Repeat},
+        webp::{WebPDecoder, WebPEncoder},
+    },
+    imageops::{self, FilterType},
+    AnimationDecoder, DynamicImage, Frame, ImageDecoder, ImageFormat, ImageReader, Limits, RgbaImage,
+};
+use sha2::{Digest, Sha256};
+
+use common::{AppError, Result};
+
+const MAX_DIMENSION: u32 = 4096;
+const MIN_DIMENSION: u32 = 32;
+const MAX_FRAMES: usize = 60;
+/// Decoded pixels across all frames. A frame is only decoded if it still
+/// fits, so an animation that fills the budget exactly is refused too.
+const MAX_ANIMATION_PIXELS: u64 = 64 * 1024 * 1024;
+/// Fast quantisation; avatars are small and viewed small.
+const GIF_SPEED: i32 = 10;
+
+/// What kind of image to produce and where to keep it.
+pub struct ImageSpec {
+    /// Object key prefix and URL segment.
+    pub kind: &'static str,
+    /// Width to height.
+    pub aspect: (u32, u32),
+    pub widths: &'static [u32],
+    pub max_upload_bytes: usize,
+}
+
+pub const AVATAR: ImageSpec = ImageSpec {
+    kind: "avatars",
+    aspect: (1, 1),
+    widths: &[128, 256, 512],
+    max_upload_bytes: 8 * 1024 * 1024,
+};
+
+pub const BANNER: ImageSpec = ImageSpec {
+    kind: "banners",
+    aspect: (5, 2),
+    widths: &[600, 1200],
+    max_upload_bytes: 10 * 1024 * 1024,
+};
+
+pub struct Variant {
+    /// File name within the image's key prefix, e.g. `256.webp`.
+    pub name: String,
+    pub content_type: &'static str,
+    pub bytes: Vec<u8>,
+}
+
+pub struct ProcessedImage {
+    /// Hex SHA-256 of the upload, prefixed with `a_` if animated.
+    pub hash: String,
+    pub animated: bool,
+    pub variants: Vec<Variant>,
+}
+
+impl ImageSpec {
+    /// The object key for one variant.
+    pub fn key(&self, hash: &str, name: &str) -> String {
+        format!("{}/{}/{}", self.kind, hash, name)
+    }
+
+    fn height_for(&self, width: u32) -> u32 {
+        width * self.aspect.1 / self.aspect.0
+    }
+}
+
+/// CPU-bound; run it on a blocking thread.
+pub fn process(bytes: &[u8], spec: &ImageSpec) -> Result<ProcessedImage> {
+    if bytes.len() > spec.max_upload_bytes {
+        return Err(AppError::BadRequest(format!(
+            "Image must be at most {} MiB",
+            spec.max_upload_bytes / (1024 * 1024)
+        )));
+    }
+    let format = image::guess_format(bytes).map_err(|_| unsupported())?;
+    if !matches!(format, ImageFormat::Png | ImageFormat::Jpeg | ImageFormat::Gif | ImageFormat::WebP) {
+        return Err(unsupported());
+    }
+
+    let frames = match format {
+        ImageFormat::Gif => decode_animation(GifDecoder::new(Cursor::new(bytes)).map_err(invalid)?)?,
+        ImageFormat::WebP => {
+            let decoder = WebPDecoder::new(Cursor::new(bytes)).map_err(invalid)?;
+            if decoder.has_animation() {
+                decode_animation(decoder)?
+            } else {
+                vec![decode_still(bytes, format)?]
+            }
+        }
+        _ => vec![decode_still(bytes, format)?],
+    };
+
+    let (width, height) = frames[0].buffer().dimensions();
+    if width < MIN_DIMENSION || height < MIN_DIMENSION {
+        return Err(AppError::BadRequest(format!(
+            "Image must be at least {}x{} pixels",
+            MIN_DIMENSION, MIN_DIMENSION
+        )));
+    }
+    let animated = frames.len() > 1;
+    let crop = centre_crop(width, height, spec.aspect);
+
+    let mut variants = Vec::new();
+    for &target_width in spec.widths {
+        let target = (target_width, spec.height_for(target_width));
+        let resized: Vec<Frame> = frames
+            .iter()
+            .map(|frame| {
+                let (x, y, w, h) = crop;
+                let cropped = imageops::crop_imm(frame.buffer(), x, y, w, h).to_image();
+                let buffer = imageops::resize(&cropped, target.0, target.1, FilterType::Lanczos3);
+                Frame::from_parts(buffer, 0, 0, frame.delay())
+            })
+            .collect();
+
+        variants.push(Variant {
+            name: format!("{}.webp", target_width),
+            content_type: "image/webp",
+            bytes: encode_webp(resized[0].buffer())?,
+        });
+        if animated {
+            variants.push(Variant {
+                name: format!("{}.gif", target_width),
+                content_type: "image/gif",
+                bytes: encode_gif(resized)?,
+            });
+        }
+    }
+
+    let digest = hex::encode(Sha256::digest(bytes));
+    let hash = if animated { format!("a_{}", digest) } else { digest };
+    Ok(ProcessedImage { hash, animated, variants })
+}
+
+fn limits() -> Limits {
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_DIMENSION);
+    limits.max_image_height = Some(MAX_DIMENSION);
+    limits
+}
+
+fn decode_still(bytes: &[u8], format: ImageFormat) -> Result<Frame> {
+    let mut reader = ImageReader::with_format(Cursor::new(bytes), format);
+    reader.limits(limits());
+    let mut decoder = reader.into_decoder().map_err(invalid)?;
+    let orientation = decoder.orientation().map_err(invalid)?;
+    let mut image = DynamicImage::from_decoder(decoder).map_err(invalid)?;
+    image.apply_orientation(orientation);
+    Ok(Frame::new(image.to_rgba8()))
+}
+
+fn decode_animation<'a>(mut decoder: impl ImageDecoder + AnimationDecoder<'a>) -> Result<Vec<Frame>> {
+    decoder.set_limits(limits()).map_err(invalid)?;
+    let (width, height) = decoder.dimensions();
+    let frame_pixels = u64::from(width) * u64::from(height);
+
+    let mut frames = Vec::new();
+    let mut decoded = decoder.into_frames();
+    loop {
+        // Every frame is decoded at the full canvas size.
+        if !next_frame_fits(frames.len(), frame_pixels) {
+            return Err(AppError::BadRequest("Animation is too large; use fewer or smaller frames".to_string()));
+        }
+        let Some(frame) = decoded.next() else {
+            break;
+        };
+        frames.push(frame.map_err(invalid)?);
+        if frames.len() > MAX_FRAMES {
+            return Err(AppError::BadRequest(format!(
+                "Animated images are limited to {} frames",
+                MAX_FRAMES
+            )));
+        }
+    }
+    if frames.is_empty() {
+        return Err(AppError::BadRequest("Image has no frames".to_string()));
+    }
+    Ok(frames)
+}
+
+/// Whether another `frame_pixels` frame stays within the budget after
+/// `decoded` frames.
+fn next_frame_fits(decoded: usize, frame_pixels: u64) -> bool {
+    (decoded as u64 + 1) * frame_pixels <= MAX_ANIMATION_PIXELS
+}
+
+/// The largest `aspect`-shaped rectangle centred in the image.
+fn centre_crop(width: u32, height: u32, (aw, ah): (u32, u32)) -> (u32, u32, u32, u32) {
+    let (w, h) = if u64::from(width) * u64::from(ah) > u64::from(height) * u64::from(aw) {
+        (height * aw / ah, height)
+    } else {
+        (width, width * ah / aw)
+    };
+    ((width - w) / 2, (height - h) / 2, w, h)
+}
+
+fn encode_webp(image: &RgbaImage) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    image
+        .write_with_encoder(WebPEncoder::new_lossless(&mut bytes))
+        .map_err(encode_error)?;
+    Ok(bytes)
+}
+
+fn encode_gif(frames: Vec<Frame>) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    {
+        let mut encoder = GifEncoder::new_with_speed(&mut bytes, GIF_SPEED);
+        encoder.set_repeat(Repeat::Infinite).map_err(encode_error)?;
+        encoder.encode_frames(frames).map_err(encode_error)?;
+    }
+    Ok(bytes)
+}
+
+fn unsupported() -> AppError {
+    AppError::BadRequest("Unsupported image type; use PNG, JPEG, GIF or WebP".to_string())
+}
+
+fn invalid(err: image::ImageError) -> AppError {
+    match err {
+        image::ImageError::Limits(_) => AppError::BadRequest(format!(
+            "Image must be at most {}x{} pixels",
+            MAX_DIMENSION, MAX_DIMENSION
+        )),
+        other => AppError::BadRequest(format!("Could not decode image: {}", other)),
+    }
+}
+
+fn encode_error(err: image::ImageError) -> AppError {
+    AppError::InternalServerError(format!("Image encoding failed: {}", err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{codecs::png::PngEncoder, Delay, Rgba};
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let image = RgbaImage::from_pixel(width, height, Rgba([200, 10, 10, 255]));
+        let mut bytes = Vec::new();
+        image.write_with_encoder(PngEncoder::new(&mut bytes)).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn test_still_image_is_cropped_into_each_size() {
+        let processed = process(&png(300, 200), &BANNER).unwrap();
+        assert!(!processed.animated);
+        let names: Vec<_> = processed.variants.iter().map(|v| v.name.as_str()).collect();
+        assert_eq!(names, ["600.webp", "1200.webp"]);
+
+        let small = image::load_from_memory(&processed.variants[0].bytes).unwrap();
+        assert_eq!((small.width(), small.height()), (600, 240));
+        assert_eq!(centre_crop(300, 200, (5, 2)), (0, 40, 300, 120));
+    }
+
+    #[test]
+    fn test_animation_is_limited_and_kept_animated() {
+        let gif = |frames: usize| {
+            let mut bytes = Vec::new();
+            {
+                let mut encoder = GifEncoder::new(&mut bytes);
+                for i in 0..frames {
+                    let buffer = RgbaImage::from_pixel(64, 64, Rgba([i as u8 * 3, 0, 0, 255]));
+                    let delay = Delay::from_numer_denom_ms(100, 1);
+                    encoder.encode_frame(Frame::from_parts(buffer, 0, 0, delay)).unwrap();
+                }
+            }
+            bytes
+        };
+
+        let processed = process(&gif(3), &AVATAR).unwrap();
+        assert!(processed.animated && processed.hash.starts_with("a_"));
+        assert_eq!(processed.variants.len(), AVATAR.widths.len() * 2);
+
+        assert!(matches!(process(&gif(MAX_FRAMES + 1), &AVATAR), Err(AppError::BadRequest(_))));
+        assert!(matches!(process(b"GIF89a not really", &AVATAR), Err(AppError::BadRequest(_))));
+        assert!(matches!(process(b"%PDF-1.7", &AVATAR), Err(AppError::BadRequest(_))));
+    }
+
+    #[test]
+    fn test_frame_budget_is_checked_before_decoding() {
+        let full = u64::from(MAX_DIMENSION) * u64::from(MAX_DIMENSION);
+        let fit = (MAX_ANIMATION_PIXELS / full) as usize;
+        assert!(next_frame_fits(fit - 1, full));
+        assert!(!next_frame_fits(fit, full));
+        assert!(next_frame_fits(MAX_FRAMES, 64 * 64));
+    }
+}
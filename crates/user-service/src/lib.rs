@@ -0,0 +1,51 @@
+use axum::Router;
+use std::sync::Arc;
+use tokio::sync::{Notify, OnceCell};
+
+use common::{auth, blocks::BlockList, models::Mutuals, openapi, typed_cache::Cache, AppState, Config};
+
+mod blocks;
+mod exports;
+mod friends;
+mod images;
+mod mutuals;
+mod notes;
+mod profile;
+mod search;
+mod settings;
+mod uploads;
+
+struct UserState {
+    app_state: AppState,
+    blocks: BlockList,
+    /// Connected on first use; see [`mutuals`].
+    mutuals: OnceCell<Cache<Mutuals>>,
+    /// Wakes this instance's export worker when a job is queued here.
+    exports_queued: Notify,
+}
+
+pub fn router(config: &Config, app_state: AppState) -> Router {
+    let state = Arc::new(UserState {
+        blocks: BlockList::new(app_state.clone()),
+        mutuals: OnceCell::new(),
+        exports_queued: Notify::new(),
+        app_state,
+    });
+    exports::spawn_worker(state.clone());
+    blocks::spawn_projection(&state);
+
+    openapi::into_router(
+        openapi::service_router("user-service")
+            .merge(profile::routes())
+            .merge(mutuals::routes())
+            .merge(friends::routes())
+            .merge(blocks::routes())
+            .merge(notes::routes())
+            .merge(search::routes())
+            .merge(settings::routes())
+            .merge(uploads::routes())
+            .merge(exports::routes())
+            .with_state(state),
+    )
+    .layer(auth::layer(&config.jwt_secret))
+}
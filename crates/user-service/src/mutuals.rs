@@ -0,0 +1,94 @@
+//! Profiles with what the viewer has in common with their owner.
+//!
+//! Mutual servers and friends are intersections of the two users'
+//! `server_members` and accepted `friendships` rows. They are cached per
+//! viewer and target for a short TTL rather than invalidated: membership and
+//! friendship changes show up within [`TTL`]. Blocks are applied on every
+//! request through the [`BlockList`](common::blocks::BlockList), as in
+//! search: nothing is shared with a user the viewer has blocked, and mutual
+//! friends blocked in either direction are left out.
+
+use std::{sync::Arc, time::Duration};
+
+use axum::extract::State;
+use serde::Serialize;
+use utoipa::ToSchema;
+use utoipa_axum::{router::OpenApiRouter, routes};
+use uuid::Uuid;
+
+use common::{
+    auth::AuthUser,
+    db,
+    error::ErrorResponse,
+    extract::{Json, Path},
+    models::{Mutuals, UserProfile},
+    typed_cache::Cache,
+    AppError, Result,
+};
+
+use crate::{profile::load_profile, UserState};
+
+pub const NAMESPACE: &str = "mutuals";
+
+const TTL: Duration = Duration::from_secs(60);
+
+pub(crate) fn routes() -> OpenApiRouter<Arc<UserState>> {
+    OpenApiRouter::new().routes(routes!(get_profile))
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ProfileWithMutuals {
+    #[serde(flatten)]
+    pub profile: UserProfile,
+    /// Empty on the viewer's own profile and between blocked users.
+    #[serde(flatten)]
+    pub mutuals: Mutuals,
+}
+
+#[utoipa::path(
+    get,
+    path = "/users/{id}/profile",
+    tag = "users",
+    params(("id" = Uuid, Path, description = "User id")),
+    security(("bearer" = [])),
+    responses(
+        (status = 200, body = ProfileWithMutuals),
+        (status = 404, body = ErrorResponse),
+    )
+)]
+async fn get_profile(
+    State(state): State<Arc<UserState>>,
+    viewer: AuthUser,
+    Path(user_id): Path<Uuid>,
+) -> Result<Json<ProfileWithMutuals>> {
+    let profile = load_profile(&state, viewer.id, user_id).await?;
+    let mutuals = if user_id == viewer.id || state.blocks.either_blocked(viewer.id, user_id).await? {
+        Mutuals::default()
+    } else {
+        let mut mutuals = load_mutuals(&state, viewer.id, user_id).await?;
+        let friend_ids: Vec<Uuid> = mutuals.mutual_friends.iter().map(|friend| friend.id).collect();
+        let blocked = state.blocks.blocked_among(viewer.id, &friend_ids).await?;
+        mutuals.mutual_friends.retain(|friend| !blocked.contains(&friend.id));
+        mutuals
+    };
+    Ok(Json(ProfileWithMutuals { profile, mutuals }))
+}
+
+/// Keyed by both ids in order, since mutual servers carry the target's
+/// nicknames.
+async fn load_mutuals(state: &UserState, viewer_id: Uuid, user_id: Uuid) -> Result<Mutuals> {
+    let cache = state
+        .mutuals
+        .get_or_try_init(|| async { Ok::<_, AppError>(Cache::new(state.app_state.cache().await?, NAMESPACE)) })
+        .await?;
+    cache
+        .get_or_load(&format!("{}:{}", viewer_id, user_id), TTL, || async {
+            let db = state.app_state.db()?;
+            let (mutual_servers, mutual_friends) = tokio::try_join!(
+                db::servers::list_mutual(db, viewer_id, user_id),
+                db::friendships::list_mutual(db, viewer_id, user_id),
+            )?;
+            Ok(Mutuals { mutual_servers, mutual_friends })
+        })
+        .await
+}
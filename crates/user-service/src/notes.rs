@@ -0,0 +1,137 @@
+//! Private notes and nicknames for other users.
+//!
+//! Notes can be kept about anyone the owner can see; nicknames only for
+//! friends, though one set earlier survives an unfriending. Both come back
+//! in the owner's friend list and on profiles they view, and every change
+//! is published as `UserNoteUpdated` so the owner's other sessions pick it
+//! up.
+
+use std::sync::Arc;
+
+use axum::extract::State;
+use chrono::Utc;
+use serde::Deserialize;
+use utoipa::ToSchema;
+use utoipa_axum::{router::OpenApiRouter, routes};
+use uuid::Uuid;
+use validator::Validate;
+
+use common::{
+    auth::AuthUser,
+    db::{self, friendships},
+    error::ErrorResponse,
+    events::UserNoteUpdatedEvent,
+    extract::{Json, Path},
+    models::UserNote,
+    AppError, Event, Result,
+};
+
+use crate::{profile::nullable, UserState};
+
+pub(crate) fn routes() -> OpenApiRouter<Arc<UserState>> {
+    OpenApiRouter::new()
+        .routes(routes!(get_notes))
+        .routes(routes!(get_note, update_note))
+}
+
+/// Omitted fields are left unchanged; `null` clears one.
+#[derive(Debug, Default, Deserialize, Validate, ToSchema)]
+pub struct UpdateNoteRequest {
+    #[serde(default, deserialize_with = "nullable")]
+    #[validate(length(min = 1, max = 256))]
+    pub note: Option<Option<String>>,
+    #[serde(default, deserialize_with = "nullable")]
+    #[validate(length(min = 1, max = 32))]
+    pub nickname: Option<Option<String>>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/users/@me/notes",
+    tag = "users",
+    security(("bearer" = [])),
+    responses(
+        (status = 200, body = Vec<UserNote>),
+    )
+)]
+async fn get_notes(State(state): State<Arc<UserState>>, user: AuthUser) -> Result<Json<Vec<UserNote>>> {
+    Ok(Json(db::notes::list_for_owner(state.app_state.db()?, user.id).await?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/users/@me/notes/{id}",
+    tag = "users",
+    params(("id" = Uuid, Path, description = "User the note is about")),
+    security(("bearer" = [])),
+    responses(
+        (status = 200, body = UserNote),
+        (status = 404, description = "No note or nickname for this user", body = ErrorResponse),
+    )
+)]
+async fn get_note(
+    State(state): State<Arc<UserState>>,
+    user: AuthUser,
+    Path(target_id): Path<Uuid>,
+) -> Result<Json<UserNote>> {
+    db::notes::find(state.app_state.db()?, user.id, target_id)
+        .await?
+        .map(Json)
+        .ok_or_else(|| AppError::NotFound("No note for this user".to_string()))
+}
+
+#[utoipa::path(
+    patch,
+    path = "/users/@me/notes/{id}",
+    tag = "users",
+    params(("id" = Uuid, Path, description = "User the note is about")),
+    request_body = UpdateNoteRequest,
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "The note as stored; both fields `null` once cleared", body = UserNote),
+        (status = 400, description = "Invalid, about yourself, or a nickname for a non-friend", body = ErrorResponse),
+        (status = 404, body = ErrorResponse),
+    )
+)]
+async fn update_note(
+    State(state): State<Arc<UserState>>,
+    user: AuthUser,
+    Path(target_id): Path<Uuid>,
+    Json(payload): Json<UpdateNoteRequest>,
+) -> Result<Json<UserNote>> {
+    payload.validate()?;
+    if target_id == user.id {
+        return Err(AppError::BadRequest("You cannot keep a note about yourself".to_string()));
+    }
+    let not_found = || AppError::NotFound("User not found".to_string());
+    if state.blocks.has_blocked(target_id, user.id).await? {
+        return Err(not_found());
+    }
+    let db = state.app_state.db()?;
+    if db::users::find_by_id(db, target_id).await?.is_none() {
+        return Err(not_found());
+    }
+    let sets_nickname = matches!(payload.nickname, Some(Some(_)));
+    if sets_nickname && !friendships::relationship(db, user.id, target_id).await?.is_friends() {
+        return Err(AppError::BadRequest("Nicknames can only be set for friends".to_string()));
+    }
+
+    let note = payload.note.as_ref().map(Option::as_deref);
+    let nickname = payload.nickname.as_ref().map(Option::as_deref);
+    let mut tx = db.begin().await?;
+    let stored = db::notes::upsert(&mut *tx, user.id, target_id, note, nickname).await?;
+    if stored.note.is_none() && stored.nickname.is_none() {
+        db::notes::delete_if_empty(&mut *tx, user.id, target_id).await?;
+    }
+    tx.commit().await?;
+
+    let event = Event::UserNoteUpdated(UserNoteUpdatedEvent {
+        user_id: user.id,
+        target_id,
+        note: stored.note.clone(),
+        nickname: stored.nickname.clone(),
+        timestamp: Utc::now(),
+    });
+    state.app_state.publish_best_effort(&event).await;
+    Ok(Json(stored))
+}
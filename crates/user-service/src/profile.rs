@@ -0,0 +1,351 @@
+//! Profiles: the account-wide profile, per-server overrides and connected
+//! accounts. Every profile change is published as `UserProfileUpdated`,
+//! listing the fields that changed.
+
+use std::sync::Arc;
+
+use axum::{extract::State, http::StatusCode};
+use chrono::Utc;
+use serde::{Deserialize, Deserializer};
+use utoipa::ToSchema;
+use utoipa_axum::{router::OpenApiRouter, routes};
+use uuid::Uuid;
+use validator::Validate;
+
+use common::{
+    auth::AuthUser,
+    db::{self, users::ProfileUpdate},
+    error::ErrorResponse,
+    events::UserProfileUpdatedEvent,
+    extract::{Json, Path},
+    models::{Connection, ConnectionProvider, PublicUser, ServerProfile, User, UserProfile},
+    AppError, AppState, Event, Result,
+};
+
+use crate::UserState;
+
+pub(crate) fn routes() -> OpenApiRouter<Arc<UserState>> {
+    OpenApiRouter::new()
+        .routes(routes!(get_current_user, update_profile))
+        .routes(routes!(get_user))
+        .routes(routes!(update_server_profile))
+        .routes(routes!(get_connections))
+        .routes(routes!(put_connection, delete_connection))
+}
+
+/// Omitted fields are left unchanged; `null` clears a nullable one.
+#[derive(Debug, Default, Deserialize, Validate, ToSchema)]
+pub struct UpdateProfileRequest {
+    #[validate(length(min = 1, max = 32))]
+    pub display_name: Option<String>,
+    #[serde(default, deserialize_with = "nullable")]
+    #[validate(length(max = 190))]
+    pub bio: Option<Option<String>>,
+    #[serde(default, deserialize_with = "nullable")]
+    #[validate(url, length(max = 2048))]
+    pub banner_url: Option<Option<String>>,
+    #[serde(default, deserialize_with = "nullable")]
+    #[validate(length(min = 1, max = 40))]
+    pub pronouns: Option<Option<String>>,
+    /// `0xRRGGBB` as an integer.
+    #[serde(default, deserialize_with = "nullable")]
+    #[validate(range(min = 0, max = 0xFFFFFF))]
+    pub accent_color: Option<Option<i32>>,
+}
+
+/// Omitted fields are left unchanged; `null` falls back to the account-wide
+/// value.
+#[derive(Debug, Default, Deserialize, Validate, ToSchema)]
+pub struct UpdateServerProfileRequest {
+    #[serde(default, deserialize_with = "nullable")]
+    #[validate(length(min = 1, max = 32))]
+    pub nickname: Option<Option<String>>,
+    #[serde(default, deserialize_with = "nullable")]
+    #[validate(url, length(max = 2048))]
+    pub avatar_url: Option<Option<String>>,
+}
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct PutConnectionRequest {
+    #[validate(length(min = 1, max = 100))]
+    pub account_name: String,
+    /// Whether other users see the connection on this profile.
+    #[serde(default = "default_visible")]
+    pub visible: bool,
+}
+
+fn default_visible() -> bool {
+    true
+}
+
+/// Distinguishes an explicit `null` (`Some(None)`) from an omitted field
+/// (`None`, via `#[serde(default)]`).
+pub(crate) fn nullable<'de, D, T>(deserializer: D) -> std::result::Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+#[utoipa::path(
+    get,
+    path = "/users/@me",
+    tag = "users",
+    security(("bearer" = [])),
+    responses(
+        (status = 200, body = User),
+    )
+)]
+async fn get_current_user(State(state): State<Arc<UserState>>, user: AuthUser) -> Result<Json<User>> {
+    db::users::find_by_id(state.app_state.db()?, user.id)
+        .await?
+        .map(Json)
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))
+}
+
+#[utoipa::path(
+    patch,
+    path = "/users/@me",
+    tag = "users",
+    request_body = UpdateProfileRequest,
+    security(("bearer" = [])),
+    responses(
+        (status = 200, body = User),
+        (status = 400, body = ErrorResponse),
+    )
+)]
+async fn update_profile(
+    State(state): State<Arc<UserState>>,
+    user: AuthUser,
+    Json(payload): Json<UpdateProfileRequest>,
+) -> Result<Json<User>> {
+    payload.validate()?;
+
+    let update = ProfileUpdate {
+        display_name: payload.display_name.as_deref(),
+        bio: payload.bio.as_ref().map(Option::as_deref),
+        banner_url: payload.banner_url.as_ref().map(Option::as_deref),
+        pronouns: payload.pronouns.as_ref().map(Option::as_deref),
+        accent_color: payload.accent_color,
+        ..Default::default()
+    };
+    let updated = db::users::update_profile(state.app_state.db()?, user.id, &update)
+        .await?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+
+    let changed = [
+        ("display_name", update.display_name.is_some()),
+        ("bio", update.bio.is_some()),
+        ("banner_url", update.banner_url.is_some()),
+        ("pronouns", update.pronouns.is_some()),
+        ("accent_color", update.accent_color.is_some()),
+    ]
+    .into_iter()
+    .filter_map(|(field, changed)| changed.then_some(field));
+    publish_profile(&state.app_state, &updated, changed).await;
+    Ok(Json(updated))
+}
+
+#[utoipa::path(
+    get,
+    path = "/users/{id}",
+    tag = "users",
+    params(("id" = Uuid, Path, description = "User id")),
+    security(("bearer" = [])),
+    responses(
+        (status = 200, body = UserProfile),
+        (status = 404, body = ErrorResponse),
+    )
+)]
+async fn get_user(
+    State(state): State<Arc<UserState>>,
+    viewer: AuthUser,
+    Path(user_id): Path<Uuid>,
+) -> Result<Json<UserProfile>> {
+    load_profile(&state, viewer.id, user_id).await.map(Json)
+}
+
+/// `user_id`'s profile as `viewer_id` sees it.
+pub(crate) async fn load_profile(state: &UserState, viewer_id: Uuid, user_id: Uuid) -> Result<UserProfile> {
+    let not_found = || AppError::NotFound("User not found".to_string());
+    // Someone who blocked the viewer is indistinguishable from no one.
+    if state.blocks.has_blocked(user_id, viewer_id).await? {
+        return Err(not_found());
+    }
+    let db = state.app_state.db()?;
+    let user = db::users::find_by_id(db, user_id).await?.ok_or_else(not_found)?;
+    let connections = db::connections::list_for_user(db, user_id, user_id != viewer_id).await?;
+    let note = if user_id == viewer_id { None } else { db::notes::find(db, viewer_id, user_id).await? };
+
+    Ok(UserProfile {
+        user: PublicUser {
+            id: user.id,
+            username: user.username,
+            display_name: user.display_name,
+            avatar_url: user.avatar_url,
+        },
+        bio: user.bio,
+        banner_url: user.banner_url,
+        pronouns: user.pronouns,
+        accent_color: user.accent_color,
+        connections,
+        note: note.as_ref().and_then(|n| n.note.clone()),
+        nickname: note.and_then(|n| n.nickname),
+    })
+}
+
+#[utoipa::path(
+    patch,
+    path = "/users/@me/servers/{id}/profile",
+    tag = "users",
+    params(("id" = Uuid, Path, description = "Server id")),
+    request_body = UpdateServerProfileRequest,
+    security(("bearer" = [])),
+    responses(
+        (status = 200, body = ServerProfile),
+        (status = 400, body = ErrorResponse),
+        (status = 404, description = "Not a member of this server", body = ErrorResponse),
+    )
+)]
+async fn update_server_profile(
+    State(state): State<Arc<UserState>>,
+    user: AuthUser,
+    Path(server_id): Path<Uuid>,
+    Json(payload): Json<UpdateServerProfileRequest>,
+) -> Result<Json<ServerProfile>> {
+    payload.validate()?;
+
+    let nickname = payload.nickname.as_ref().map(Option::as_deref);
+    let avatar_url = payload.avatar_url.as_ref().map(Option::as_deref);
+    let profile = db::servers::update_member_profile(state.app_state.db()?, server_id, user.id, nickname, avatar_url)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Not a member of this server".to_string()))?;
+
+    let changed: Vec<String> = [("nickname", nickname.is_some()), ("avatar_url", avatar_url.is_some())]
+        .into_iter()
+        .filter(|(_, changed)| *changed)
+        .map(|(field, _)| field.to_string())
+        .collect();
+
+    if !changed.is_empty() {
+        let event = UserProfileUpdatedEvent {
+            user_id: user.id,
+            server_id: Some(server_id),
+            changed,
+            display_name: None,
+            avatar_url: profile.avatar_url.clone(),
+            bio: None,
+            banner_url: None,
+            pronouns: None,
+            accent_color: None,
+            nickname: profile.nickname.clone(),
+            timestamp: Utc::now(),
+        };
+        state.app_state.publish_best_effort(&Event::UserProfileUpdated(event)).await;
+    }
+    Ok(Json(profile))
+}
+
+#[utoipa::path(
+    get,
+    path = "/users/@me/connections",
+    tag = "users",
+    security(("bearer" = [])),
+    responses(
+        (status = 200, body = Vec<Connection>),
+    )
+)]
+async fn get_connections(State(state): State<Arc<UserState>>, user: AuthUser) -> Result<Json<Vec<Connection>>> {
+    Ok(Json(db::connections::list_for_user(state.app_state.db()?, user.id, false).await?))
+}
+
+#[utoipa::path(
+    put,
+    path = "/users/@me/connections/{provider}",
+    tag = "users",
+    params(("provider" = ConnectionProvider, Path, description = "Platform")),
+    request_body = PutConnectionRequest,
+    security(("bearer" = [])),
+    responses(
+        (status = 200, body = Connection),
+        (status = 400, body = ErrorResponse),
+    )
+)]
+async fn put_connection(
+    State(state): State<Arc<UserState>>,
+    user: AuthUser,
+    Path(provider): Path<ConnectionProvider>,
+    Json(payload): Json<PutConnectionRequest>,
+) -> Result<Json<Connection>> {
+    payload.validate()?;
+    let connection =
+        db::connections::upsert(state.app_state.db()?, user.id, provider, &payload.account_name, payload.visible)
+            .await?;
+    Ok(Json(connection))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/users/@me/connections/{provider}",
+    tag = "users",
+    params(("provider" = ConnectionProvider, Path, description = "Platform")),
+    security(("bearer" = [])),
+    responses(
+        (status = 204, description = "Connection removed"),
+        (status = 404, body = ErrorResponse),
+    )
+)]
+async fn delete_connection(
+    State(state): State<Arc<UserState>>,
+    user: AuthUser,
+    Path(provider): Path<ConnectionProvider>,
+) -> Result<StatusCode> {
+    if !db::connections::delete(state.app_state.db()?, user.id, provider).await? {
+        return Err(AppError::NotFound("No connection for this provider".to_string()));
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Publishes an account-wide profile change, unless nothing changed.
+pub(crate) async fn publish_profile<'a>(state: &AppState, user: &User, changed: impl IntoIterator<Item = &'a str>) {
+    let changed: Vec<String> = changed.into_iter().map(str::to_string).collect();
+    if changed.is_empty() {
+        return;
+    }
+    let event = UserProfileUpdatedEvent {
+        user_id: user.id,
+        server_id: None,
+        changed,
+        display_name: Some(user.display_name.clone()),
+        avatar_url: user.avatar_url.clone(),
+        bio: user.bio.clone(),
+        banner_url: user.banner_url.clone(),
+        pronouns: user.pronouns.clone(),
+        accent_color: user.accent_color,
+        nickname: None,
+        timestamp: Utc::now(),
+    };
+    state.publish_best_effort(&Event::UserProfileUpdated(event)).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_null_clears_and_omitted_keeps() {
+        let request: UpdateProfileRequest =
+            serde_json::from_str(r#"{"bio": null, "accent_color": 16711680}"#).unwrap();
+        assert_eq!(request.bio, Some(None));
+        assert_eq!(request.pronouns, None);
+        assert_eq!(request.accent_color, Some(Some(0xFF0000)));
+        assert!(request.validate().is_ok());
+
+        let request: UpdateProfileRequest =
+            serde_json::from_str(r#"{"accent_color": 16777216, "banner_url": "not a url"}"#).unwrap();
+        let errors = request.validate().unwrap_err();
+        let fields = errors.field_errors();
+        assert!(fields.contains_key("accent_color") && fields.contains_key("banner_url"));
+    }
+}
@@ -0,0 +1,112 @@
+//! User search and the discoverability setting that governs it.
+
+use std::sync::Arc;
+
+use axum::extract::State;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+use common::{
+    auth::AuthUser,
+    db,
+    error::ErrorResponse,
+    extract::{Json, Query},
+    models::UserSearchResult,
+    AppError, Result,
+};
+
+use crate::UserState;
+
+const MIN_QUERY_LENGTH: usize = 2;
+const MAX_QUERY_LENGTH: usize = 32;
+const DEFAULT_PAGE_SIZE: i64 = 25;
+const MAX_PAGE_SIZE: i64 = 50;
+
+pub(crate) fn routes() -> OpenApiRouter<Arc<UserState>> {
+    OpenApiRouter::new()
+        .routes(routes!(search_users))
+        .routes(routes!(get_privacy, update_privacy))
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+struct SearchQuery {
+    /// Matched against username and display name, case-insensitively
+    q: String,
+    /// Page size, at most 50
+    limit: Option<i64>,
+    /// Results to skip
+    offset: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct PrivacySettings {
+    /// Whether non-friends can find this user in search.
+    pub discoverable: bool,
+}
+
+#[utoipa::path(
+    get,
+    path = "/users/search",
+    tag = "users",
+    params(SearchQuery),
+    security(("bearer" = [])),
+    responses(
+        (status = 200, body = Vec<UserSearchResult>),
+        (status = 400, body = ErrorResponse),
+    )
+)]
+async fn search_users(
+    State(state): State<Arc<UserState>>,
+    user: AuthUser,
+    Query(query): Query<SearchQuery>,
+) -> Result<Json<Vec<UserSearchResult>>> {
+    let q = query.q.trim();
+    let length = q.chars().count();
+    if !(MIN_QUERY_LENGTH..=MAX_QUERY_LENGTH).contains(&length) {
+        return Err(AppError::BadRequest(format!(
+            "Search query must be {} to {} characters",
+            MIN_QUERY_LENGTH, MAX_QUERY_LENGTH
+        )));
+    }
+    let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+    let offset = query.offset.unwrap_or(0).max(0);
+
+    let results = db::users::search(state.app_state.db()?, user.id, q, limit, offset).await?;
+    Ok(Json(results))
+}
+
+#[utoipa::path(
+    get,
+    path = "/users/@me/privacy",
+    tag = "users",
+    security(("bearer" = [])),
+    responses(
+        (status = 200, body = PrivacySettings),
+    )
+)]
+async fn get_privacy(State(state): State<Arc<UserState>>, user: AuthUser) -> Result<Json<PrivacySettings>> {
+    let discoverable = db::users::is_discoverable(state.app_state.db()?, user.id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+    Ok(Json(PrivacySettings { discoverable }))
+}
+
+#[utoipa::path(
+    put,
+    path = "/users/@me/privacy",
+    tag = "users",
+    request_body = PrivacySettings,
+    security(("bearer" = [])),
+    responses(
+        (status = 200, body = PrivacySettings),
+    )
+)]
+async fn update_privacy(
+    State(state): State<Arc<UserState>>,
+    user: AuthUser,
+    Json(settings): Json<PrivacySettings>,
+) -> Result<Json<PrivacySettings>> {
+    db::users::set_discoverable(state.app_state.db()?, user.id, settings.discoverable).await?;
+    Ok(Json(settings))
+}
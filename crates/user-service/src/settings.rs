@@ -0,0 +1,172 @@
+//! Synced client settings (see [`common::settings`]).
+//!
+//! Responses carry the document's version as a strong `ETag`. A `PATCH`
+//! with `If-Match` only applies if the stored version still matches, so
+//! concurrent sessions cannot silently overwrite each other; without it the
+//! patch applies to whatever is current. Every change is published as
+//! `UserSettingsUpdated` so a user's other sessions pick it up.
+
+use std::sync::Arc;
+
+use axum::{
+    extract::State,
+    http::{
+        header::{ETAG, IF_MATCH},
+        HeaderMap, HeaderValue,
+    },
+};
+use chrono::Utc;
+use serde_json::Value;
+use utoipa_axum::{router::OpenApiRouter, routes};
+use validator::Validate;
+
+use common::{
+    auth::AuthUser,
+    db,
+    error::ErrorResponse,
+    events::UserSettingsUpdatedEvent,
+    extract::Json,
+    settings::{merge_patch, UserSettings},
+    AppError, Event, Result,
+};
+
+use crate::UserState;
+
+pub(crate) fn routes() -> OpenApiRouter<Arc<UserState>> {
+    OpenApiRouter::new().routes(routes!(get_settings, update_settings))
+}
+
+type SettingsResponse = ([(axum::http::HeaderName, HeaderValue); 1], Json<UserSettings>);
+
+#[utoipa::path(
+    get,
+    path = "/users/@me/settings",
+    tag = "settings",
+    security(("bearer" = [])),
+    responses(
+        (status = 200, body = UserSettings, headers(("ETag" = String, description = "Settings version"))),
+    )
+)]
+async fn get_settings(State(state): State<Arc<UserState>>, user: AuthUser) -> Result<SettingsResponse> {
+    let (settings, version) = db::settings::find(state.app_state.db()?, user.id).await?;
+    Ok(([(ETAG, etag(version))], Json(settings)))
+}
+
+#[utoipa::path(
+    patch,
+    path = "/users/@me/settings",
+    tag = "settings",
+    request_body(content = Object, content_type = "application/merge-patch+json",
+                 description = "JSON merge patch; `null` resets a field to its default"),
+    params(("If-Match" = Option<String>, Header, description = "ETag the patch was based on")),
+    security(("bearer" = [])),
+    responses(
+        (status = 200, body = UserSettings, headers(("ETag" = String, description = "New settings version"))),
+        (status = 400, body = ErrorResponse),
+        (status = 412, description = "Settings changed since the given ETag", body = ErrorResponse),
+    )
+)]
+async fn update_settings(
+    State(state): State<Arc<UserState>>,
+    user: AuthUser,
+    headers: HeaderMap,
+    Json(patch): Json<Value>,
+) -> Result<SettingsResponse> {
+    if !patch.is_object() {
+        return Err(AppError::BadRequest("Settings patch must be a JSON object".to_string()));
+    }
+    let expected = headers
+        .get(IF_MATCH)
+        .map(|value| parse_if_match(value).ok_or_else(|| AppError::BadRequest("Malformed If-Match header".to_string())))
+        .transpose()?
+        .flatten();
+
+    let mut tx = state.app_state.db()?.begin().await?;
+    let (current, version) = db::settings::find_for_update(&mut tx, user.id).await?;
+    check_version(expected, version)?;
+    let settings = apply_patch(&current, &patch)?;
+
+    if settings == current {
+        return Ok(([(ETAG, etag(version))], Json(settings)));
+    }
+    let version = db::settings::save(&mut *tx, user.id, &settings).await?;
+    tx.commit().await?;
+
+    let event = Event::UserSettingsUpdated(UserSettingsUpdatedEvent {
+        user_id: user.id,
+        version,
+        settings: settings.clone(),
+        timestamp: Utc::now(),
+    });
+    state.app_state.publish_best_effort(&event).await;
+    Ok(([(ETAG, etag(version))], Json(settings)))
+}
+
+/// Fails with 412 when the client's `If-Match` names another version.
+fn check_version(expected: Option<i64>, version: i64) -> Result<()> {
+    if expected.is_some_and(|expected| expected != version) {
+        return Err(AppError::PreconditionFailed(format!(
+            "Settings are at version {}; refetch and retry",
+            version
+        )));
+    }
+    Ok(())
+}
+
+/// Merges `patch` into `current`; the result must still validate.
+fn apply_patch(current: &UserSettings, patch: &Value) -> Result<UserSettings> {
+    let mut document =
+        serde_json::to_value(current).map_err(|e| AppError::InternalServerError(e.to_string()))?;
+    merge_patch(&mut document, patch);
+    let settings: UserSettings =
+        serde_json::from_value(document).map_err(|e| AppError::Validation(e.to_string()))?;
+    settings.validate()?;
+    Ok(settings)
+}
+
+fn etag(version: i64) -> HeaderValue {
+    HeaderValue::from_str(&format!("\"{}\"", version)).expect("a quoted integer is a valid header value")
+}
+
+/// `Some(None)` for `*`, which matches any version.
+fn parse_if_match(value: &HeaderValue) -> Option<Option<i64>> {
+    let value = value.to_str().ok()?.trim();
+    if value == "*" {
+        return Some(None);
+    }
+    value.strip_prefix('"')?.strip_suffix('"')?.parse().ok().map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use common::settings::Theme;
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_check_version() {
+        assert!(check_version(None, 3).is_ok());
+        assert!(check_version(Some(3), 3).is_ok());
+        assert!(matches!(check_version(Some(2), 3), Err(AppError::PreconditionFailed(_))));
+    }
+
+    #[test]
+    fn test_apply_patch() {
+        let current = UserSettings::default();
+        let dark = apply_patch(&current, &json!({ "theme": "dark" })).unwrap();
+        assert_eq!(dark.theme, Theme::Dark);
+        assert_eq!(apply_patch(&dark, &json!({ "theme": null })).unwrap(), current);
+        assert_eq!(apply_patch(&current, &json!({})).unwrap(), current);
+        assert!(matches!(apply_patch(&current, &json!({ "theme": "sepia" })), Err(AppError::Validation(_))));
+        assert!(apply_patch(&current, &json!({ "locale": "x" })).is_err());
+    }
+
+    #[test]
+    fn test_parse_if_match() {
+        assert_eq!(parse_if_match(&etag(7)), Some(Some(7)));
+        assert_eq!(parse_if_match(&HeaderValue::from_static("*")), Some(None));
+        assert_eq!(parse_if_match(&HeaderValue::from_static("7")), None);
+        assert_eq!(parse_if_match(&HeaderValue::from_static("W/\"7\"")), None);
+    }
+}
@@ -0,0 +1,263 @@
+//! Avatar and banner uploads.
+//!
+//! Uploads are processed by [`images`](crate::images) and stored under a key
+//! derived from the upload's SHA-256, so identical uploads share objects and
+//! stored objects never change. The profile field is set to the media URL of
+//! the largest static variant; the other variants sit beside it.
+
+use std::{sync::Arc, time::Duration};
+
+use axum::{
+    extract::{DefaultBodyLimit, State},
+    http::header,
+    response::{IntoResponse, Redirect, Response},
+};
+use futures::future::try_join_all;
+use serde::Serialize;
+use utoipa::ToSchema;
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+use common::{
+    auth::AuthUser,
+    db::{self, users::ProfileUpdate},
+    error::ErrorResponse,
+    extract::{Json, Multipart, Path},
+    models::User,
+    AppError, Result,
+};
+
+use crate::{
+    images::{self, ImageSpec, AVATAR, BANNER},
+    profile::publish_profile,
+    UserState,
+};
+
+/// Longest a redirect to stored media stays valid.
+const MEDIA_URL_TTL: Duration = Duration::from_secs(3600);
+/// Multipart framing on top of the largest image.
+const BODY_LIMIT: usize = 11 * 1024 * 1024;
+
+pub(crate) fn routes() -> OpenApiRouter<Arc<UserState>> {
+    OpenApiRouter::new()
+        .routes(routes!(upload_avatar, delete_avatar))
+        .routes(routes!(upload_banner, delete_banner))
+        .routes(routes!(get_media))
+        .layer(DefaultBodyLimit::max(BODY_LIMIT))
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct UploadedImage {
+    pub hash: String,
+    pub animated: bool,
+    /// Media URL of every variant, e.g. `256.webp` and, when animated,
+    /// `256.gif`.
+    pub variants: Vec<String>,
+    pub user: User,
+}
+
+/// Multipart form with the image in a `file` field.
+#[derive(ToSchema)]
+#[allow(dead_code)]
+struct ImageUpload {
+    #[schema(value_type = String, format = Binary)]
+    file: Vec<u8>,
+}
+
+#[utoipa::path(
+    put,
+    path = "/users/@me/avatar",
+    tag = "users",
+    request_body(content = ImageUpload, content_type = "multipart/form-data"),
+    security(("bearer" = [])),
+    responses(
+        (status = 200, body = UploadedImage),
+        (status = 400, description = "Not a supported image, or over the size or frame limits", body = ErrorResponse),
+    )
+)]
+async fn upload_avatar(
+    State(state): State<Arc<UserState>>,
+    user: AuthUser,
+    multipart: Multipart,
+) -> Result<Json<UploadedImage>> {
+    upload(&state, &user, multipart, &AVATAR).await.map(Json)
+}
+
+#[utoipa::path(
+    delete,
+    path = "/users/@me/avatar",
+    tag = "users",
+    security(("bearer" = [])),
+    responses(
+        (status = 200, body = User),
+    )
+)]
+async fn delete_avatar(State(state): State<Arc<UserState>>, user: AuthUser) -> Result<Json<User>> {
+    set_image(&state, &user, &AVATAR, None).await.map(Json)
+}
+
+#[utoipa::path(
+    put,
+    path = "/users/@me/banner",
+    tag = "users",
+    request_body(content = ImageUpload, content_type = "multipart/form-data"),
+    security(("bearer" = [])),
+    responses(
+        (status = 200, body = UploadedImage),
+        (status = 400, description = "Not a supported image, or over the size or frame limits", body = ErrorResponse),
+    )
+)]
+async fn upload_banner(
+    State(state): State<Arc<UserState>>,
+    user: AuthUser,
+    multipart: Multipart,
+) -> Result<Json<UploadedImage>> {
+    upload(&state, &user, multipart, &BANNER).await.map(Json)
+}
+
+#[utoipa::path(
+    delete,
+    path = "/users/@me/banner",
+    tag = "users",
+    security(("bearer" = [])),
+    responses(
+        (status = 200, body = User),
+    )
+)]
+async fn delete_banner(State(state): State<Arc<UserState>>, user: AuthUser) -> Result<Json<User>> {
+    set_image(&state, &user, &BANNER, None).await.map(Json)
+}
+
+#[utoipa::path(
+    get,
+    path = "/media/{kind}/{hash}/{file}",
+    tag = "users",
+    params(
+        ("kind" = String, Path, description = "`avatars` or `banners`"),
+        ("hash" = String, Path, description = "Image hash"),
+        ("file" = String, Path, description = "Variant, e.g. `256.webp`"),
+    ),
+    responses(
+        (status = 302, description = "Redirect to a short-lived storage URL"),
+        (status = 404, body = ErrorResponse),
+    )
+)]
+async fn get_media(
+    State(state): State<Arc<UserState>>,
+    Path((kind, hash, file)): Path<(String, String, String)>,
+) -> Result<Response> {
+    let spec = [&AVATAR, &BANNER]
+        .into_iter()
+        .find(|spec| spec.kind == kind)
+        .ok_or_else(|| AppError::NotFound("Unknown media kind".to_string()))?;
+    if !valid_hash(&hash) || !valid_file(&file) {
+        return Err(AppError::NotFound("Media not found".to_string()));
+    }
+
+    let storage = state.app_state.storage()?;
+    let key = spec.key(&hash, &file);
+    if !storage.exists(&key).await? {
+        return Err(AppError::NotFound("Media not found".to_string()));
+    }
+    let url = storage.signed_url(&key, MEDIA_URL_TTL).await?;
+    // Objects never change, but the signed URL expires.
+    let cache_control = format!("private, max-age={}", MEDIA_URL_TTL.as_secs() / 2);
+    Ok(([(header::CACHE_CONTROL, cache_control)], Redirect::to(&url)).into_response())
+}
+
+/// A [`ProcessedImage::hash`](crate::images::ProcessedImage::hash): hex
+/// SHA-256, with `a_` in front for animations.
+fn valid_hash(hash: &str) -> bool {
+    let digest = hash.strip_prefix("a_").unwrap_or(hash);
+    digest.len() == 64 && digest.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+/// A variant name such as `256.webp`.
+fn valid_file(file: &str) -> bool {
+    file.split_once('.').is_some_and(|(width, extension)| {
+        !width.is_empty()
+            && width.bytes().all(|b| b.is_ascii_digit())
+            && matches!(extension, "webp" | "gif")
+    })
+}
+
+async fn upload(
+    state: &UserState,
+    user: &AuthUser,
+    Multipart(mut multipart): Multipart,
+    spec: &'static ImageSpec,
+) -> Result<UploadedImage> {
+    let mut bytes = None;
+    while let Some(field) = multipart.next_field().await.map_err(bad_multipart)? {
+        if field.name() == Some("file") {
+            bytes = Some(field.bytes().await.map_err(bad_multipart)?);
+            break;
+        }
+    }
+    let bytes = bytes.ok_or_else(|| AppError::BadRequest("Missing `file` field".to_string()))?;
+
+    let processed = tokio::task::spawn_blocking(move || images::process(&bytes, spec))
+        .await
+        .map_err(|e| AppError::InternalServerError(format!("Image processing panicked: {}", e)))??;
+
+    let storage = state.app_state.storage()?;
+    let hash = &processed.hash;
+    try_join_all(processed.variants.iter().map(|variant| async move {
+        let key = spec.key(hash, &variant.name);
+        storage.put(&key, variant.bytes.clone(), variant.content_type).await
+    }))
+    .await?;
+
+    let largest = format!("{}.webp", spec.widths[spec.widths.len() - 1]);
+    let url = media_url(spec, &processed.hash, &largest);
+    let user = set_image(state, user, spec, Some(&url)).await?;
+    Ok(UploadedImage {
+        variants: processed
+            .variants
+            .iter()
+            .map(|variant| media_url(spec, &processed.hash, &variant.name))
+            .collect(),
+        hash: processed.hash,
+        animated: processed.animated,
+        user,
+    })
+}
+
+/// Points the profile field for `spec` at `url`, or clears it.
+async fn set_image(state: &UserState, user: &AuthUser, spec: &ImageSpec, url: Option<&str>) -> Result<User> {
+    let (update, field) = if spec.kind == AVATAR.kind {
+        (ProfileUpdate { avatar_url: Some(url), ..Default::default() }, "avatar_url")
+    } else {
+        (ProfileUpdate { banner_url: Some(url), ..Default::default() }, "banner_url")
+    };
+    let updated = db::users::update_profile(state.app_state.db()?, user.id, &update)
+        .await?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+    publish_profile(&state.app_state, &updated, [field]).await;
+    Ok(updated)
+}
+
+fn media_url(spec: &ImageSpec, hash: &str, file: &str) -> String {
+    format!("/media/{}", spec.key(hash, file))
+}
+
+fn bad_multipart(err: axum::extract::multipart::MultipartError) -> AppError {
+    AppError::BadRequest(format!("Invalid multipart body: {}", err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_media_path_segments() {
+        let digest = "0123456789abcdef".repeat(4);
+        assert!(valid_hash(&digest) && valid_hash(&format!("a_{}", digest)));
+        for hash in ["", "a_", "..", &digest[1..], &digest.to_uppercase(), &format!("{}.", &digest[1..])] {
+            assert!(!valid_hash(hash), "{hash:?}");
+        }
+        assert!(valid_file("256.webp") && valid_file("1200.gif"));
+        for file in ["", ".webp", "256", "256.png", "../256.webp", "256.webp.gif"] {
+            assert!(!valid_file(file), "{file:?}");
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use reqwest::{Client, StatusCode};
+use serde_json::{json, Value};
+use test_support::{fixtures, TestStack};
+
+#[tokio::test]
+#[ignore = "requires Docker"]
+async fn test_block_ends_friendship_and_refuses_requests() -> anyhow::Result<()> {
+    let stack = TestStack::start().await?;
+    let service = stack.spawn(user_service::router).await?;
+    let http = Client::new();
+
+    let alice = fixtures::create_user(&stack, "alice").await?;
+    let bob = fixtures::create_user(&stack, "bob").await?;
+
+    for (from, to) in [(&alice, &bob), (&bob, &alice)] {
+        http.post(service.url("/users/@me/friends"))
+            .header("authorization", from.bearer())
+            .json(&json!({ "user_id": to.id() }))
+            .send()
+            .await?
+            .error_for_status()?;
+    }
+
+    let blocked = http
+        .post(service.url("/users/@me/blocked"))
+        .header("authorization", alice.bearer())
+        .json(&json!({ "user_id": bob.id() }))
+        .send()
+        .await?;
+    assert_eq!(blocked.status(), StatusCode::NO_CONTENT);
+
+    let friends: Value = http
+        .get(service.url("/users/@me/friends"))
+        .header("authorization", bob.bearer())
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(friends, json!([]));
+
+    let request = http
+        .post(service.url("/users/@me/friends"))
+        .header("authorization", bob.bearer())
+        .json(&json!({ "user_id": alice.id() }))
+        .send()
+        .await?;
+    assert_eq!(request.status(), StatusCode::FORBIDDEN);
+
+    let list: Value = http
+        .get(service.url("/users/@me/blocked"))
+        .header("authorization", alice.bearer())
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(list[0]["username"], "bob");
+
+    let unblocked = http
+        .delete(service.url(&format!("/users/@me/blocked/{}", bob.id())))
+        .header("authorization", alice.bearer())
+        .send()
+        .await?;
+    assert_eq!(unblocked.status(), StatusCode::NO_CONTENT);
+    Ok(())
+}
@@ -0,0 +1,117 @@
+use std::{
+    io::{Cursor, Read},
+    time::Duration,
+};
+
+use common::db::exports::Section;
+use reqwest::{Client, StatusCode};
+use serde_json::{json, Value};
+use test_support::{fixtures, TestStack};
+
+#[tokio::test]
+#[ignore = "requires Docker"]
+async fn test_one_export_in_flight_per_user() -> anyhow::Result<()> {
+    let stack = TestStack::start().await?;
+    let service = stack.spawn(user_service::router).await?;
+    let http = Client::new();
+
+    let user = fixtures::create_user(&stack, "exporter").await?;
+    let queued = http
+        .post(service.url("/users/@me/exports"))
+        .header("authorization", user.bearer())
+        .send()
+        .await?;
+    assert_eq!(queued.status(), StatusCode::ACCEPTED);
+    let queued: Value = queued.json().await?;
+    assert_eq!(queued["status"], "pending");
+    assert!(queued.get("object_key").is_none());
+
+    let again = http
+        .post(service.url("/users/@me/exports"))
+        .header("authorization", user.bearer())
+        .send()
+        .await?;
+    assert_eq!(again.status(), StatusCode::CONFLICT);
+
+    let listed: Value = http
+        .get(service.url("/users/@me/exports"))
+        .header("authorization", user.bearer())
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(listed[0]["id"], queued["id"]);
+    assert_eq!(listed[0]["download_url"], Value::Null);
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore = "requires Docker"]
+async fn test_export_runs_to_a_downloadable_archive() -> anyhow::Result<()> {
+    let stack = TestStack::start_with_storage().await?;
+    let service = stack.spawn(user_service::router).await?;
+    let http = Client::new();
+
+    let alice = fixtures::create_user(&stack, "alice").await?;
+    let bob = fixtures::create_user(&stack, "bob").await?;
+    http.post(service.url("/users/@me/friends"))
+        .header("authorization", bob.bearer())
+        .json(&json!({ "user_id": alice.id() }))
+        .send()
+        .await?
+        .error_for_status()?;
+
+    let queued: Value = http
+        .post(service.url("/users/@me/exports"))
+        .header("authorization", alice.bearer())
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    let export_url = service.url(&format!("/users/@me/exports/{}", queued["id"].as_str().unwrap()));
+
+    let mut export = Value::Null;
+    for _ in 0..60 {
+        export = http
+            .get(&export_url)
+            .header("authorization", alice.bearer())
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        if export["status"] != "pending" && export["status"] != "running" {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(500)).await;
+    }
+    assert_eq!(export["status"], "completed", "{}", export);
+
+    let archive = http
+        .get(export["download_url"].as_str().unwrap())
+        .send()
+        .await?
+        .error_for_status()?
+        .bytes()
+        .await?;
+    let mut zip = zip::ZipArchive::new(Cursor::new(archive))?;
+    assert_eq!(zip.len(), 1 + Section::ALL.len());
+
+    let mut profile = String::new();
+    zip.by_name("profile.json")?.read_to_string(&mut profile)?;
+    let profile: Value = serde_json::from_str(&profile)?;
+    assert_eq!(profile["user"]["username"], "alice");
+
+    for section in Section::ALL {
+        zip.by_name(section.file_name())?;
+    }
+    let mut relationships = String::new();
+    zip.by_name(Section::Relationships.file_name())?.read_to_string(&mut relationships)?;
+    let relationships: Vec<Value> = relationships.lines().map(serde_json::from_str).collect::<Result<_, _>>()?;
+    assert_eq!(relationships.len(), 1);
+    assert_eq!(relationships[0]["username"], "bob");
+    assert_eq!(relationships[0]["incoming"], true);
+    assert_eq!(relationships[0]["status"], "pending");
+    Ok(())
+}
@@ -0,0 +1,121 @@
+use std::time::Duration;
+
+use futures::StreamExt;
+use reqwest::{Client, StatusCode};
+use serde_json::{json, Value};
+use test_support::{fixtures, TestStack};
+
+#[tokio::test]
+#[ignore = "requires Docker"]
+async fn test_friend_request_lifecycle() -> anyhow::Result<()> {
+    let stack = TestStack::start().await?;
+    let service = stack.spawn(user_service::router).await?;
+    let mut events = stack.state.nats()?.subscribe("user.friend.>").await?;
+    let http = Client::new();
+
+    let alice = fixtures::create_user(&stack, "alice").await?;
+    let bob = fixtures::create_user(&stack, "bob").await?;
+    let carol = fixtures::create_user(&stack, "carol").await?;
+
+    // alice -> bob, then cancelled.
+    let sent: Value = http
+        .post(service.url("/users/@me/friends"))
+        .header("authorization", alice.bearer())
+        .json(&json!({ "username": "bob" }))
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(sent["status"], "pending");
+    let duplicate = http
+        .post(service.url("/users/@me/friends"))
+        .header("authorization", alice.bearer())
+        .json(&json!({ "user_id": bob.id() }))
+        .send()
+        .await?;
+    assert_eq!(duplicate.status(), StatusCode::CONFLICT);
+    let cancelled = http
+        .delete(service.url(&format!("/users/@me/friends/requests/{}", bob.id())))
+        .header("authorization", alice.bearer())
+        .send()
+        .await?;
+    assert_eq!(cancelled.status(), StatusCode::NO_CONTENT);
+
+    // carol -> alice, declined.
+    http.post(service.url("/users/@me/friends"))
+        .header("authorization", carol.bearer())
+        .json(&json!({ "user_id": alice.id() }))
+        .send()
+        .await?
+        .error_for_status()?;
+    let declined = http
+        .post(service.url(&format!("/users/@me/friends/requests/{}/decline", carol.id())))
+        .header("authorization", alice.bearer())
+        .send()
+        .await?;
+    assert_eq!(declined.status(), StatusCode::NO_CONTENT);
+
+    // bob -> alice, accepted.
+    http.post(service.url("/users/@me/friends"))
+        .header("authorization", bob.bearer())
+        .json(&json!({ "user_id": alice.id() }))
+        .send()
+        .await?
+        .error_for_status()?;
+    let requests: Value = http
+        .get(service.url("/users/@me/friends/requests"))
+        .header("authorization", alice.bearer())
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(requests["incoming"][0]["username"], "bob");
+    assert!(requests["incoming"][0].get("email").is_none());
+    assert_eq!(requests["outgoing"], json!([]));
+    let accepted = http
+        .post(service.url(&format!("/users/@me/friends/requests/{}/accept", bob.id())))
+        .header("authorization", alice.bearer())
+        .send()
+        .await?;
+    assert_eq!(accepted.status(), StatusCode::NO_CONTENT);
+
+    for user in [&alice, &bob] {
+        let friends: Value = http
+            .get(service.url("/users/@me/friends"))
+            .header("authorization", user.bearer())
+            .send()
+            .await?
+            .json()
+            .await?;
+        assert_eq!(friends.as_array().map(Vec::len), Some(1));
+    }
+
+    let removed = http
+        .delete(service.url(&format!("/users/@me/friends/{}", alice.id())))
+        .header("authorization", bob.bearer())
+        .send()
+        .await?;
+    assert_eq!(removed.status(), StatusCode::NO_CONTENT);
+
+    // Added and removed, each addressed to both sides.
+    let mut received = Vec::new();
+    for _ in 0..4 {
+        let message = tokio::time::timeout(Duration::from_secs(5), events.next())
+            .await?
+            .expect("subscription open");
+        let event: Value = serde_json::from_slice(&message.payload)?;
+        let user_id = event["user_id"].as_str().unwrap_or_default().to_string();
+        received.push((message.subject.to_string(), user_id));
+    }
+    received.sort();
+    let (a, b) = (alice.id().to_string(), bob.id().to_string());
+    let mut expected = vec![
+        ("user.friend.added".to_string(), a.clone()),
+        ("user.friend.added".to_string(), b.clone()),
+        ("user.friend.removed".to_string(), a),
+        ("user.friend.removed".to_string(), b),
+    ];
+    expected.sort();
+    assert_eq!(received, expected);
+    Ok(())
+}
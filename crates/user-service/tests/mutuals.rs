@@ -0,0 +1,93 @@
+use reqwest::Client;
+use serde_json::{json, Value};
+use test_support::{
+    fixtures::{self, TestUser},
+    RunningService, TestStack,
+};
+
+#[tokio::test]
+#[ignore = "requires Docker"]
+async fn test_profile_lists_only_shared_servers() -> anyhow::Result<()> {
+    let stack = TestStack::start().await?;
+    let service = stack.spawn(user_service::router).await?;
+    let http = Client::new();
+
+    let viewer = fixtures::create_user(&stack, "viewer").await?;
+    let target = fixtures::create_user(&stack, "target").await?;
+    let shared = fixtures::create_server(&stack, &viewer, "shared").await?;
+    fixtures::add_member(&stack, &shared, &target).await?;
+    fixtures::create_server(&stack, &target, "theirs").await?;
+
+    let profile: Value = http
+        .get(service.url(&format!("/users/{}/profile", target.id())))
+        .header("authorization", viewer.bearer())
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    assert_eq!(profile["username"], "target");
+    let servers = profile["mutual_servers"]
+        .as_array()
+        .cloned()
+        .unwrap_or_default();
+    assert_eq!(servers.len(), 1);
+    assert_eq!(servers[0]["name"], "shared");
+    assert_eq!(profile["mutual_friends"], Value::Array(vec![]));
+    Ok(())
+}
+
+async fn befriend(http: &Client, service: &RunningService, a: &TestUser, b: &TestUser) -> anyhow::Result<()> {
+    for (from, to) in [(a, b), (b, a)] {
+        http.post(service.url("/users/@me/friends"))
+            .header("authorization", from.bearer())
+            .json(&json!({ "user_id": to.id() }))
+            .send()
+            .await?
+            .error_for_status()?;
+    }
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore = "requires Docker"]
+async fn test_profile_hides_blocked_mutual_friends() -> anyhow::Result<()> {
+    let stack = TestStack::start().await?;
+    let service = stack.spawn(user_service::router).await?;
+    let http = Client::new();
+
+    let viewer = fixtures::create_user(&stack, "viewer").await?;
+    let target = fixtures::create_user(&stack, "target").await?;
+    let carol = fixtures::create_user(&stack, "carol").await?;
+    befriend(&http, &service, &viewer, &carol).await?;
+    befriend(&http, &service, &target, &carol).await?;
+
+    let profile_url = service.url(&format!("/users/{}/profile", target.id()));
+    let profile: Value = http
+        .get(&profile_url)
+        .header("authorization", viewer.bearer())
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    assert_eq!(profile["mutual_friends"][0]["username"], "carol");
+
+    // Still cached, but the block applies straight away.
+    http.post(service.url("/users/@me/blocked"))
+        .header("authorization", viewer.bearer())
+        .json(&json!({ "user_id": carol.id() }))
+        .send()
+        .await?
+        .error_for_status()?;
+    let profile: Value = http
+        .get(&profile_url)
+        .header("authorization", viewer.bearer())
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    assert_eq!(profile["mutual_friends"], Value::Array(vec![]));
+    Ok(())
+}
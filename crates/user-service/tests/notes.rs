@@ -0,0 +1,67 @@
+use std::time::Duration;
+
+use futures::StreamExt;
+use reqwest::{Client, StatusCode};
+use serde_json::{json, Value};
+use test_support::{fixtures, TestStack};
+
+#[tokio::test]
+#[ignore = "requires Docker"]
+async fn test_notes_are_private_and_nicknames_need_friendship() -> anyhow::Result<()> {
+    let stack = TestStack::start().await?;
+    let service = stack.spawn(user_service::router).await?;
+    let mut events = stack.state.nats()?.subscribe("user.note.updated").await?;
+    let http = Client::new();
+
+    let alice = fixtures::create_user(&stack, "alice").await?;
+    let bob = fixtures::create_user(&stack, "bob").await?;
+    let note_url = service.url(&format!("/users/@me/notes/{}", bob.id()));
+
+    let refused = http
+        .patch(&note_url)
+        .header("authorization", alice.bearer())
+        .json(&json!({ "nickname": "bobby" }))
+        .send()
+        .await?;
+    assert_eq!(refused.status(), StatusCode::BAD_REQUEST);
+
+    let stored: Value = http
+        .patch(&note_url)
+        .header("authorization", alice.bearer())
+        .json(&json!({ "note": "met at the meetup" }))
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    assert_eq!(stored["note"], "met at the meetup");
+    let event = tokio::time::timeout(Duration::from_secs(5), events.next()).await?.expect("note event");
+    let event: Value = serde_json::from_slice(&event.payload)?;
+    assert_eq!(event["user_id"], alice.id().to_string());
+
+    let profile_url = service.url(&format!("/users/{}", bob.id()));
+    let seen_by_alice: Value =
+        http.get(&profile_url).header("authorization", alice.bearer()).send().await?.json().await?;
+    assert_eq!(seen_by_alice["note"], "met at the meetup");
+    let own_view: Value = http
+        .get(service.url(&format!("/users/{}", alice.id())))
+        .header("authorization", bob.bearer())
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(own_view["note"], Value::Null);
+
+    let cleared: Value = http
+        .patch(&note_url)
+        .header("authorization", alice.bearer())
+        .json(&json!({ "note": null }))
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(cleared["note"], Value::Null);
+    let gone = http.get(&note_url).header("authorization", alice.bearer()).send().await?;
+    assert_eq!(gone.status(), StatusCode::NOT_FOUND);
+    Ok(())
+}
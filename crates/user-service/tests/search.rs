@@ -0,0 +1,41 @@
+use reqwest::Client;
+use serde_json::{json, Value};
+use test_support::{fixtures, TestStack};
+
+#[tokio::test]
+#[ignore = "requires Docker"]
+async fn test_search_ranks_acquaintances_and_respects_opt_out() -> anyhow::Result<()> {
+    let stack = TestStack::start().await?;
+    let service = stack.spawn(user_service::router).await?;
+    let http = Client::new();
+
+    let searcher = fixtures::create_user(&stack, "searcher").await?;
+    let stranger = fixtures::create_user(&stack, "marble").await?;
+    let colleague = fixtures::create_user(&stack, "marbles").await?;
+    let hidden = fixtures::create_user(&stack, "marbled").await?;
+
+    let server = fixtures::create_server(&stack, &searcher, "guild").await?;
+    fixtures::add_member(&stack, &server, &colleague).await?;
+
+    http.put(service.url("/users/@me/privacy"))
+        .header("authorization", hidden.bearer())
+        .json(&json!({ "discoverable": false }))
+        .send()
+        .await?
+        .error_for_status()?;
+
+    let results: Value = http
+        .get(service.url("/users/search?q=MARB"))
+        .header("authorization", searcher.bearer())
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    let results = results.as_array().cloned().unwrap_or_default();
+    let ids: Vec<_> = results.iter().map(|r| r["id"].as_str().unwrap_or_default().to_string()).collect();
+    assert_eq!(ids, [colleague.id().to_string(), stranger.id().to_string()]);
+    assert_eq!(results[0]["mutual_servers"], 1);
+    assert!(results.iter().all(|r| r.get("email").is_none()));
+    Ok(())
+}
@@ -0,0 +1,49 @@
+use reqwest::{Client, StatusCode};
+use serde_json::{json, Value};
+use test_support::{fixtures, TestStack};
+
+#[tokio::test]
+#[ignore = "requires Docker"]
+async fn test_settings_patch_requires_current_version() -> anyhow::Result<()> {
+    let stack = TestStack::start().await?;
+    let service = stack.spawn(user_service::router).await?;
+    let http = Client::new();
+    let alice = fixtures::create_user(&stack, "alice").await?;
+
+    let initial = http
+        .get(service.url("/users/@me/settings"))
+        .header("authorization", alice.bearer())
+        .send()
+        .await?;
+    assert_eq!(initial.headers()["etag"], "\"0\"");
+
+    let patched = http
+        .patch(service.url("/users/@me/settings"))
+        .header("authorization", alice.bearer())
+        .header("if-match", "\"0\"")
+        .json(&json!({ "theme": "dark", "notifications": { "sounds": false } }))
+        .send()
+        .await?;
+    assert_eq!(patched.headers()["etag"], "\"1\"");
+    let settings: Value = patched.json().await?;
+    assert_eq!(settings["theme"], "dark");
+    assert_eq!(settings["notifications"]["desktop"], true);
+
+    let stale = http
+        .patch(service.url("/users/@me/settings"))
+        .header("authorization", alice.bearer())
+        .header("if-match", "\"0\"")
+        .json(&json!({ "theme": "light" }))
+        .send()
+        .await?;
+    assert_eq!(stale.status(), StatusCode::PRECONDITION_FAILED);
+
+    let invalid = http
+        .patch(service.url("/users/@me/settings"))
+        .header("authorization", alice.bearer())
+        .json(&json!({ "theme": "neon" }))
+        .send()
+        .await?;
+    assert_eq!(invalid.status(), StatusCode::BAD_REQUEST);
+    Ok(())
+}
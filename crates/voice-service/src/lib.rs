@@ -0,0 +1,62 @@
+use axum::{http::StatusCode, Router};
+use std::sync::Arc;
+use utoipa_axum::routes;
+use common::{error::ErrorResponse, metrics, openapi, AppState, Config};
+
+pub fn router(_config: &Config, app_state: AppState) -> Router {
+    metrics::describe_gauge!("voice_participants", "Users connected to voice channels");
+
+    openapi::into_router(
+        openapi::service_router("voice-service")
+            .routes(routes!(join_voice))
+            .routes(routes!(leave_voice))
+            .routes(routes!(update_voice_state))
+            .routes(routes!(webrtc_signal))
+            .with_state(Arc::new(app_state)),
+    )
+}
+
+#[utoipa::path(
+    post,
+    path = "/voice/join",
+    tag = "voice",
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Joined; returns media server connection details"),
+        (status = 403, body = ErrorResponse),
+    )
+)]
+async fn join_voice() -> StatusCode { StatusCode::NOT_IMPLEMENTED }
+
+#[utoipa::path(
+    post,
+    path = "/voice/leave",
+    tag = "voice",
+    security(("bearer" = [])),
+    responses(
+        (status = 204, description = "Left the voice channel"),
+    )
+)]
+async fn leave_voice() -> StatusCode { StatusCode::NOT_IMPLEMENTED }
+
+#[utoipa::path(
+    patch,
+    path = "/voice/state",
+    tag = "voice",
+    security(("bearer" = [])),
+    responses(
+        (status = 204, description = "Mute/deafen state updated"),
+    )
+)]
+async fn update_voice_state() -> StatusCode { StatusCode::NOT_IMPLEMENTED }
+
+#[utoipa::path(
+    post,
+    path = "/voice/signal",
+    tag = "voice",
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "SDP answer or ICE candidates"),
+    )
+)]
+async fn webrtc_signal() -> StatusCode { StatusCode::NOT_IMPLEMENTED }
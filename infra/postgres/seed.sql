@@ -0,0 +1,5 @@
+-- Development seed data. Apply after migrations with `make db-seed`.
+
+-- Insert a default user
+INSERT INTO users (username, email, password_hash, display_name) 
+VALUES ('admin', 'admin@discord-clone.local', '$argon2id$v=19$m=19456,t=2,p=1$AAAAAAAAAAAAAAAAAAAAAA$BBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBB', 'Admin User');
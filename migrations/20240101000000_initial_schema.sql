@@ -1,5 +1,6 @@
 -- Discord Clone Database Schema
 -- PostgreSQL 16
+-- Initial schema. Never edit an applied migration; add a new one instead.
 
 -- Enable UUID extension
 CREATE EXTENSION IF NOT EXISTS "uuid-ossp";
@@ -270,8 +271,3 @@ CREATE TRIGGER update_channels_updated_at BEFORE UPDATE ON channels
 
 CREATE TRIGGER update_friendships_updated_at BEFORE UPDATE ON friendships
     FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
-
--- Sample Data (Optional, for development)
--- Insert a default user
-INSERT INTO users (username, email, password_hash, display_name) 
-VALUES ('admin', 'admin@discord-clone.local', '$argon2id$v=19$m=19456,t=2,p=1$AAAAAAAAAAAAAAAAAAAAAA$BBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBB', 'Admin User');
@@ -0,0 +1,39 @@
+-- Time-ordered UUIDv7 primary keys (see common::id).
+-- Rows created before this migration keep their random v4 ids.
+
+-- A v4 uuid with the Unix time in milliseconds written over its first 48
+-- bits and the version nibble switched from 4 to 7.
+CREATE OR REPLACE FUNCTION uuid_generate_v7() RETURNS UUID AS $$
+    SELECT encode(
+        set_bit(
+            set_bit(
+                overlay(uuid_send(gen_random_uuid())
+                        PLACING substring(int8send(floor(extract(epoch FROM clock_timestamp()) * 1000)::BIGINT) FROM 3)
+                        FROM 1 FOR 6),
+                52, 1),
+            53, 1),
+        'hex')::UUID;
+$$ LANGUAGE SQL VOLATILE;
+
+ALTER TABLE users ALTER COLUMN id SET DEFAULT uuid_generate_v7();
+ALTER TABLE servers ALTER COLUMN id SET DEFAULT uuid_generate_v7();
+ALTER TABLE server_members ALTER COLUMN id SET DEFAULT uuid_generate_v7();
+ALTER TABLE roles ALTER COLUMN id SET DEFAULT uuid_generate_v7();
+ALTER TABLE member_roles ALTER COLUMN id SET DEFAULT uuid_generate_v7();
+ALTER TABLE channels ALTER COLUMN id SET DEFAULT uuid_generate_v7();
+ALTER TABLE direct_messages ALTER COLUMN id SET DEFAULT uuid_generate_v7();
+ALTER TABLE messages ALTER COLUMN id SET DEFAULT uuid_generate_v7();
+ALTER TABLE message_attachments ALTER COLUMN id SET DEFAULT uuid_generate_v7();
+ALTER TABLE reactions ALTER COLUMN id SET DEFAULT uuid_generate_v7();
+ALTER TABLE voice_sessions ALTER COLUMN id SET DEFAULT uuid_generate_v7();
+ALTER TABLE friendships ALTER COLUMN id SET DEFAULT uuid_generate_v7();
+ALTER TABLE invites ALTER COLUMN id SET DEFAULT uuid_generate_v7();
+ALTER TABLE webhooks ALTER COLUMN id SET DEFAULT uuid_generate_v7();
+ALTER TABLE bans ALTER COLUMN id SET DEFAULT uuid_generate_v7();
+ALTER TABLE audit_logs ALTER COLUMN id SET DEFAULT uuid_generate_v7();
+
+-- Message history pages by id instead of created_at.
+DROP INDEX idx_messages_channel;
+DROP INDEX idx_messages_dm;
+CREATE INDEX idx_messages_channel ON messages(channel_id, id DESC);
+CREATE INDEX idx_messages_dm ON messages(dm_id, id DESC);
@@ -0,0 +1,11 @@
+-- Fuzzy user search (see db::users::search).
+
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+-- Users who opt out are only found by their friends.
+ALTER TABLE users ADD COLUMN discoverable BOOLEAN NOT NULL DEFAULT TRUE;
+
+-- Serve both prefix (LIKE 'abc%') and similarity (%) matches on the
+-- lowercased names the search compares against.
+CREATE INDEX idx_users_username_trgm ON users USING GIN (lower(username) gin_trgm_ops);
+CREATE INDEX idx_users_display_name_trgm ON users USING GIN (lower(display_name) gin_trgm_ops);
@@ -0,0 +1,20 @@
+-- Rich profiles (see user-service profile endpoints).
+
+ALTER TABLE users ADD COLUMN banner_url TEXT;
+ALTER TABLE users ADD COLUMN pronouns VARCHAR(40);
+-- 0xRRGGBB
+ALTER TABLE users ADD COLUMN accent_color INTEGER CHECK (accent_color BETWEEN 0 AND 16777215);
+
+-- Per-server overrides; nickname already exists.
+ALTER TABLE server_members ADD COLUMN avatar_url TEXT;
+
+-- Accounts on other platforms a user lists on their profile.
+CREATE TABLE user_connections (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v7(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    provider VARCHAR(20) NOT NULL,
+    account_name VARCHAR(100) NOT NULL,
+    visible BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE(user_id, provider)
+);
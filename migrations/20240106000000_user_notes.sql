@@ -0,0 +1,11 @@
+-- Private notes and nicknames one user keeps about another. Only the owner
+-- ever sees them; a row with neither is deleted.
+CREATE TABLE user_notes (
+    owner_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    target_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    note VARCHAR(256),
+    nickname VARCHAR(32),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (owner_id, target_id),
+    CHECK (owner_id <> target_id)
+);
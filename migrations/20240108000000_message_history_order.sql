@@ -0,0 +1,6 @@
+-- Message history pages on (created_at, id): messages from before the
+-- switch to UUIDv7 have random ids, so the id alone does not sort by time.
+DROP INDEX idx_messages_channel;
+DROP INDEX idx_messages_dm;
+CREATE INDEX idx_messages_channel ON messages(channel_id, created_at DESC, id DESC);
+CREATE INDEX idx_messages_dm ON messages(dm_id, created_at DESC, id DESC);
@@ -0,0 +1,2 @@
+-- Failed export jobs wait before they are picked up again.
+ALTER TABLE data_exports ADD COLUMN next_attempt_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP;